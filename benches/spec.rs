@@ -0,0 +1,100 @@
+//! Benchmarks of the per-request cost of `Spec::get_example`.
+//!
+//! Specs of increasing size are generated, and the same requests are
+//! served from each of them: the cost per request should not grow with
+//! the number of operations in the spec.
+use actix_web::test::TestRequest;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use openapi_mocker::openapi::spec::Spec;
+use serde_json::{json, Map, Value};
+
+/// The number of paths of the generated specs. Each path declares a GET
+/// and a PUT operation.
+const SIZES: [usize; 4] = [10, 100, 1_000, 5_000];
+
+/// Generate a spec with `size` templated paths, each declaring named
+/// examples, headers and a schema to generate values from.
+fn generate_spec(size: usize) -> Value {
+    let mut paths = Map::new();
+    for i in 0..size {
+        let operation = json!({
+            "parameters": [{"name": "id", "in": "path", "required": true, "schema": {"type": "integer"}}],
+            "responses": {
+                "200": {
+                    "description": "OK",
+                    "headers": {"x-rate-limit": {"schema": {"type": "integer"}, "example": 100}},
+                    "content": {
+                        "application/json": {
+                            "schema": {"$ref": "#/components/schemas/Resource"},
+                            "examples": {
+                                "default": {"value": {"id": 1, "name": format!("resource {}", i)}},
+                                "query:filter=1": {"value": {"id": 2, "name": "filtered"}},
+                                "header:x-tenant=a": {"value": {"id": 3, "name": "tenant"}}
+                            }
+                        }
+                    }
+                },
+                "404": {
+                    "description": "Not found",
+                    "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Error"}}}
+                }
+            }
+        });
+        let path = json!({"get": operation, "put": operation});
+        paths.insert(format!("/resources{}/{{id}}", i), path);
+    }
+
+    json!({
+        "openapi": "3.0.0",
+        "info": {"title": "Benchmark", "version": "1.0.0"},
+        "paths": paths,
+        "components": {
+            "schemas": {
+                "Resource": {
+                    "type": "object",
+                    "required": ["id", "name"],
+                    "properties": {"id": {"type": "integer"}, "name": {"type": "string"}}
+                },
+                "Error": {
+                    "type": "object",
+                    "required": ["code"],
+                    "properties": {"code": {"type": "integer"}, "message": {"type": "string"}}
+                }
+            }
+        }
+    })
+}
+
+/// Write a generated spec to a temporary file and load it.
+fn load_spec(size: usize) -> Spec {
+    let path = std::env::temp_dir().join(format!("openapi-mocker-bench-{}.json", size));
+    std::fs::write(&path, generate_spec(size).to_string()).expect("failed to write spec");
+    Spec::from_path(path.to_str().unwrap()).expect("failed to load spec")
+}
+
+fn bench_get_example(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_example");
+    for size in SIZES {
+        let spec = load_spec(size);
+        let path = format!("/resources{}/42", size / 2);
+
+        let req = TestRequest::with_uri(&path).to_http_request();
+        group.bench_with_input(BenchmarkId::new("default", size), &req, |b, req| {
+            b.iter(|| spec.get_example(req).unwrap())
+        });
+
+        let req = TestRequest::with_uri(&format!("{}?filter=1", path)).to_http_request();
+        group.bench_with_input(BenchmarkId::new("query", size), &req, |b, req| {
+            b.iter(|| spec.get_example(req).unwrap())
+        });
+
+        let req = TestRequest::with_uri("/missing/42").to_http_request();
+        group.bench_with_input(BenchmarkId::new("not_found", size), &req, |b, req| {
+            b.iter(|| spec.get_example(req).unwrap_err())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_get_example);
+criterion_main!(benches);
@@ -1,12 +1,15 @@
 //! # OpenAPI Mock Server
 //!
-//! `openapi-mocker` is a simple mock server for OpenAPI 3.0 specs.
+//! `openapi-mocker` is a simple mock server for OpenAPI 3.0 and 3.1 specs,
+//! and Swagger 2.0 ones.
 //! It can be used to quickly create a mock server for an OpenAPI spec.
 //!
 //! The server will respond with example responses defined in the spec.
-//! If no example is defined, it will respond with an empty JSON object.
-//! The server will respond with a 200 status code by default, but you can
-//! specify a different status code in the URL.
+//! If no example is defined, it will respond with a value generated from
+//! the response schema.
+//! The server will respond with the status code of the response the example
+//! was declared in, e.g. an example declared under a `401` response is
+//! returned with a 401 status code.
 //!
 //! ## Usage
 //! ```sh
@@ -15,6 +18,9 @@
 //! * `<spec>` - Path to the OpenAPI spec file
 //! * `[port]` - Port to bind the server to (default: 8080)
 //!
+//! Several specs can be served at once, each mounted under its own prefix
+//! or on its own port, e.g. `users.yaml@/users orders.yaml@:8081`.
+//!
 //! ## Example
 //! ```sh
 //! openapi-mocker tests/testdata/petstore.yaml
@@ -34,8 +40,42 @@ pub mod server;
 #[derive(Parser)]
 #[clap(version = "0.1.3", author = "Thiago Pacheco")]
 pub struct Args {
-    #[clap(index = 1)]
-    pub spec: PathBuf,
+    /// OpenAPI spec files, each mounted as <spec>, <spec>@/prefix, <spec>@:port or <spec>@:port/prefix
+    #[clap(index = 1, required = true, num_args = 1.., value_name = "SPEC")]
+    pub specs: Vec<server::mount::SpecMount>,
     #[clap(short, long, default_value = "8080")]
     pub port: Option<u16>,
+    /// Base path to mount the operations under, replacing the paths of the spec servers
+    #[clap(long)]
+    pub base_path: Option<String>,
+    /// Validate requests against the spec, responding 400 to invalid requests
+    #[clap(long)]
+    pub validate_requests: bool,
+    /// Validate responses against their schema, reporting mismatches as set by the mode
+    #[clap(long, value_enum, num_args = 0..=1, default_missing_value = "warn")]
+    pub validate_responses: Option<server::ResponseValidation>,
+    /// Maximum size of the request bodies, in bytes
+    #[clap(long, value_name = "BYTES", default_value_t = server::DEFAULT_MAX_BODY_SIZE)]
+    pub max_body_size: usize,
+    /// Add an x-mock-reason header to the responses, explaining which example was selected and why
+    #[clap(long)]
+    pub explain: bool,
+    /// Number of requests kept in the journal of the admin API
+    #[clap(long, default_value_t = server::journal::DEFAULT_JOURNAL_SIZE)]
+    pub journal_size: usize,
+    /// Forward the requests no named example matches to an upstream, recording its responses
+    #[clap(long, value_name = "URL")]
+    pub proxy_to: Option<String>,
+    /// File to record the upstream responses to, as a copy of the spec (default: <spec>.recorded.<ext>)
+    #[clap(long, value_name = "FILE")]
+    pub record_to: Option<PathBuf>,
+    /// Header naming the recorded examples of the requests carrying it, e.g. x-tenant
+    #[clap(long, value_name = "NAME")]
+    pub record_header: Vec<String>,
+    /// Reload the spec when its file changes
+    #[clap(long)]
+    pub watch: bool,
+    /// Serve the collections of the spec from an in-memory store, seeded with their examples
+    #[clap(long)]
+    pub stateful: bool,
 }
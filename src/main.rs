@@ -1,23 +1,110 @@
 use actix_web::{web, App, HttpServer};
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
 use openapi_mocker::{
-    openapi::spec::Spec,
-    server::{get_scope, AppState},
+    openapi::recorder::Recorder,
+    server::{
+        admin::get_admin_scope,
+        get_scope,
+        journal::Journal,
+        mount::{find_conflicts, MountedSpec},
+        proxy::Proxy,
+        reload::{watch, SpecSource, WATCH_INTERVAL},
+        AppState,
+    },
     Args,
 };
+use std::sync::Mutex;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     let args = Args::parse();
-    println!("Starting server with spec: {}", args.spec.display());
+    if args.proxy_to.is_some() && args.specs.len() > 1 {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--proxy-to records the responses of a single spec",
+            )
+            .exit();
+    }
 
     let port = args.port.unwrap_or(8080);
-    let spec = Spec::from_path(args.spec.to_str().unwrap_or("")).expect("Failed to load spec");
-    let data = web::Data::new(AppState { spec });
+    let specs: Vec<MountedSpec> = args
+        .specs
+        .iter()
+        .map(|mount| {
+            println!("Starting server with spec: {}", mount.path.display());
+            let source = SpecSource {
+                base_path: args.base_path.clone(),
+                prefix: mount.prefix.clone(),
+                ..SpecSource::new(&mount.path)
+            };
+            let spec = source
+                .load()
+                .unwrap_or_else(|error| panic!("Failed to load spec: {}", error));
+            if !spec.prefix().is_empty() {
+                println!("Mounted under {}", spec.prefix());
+            }
+            if !spec.base_paths().is_empty() {
+                println!("Serving under base paths: {}", spec.base_paths().join(", "));
+            }
+            MountedSpec {
+                source: Some(source),
+                port: mount.port,
+                ..MountedSpec::new(spec)
+            }
+        })
+        .collect();
+    for conflict in find_conflicts(&specs) {
+        println!("Warning: route conflict, {}", conflict);
+    }
 
-    let server = HttpServer::new(move || App::new().app_data(data.clone()).service(get_scope()))
-        .bind(("0.0.0.0", port))
-        .expect("Failed to bind to port");
+    let mut ports: Vec<u16> = specs
+        .iter()
+        .map(|mounted| mounted.port.unwrap_or(port))
+        .collect();
+    ports.sort();
+    ports.dedup();
+    let mut state = AppState {
+        validate_requests: args.validate_requests,
+        validate_responses: args.validate_responses,
+        explain: args.explain,
+        max_body_size: args.max_body_size,
+        journal: Mutex::new(Journal::new(args.journal_size)),
+        ..AppState::with_specs(specs)
+    };
+    if args.stateful {
+        state = state.stateful();
+        println!("Stateful mode enabled");
+    }
+    if let Some(upstream) = &args.proxy_to {
+        let path = args
+            .record_to
+            .clone()
+            .unwrap_or_else(|| Recorder::default_path(&args.specs[0].path));
+        println!("Proxying to {}, recording to {}", upstream, path.display());
+        let recorder = Recorder::for_spec(path, &state.specs[0].spec(), args.record_header);
+        state.proxy = Some(Proxy::new(upstream).recording(recorder));
+    }
+    let data = web::Data::new(state);
+    if args.watch {
+        println!("Watching the spec files for changes");
+        watch(data.clone(), WATCH_INTERVAL);
+    }
+    #[cfg(unix)]
+    openapi_mocker::server::reload::reload_on_hangup(data.clone());
+
+    let mut server = HttpServer::new(move || {
+        App::new()
+            .app_data(data.clone())
+            .service(get_admin_scope())
+            .service(get_scope())
+    });
+    for port in ports {
+        println!("Listening on port {}", port);
+        server = server
+            .bind(("0.0.0.0", port))
+            .expect("Failed to bind to port");
+    }
 
     server.run().await.expect("Failed to run server");
 
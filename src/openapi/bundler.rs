@@ -0,0 +1,355 @@
+//! Bundling of specs split across files.
+//!
+//! A spec may reference values of other files, e.g.
+//! `$ref: ./schemas/pet.yaml#/Pet`, which neither `oas3` nor the raw
+//! document helpers follow. The bundler loads the referenced files,
+//! relative to the file declaring the reference, and inlines the values
+//! they point to, so that the bundled document only holds local references.
+//!
+//! References to the spec file itself are kept as they are. A value
+//! referencing itself through its own content, such as a tree schema, is
+//! inlined once, and the nested reference points back to the inlined value.
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use serde_json::{json, Value};
+
+/// The reasons a spec cannot be bundled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BundleError {
+    /// A file cannot be read or parsed.
+    Read { path: PathBuf, error: String },
+    /// A reference points to a value that does not exist.
+    Unresolved {
+        /// The reference, as declared.
+        reference: String,
+        /// The file declaring the reference.
+        file: PathBuf,
+        /// The JSON pointer of the reference in the bundled document.
+        location: String,
+    },
+    /// References point to each other without ever reaching a value.
+    /// Holds the references of the cycle, in the order they are followed.
+    Cycle(Vec<String>),
+    /// A reference points to a remote URL, which is not fetched.
+    Remote { reference: String, file: PathBuf },
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::Read { path, error } => {
+                write!(f, "Failed to read {}: {}", path.display(), error)
+            }
+            BundleError::Unresolved {
+                reference,
+                file,
+                location,
+            } => write!(
+                f,
+                "Unresolved reference {} in {} at {}",
+                reference,
+                file.display(),
+                location
+            ),
+            BundleError::Cycle(references) => {
+                write!(f, "Reference cycle: {}", references.join(" -> "))
+            }
+            BundleError::Remote { reference, file } => write!(
+                f,
+                "Unsupported remote reference {} in {}",
+                reference,
+                file.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+/// Load a spec file and bundle the files it references.
+/// # Arguments
+/// * `path` - Path to the OpenAPI spec file (YAML or JSON)
+/// # Returns
+/// The bundled document, only holding local references
+/// # Errors
+/// Returns an error naming the reference if a referenced file cannot be
+/// read, a reference cannot be resolved, or references form a cycle.
+/// # Example
+/// ```rust
+/// use std::path::Path;
+/// use openapi_mocker::openapi::bundler::bundle;
+/// let document = bundle(Path::new("tests/testdata/bundle/openapi.yaml")).unwrap();
+/// let schema = &document["components"]["schemas"]["Pet"];
+/// assert_eq!(schema["required"][0], "id");
+/// ```
+pub fn bundle(path: &Path) -> Result<Value, BundleError> {
+    bundle_files(path).map(|(document, _)| document)
+}
+
+/// Load a spec file and bundle the files it references, see `bundle`.
+/// # Returns
+/// The bundled document, along with the paths of the files it was bundled
+/// from, the spec file first
+/// # Example
+/// ```rust
+/// use std::path::Path;
+/// use openapi_mocker::openapi::bundler::bundle_files;
+/// let (_, files) = bundle_files(Path::new("tests/testdata/bundle/openapi.yaml")).unwrap();
+/// assert!(files[0].ends_with("bundle/openapi.yaml"));
+/// assert!(files.iter().any(|file| file.ends_with("schemas/pet.yaml")));
+/// ```
+pub fn bundle_files(path: &Path) -> Result<(Value, Vec<PathBuf>), BundleError> {
+    let mut bundler = Bundler {
+        root: file_key(path),
+        files: HashMap::new(),
+    };
+    let root = bundler.root.clone();
+    let document = bundler.load(&root)?;
+    let document = bundler.walk(&document, &root, String::new(), &mut Vec::new())?;
+
+    let mut files: Vec<PathBuf> = bundler.files.into_keys().filter(|f| *f != root).collect();
+    files.sort();
+    files.insert(0, root);
+    Ok((document, files))
+}
+
+/// The value a reference resolves to.
+enum Target {
+    /// A value of the spec file, referenced by its JSON pointer.
+    Local(String),
+    /// A value of another file.
+    External {
+        file: PathBuf,
+        pointer: String,
+        value: Value,
+    },
+}
+
+/// A value being inlined, to detect the values nested in themselves.
+struct Inlined {
+    file: PathBuf,
+    pointer: String,
+    /// The JSON pointer of the inlined value in the bundled document.
+    location: String,
+}
+
+struct Bundler {
+    root: PathBuf,
+    /// The loaded files, by path. They are shared rather than cloned, since
+    /// every reference looks its file up.
+    files: HashMap<PathBuf, Rc<Value>>,
+}
+
+impl Bundler {
+    fn load(&mut self, path: &Path) -> Result<Rc<Value>, BundleError> {
+        if let Some(document) = self.files.get(path) {
+            return Ok(Rc::clone(document));
+        }
+        let read_error = |error: String| BundleError::Read {
+            path: path.to_path_buf(),
+            error,
+        };
+        let file = std::fs::File::open(path).map_err(|e| read_error(e.to_string()))?;
+        let document: Value =
+            serde_yaml::from_reader(file).map_err(|e| read_error(e.to_string()))?;
+        let document = Rc::new(document);
+        self.files.insert(path.to_path_buf(), Rc::clone(&document));
+        Ok(document)
+    }
+
+    /// Copy a value of a file into the bundled document, inlining the
+    /// values of other files it references.
+    /// # Arguments
+    /// * `value` - The value to copy
+    /// * `file` - The file the value is declared in
+    /// * `location` - The JSON pointer of the value in the bundled document
+    /// * `stack` - The values being inlined, outermost first
+    fn walk(
+        &mut self,
+        value: &Value,
+        file: &Path,
+        location: String,
+        stack: &mut Vec<Inlined>,
+    ) -> Result<Value, BundleError> {
+        if let Some(reference) = value.get("$ref").and_then(Value::as_str) {
+            return match self.follow(reference, file, &location)? {
+                Target::Local(_) if file == self.root && reference.starts_with('#') => {
+                    Ok(value.clone())
+                }
+                Target::Local(pointer) => Ok(json!({ "$ref": format!("#{}", pointer) })),
+                Target::External {
+                    file,
+                    pointer,
+                    value,
+                } => {
+                    let inlined = stack
+                        .iter()
+                        .find(|inlined| inlined.file == file && inlined.pointer == pointer);
+                    if let Some(inlined) = inlined {
+                        return Ok(json!({ "$ref": format!("#{}", inlined.location) }));
+                    }
+                    stack.push(Inlined {
+                        file: file.clone(),
+                        pointer,
+                        location: location.clone(),
+                    });
+                    let value = self.walk(&value, &file, location, stack);
+                    stack.pop();
+                    value
+                }
+            };
+        }
+
+        match value {
+            Value::Object(object) => {
+                let mut bundled = serde_json::Map::new();
+                for (key, value) in object {
+                    let location = format!("{}/{}", location, escape(key));
+                    bundled.insert(key.clone(), self.walk(value, file, location, stack)?);
+                }
+                Ok(Value::Object(bundled))
+            }
+            Value::Array(values) => values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| self.walk(value, file, format!("{}/{}", location, i), stack))
+                .collect::<Result<Vec<Value>, BundleError>>()
+                .map(Value::Array),
+            value => Ok(value.clone()),
+        }
+    }
+
+    /// Follow a reference, and the references it points to, to a value.
+    /// # Arguments
+    /// * `reference` - The reference, e.g. `./schemas/pet.yaml#/Pet`
+    /// * `file` - The file declaring the reference
+    /// * `location` - The JSON pointer of the reference in the bundled document
+    fn follow(
+        &mut self,
+        reference: &str,
+        file: &Path,
+        location: &str,
+    ) -> Result<Target, BundleError> {
+        let mut reference = reference.to_string();
+        let mut file = file.to_path_buf();
+        let mut followed: Vec<(PathBuf, String)> = Vec::new();
+        let mut local: Option<String> = None;
+        loop {
+            if reference.contains("://") {
+                return Err(BundleError::Remote { reference, file });
+            }
+            let (path, pointer) = reference.split_once('#').unwrap_or((&reference, ""));
+            let target = match path.is_empty() {
+                true => file.clone(),
+                false => file_key(&file.parent().unwrap_or(Path::new("")).join(path)),
+            };
+            let pointer = pointer.to_string();
+            if followed.contains(&(target.clone(), pointer.clone())) {
+                let mut references: Vec<String> = followed
+                    .iter()
+                    .map(|(file, pointer)| format!("{}#{}", file.display(), pointer))
+                    .collect();
+                references.push(format!("{}#{}", target.display(), pointer));
+                return Err(BundleError::Cycle(references));
+            }
+
+            let document = self.load(&target)?;
+            let Some(value) = document.pointer(&pointer) else {
+                return Err(BundleError::Unresolved {
+                    reference,
+                    file,
+                    location: location.to_string(),
+                });
+            };
+            if target == self.root && local.is_none() {
+                local = Some(pointer.clone());
+            }
+            followed.push((target.clone(), pointer.clone()));
+
+            match value.get("$ref").and_then(Value::as_str) {
+                Some(next) => {
+                    reference = next.to_string();
+                    file = target;
+                }
+                None => {
+                    return Ok(match local {
+                        Some(pointer) => Target::Local(pointer),
+                        None => Target::External {
+                            file: target,
+                            pointer,
+                            value: value.clone(),
+                        },
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// The key of a file in the loaded files: its canonical path, so that
+/// a file referenced through different relative paths is loaded once.
+fn file_key(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Escape a key as a JSON pointer token.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_inlines_external_refs() {
+        let document = bundle(Path::new("tests/testdata/bundle/openapi.yaml")).unwrap();
+        // Local references are kept
+        let schema = &document["paths"]["/pets"]["get"]["responses"]["200"]["content"]
+            ["application/json"]["schema"];
+        assert_eq!(schema["items"]["$ref"], "#/components/schemas/Pet");
+
+        let response = &document["components"]["responses"]["NotFound"];
+        assert_eq!(response["description"], "Not found");
+        let schema = &response["content"]["application/json"]["schema"];
+        assert_eq!(schema["properties"]["code"]["type"], "integer");
+        let examples = &document["paths"]["/pets/{petId}"]["get"]["responses"]["200"]["content"]
+            ["application/json"]["examples"];
+        assert_eq!(examples["/pets/1"]["value"]["name"], "doggie");
+
+        // A reference of another file back to the spec file is made local
+        let owner = &document["components"]["schemas"]["Pet"]["properties"]["owner"];
+        assert_eq!(owner["$ref"], "#/components/schemas/Owner");
+    }
+
+    #[test]
+    fn test_bundle_recursive_schema() {
+        let document = bundle(Path::new("tests/testdata/bundle/openapi.yaml")).unwrap();
+        let tree = &document["components"]["schemas"]["Tree"];
+        assert_eq!(tree["properties"]["value"]["type"], "string");
+        assert_eq!(
+            tree["properties"]["children"]["items"]["$ref"],
+            "#/components/schemas/Tree"
+        );
+    }
+
+    #[test]
+    fn test_bundle_errors() {
+        let error = bundle(Path::new("tests/testdata/bundle/unresolved.yaml")).unwrap_err();
+        assert!(matches!(
+            &error,
+            BundleError::Unresolved { reference, location, .. }
+                if reference == "./schemas/pet.yaml#/Missing"
+                    && location == "/components/schemas/Missing"
+        ));
+
+        let error = bundle(Path::new("tests/testdata/bundle/cycle.yaml")).unwrap_err();
+        assert!(matches!(error, BundleError::Cycle(references) if references.len() == 3));
+
+        let error = bundle(Path::new("tests/testdata/bundle/missing.yaml")).unwrap_err();
+        assert!(matches!(error, BundleError::Read { .. }));
+    }
+}
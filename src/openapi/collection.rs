@@ -0,0 +1,223 @@
+//! Inference of the collections of a spec, for the stateful mode.
+//!
+//! A collection is a pair of paths shaped as `/pets` and `/pets/{petId}`:
+//! the first lists and creates items, the second reads, updates and
+//! deletes a single item identified by the last segment of its path.
+use actix_web::http::{Method, StatusCode};
+use serde_json::Value;
+
+use super::document::resolve_ref;
+use super::index::{Index, OperationEntry};
+use super::response::{is_success_key, status_code};
+
+/// A collection inferred from the paths of a spec.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collection {
+    /// The path listing the items, e.g. `/pets`.
+    pub path: String,
+    /// The path of a single item, e.g. `/pets/{petId}`.
+    pub item_path: String,
+    /// The path parameter identifying an item, e.g. `petId`.
+    pub param: String,
+    /// The field of the items holding their identifier, e.g. `id`.
+    pub id_field: String,
+    /// The methods declared for the collection path, with their success status.
+    pub methods: Vec<(Method, StatusCode)>,
+    /// The methods declared for the item path, with their success status.
+    pub item_methods: Vec<(Method, StatusCode)>,
+    /// The items the collection starts with, taken from the examples.
+    pub seed: Vec<Value>,
+}
+
+/// Infer the collections of a spec from the shape of its paths.
+///
+/// Every path whose last segment is a plain template, such as
+/// `/pets/{petId}`, makes a collection with its parent path when the
+/// parent path is declared as well.
+///
+/// The collection is seeded with the objects of the named examples of
+/// the success responses of both `GET` operations: the arrays returned
+/// by the collection path, and the objects returned by the item path.
+/// Items are identified by the field named after the path parameter,
+/// or by `id`.
+pub fn infer_collections(index: &Index, document: &Value) -> Vec<Collection> {
+    let mut keys: Vec<&str> = index.paths().collect();
+    keys.sort_unstable();
+
+    keys.iter()
+        .filter_map(|item_path| {
+            let (path, segment) = item_path.rsplit_once('/')?;
+            let param = segment.strip_prefix('{')?.strip_suffix('}')?;
+            if path.is_empty() || param.contains(['{', '}']) || !keys.contains(&path) {
+                return None;
+            }
+
+            let seed = load_seed(index, path, item_path);
+            let id_field = load_id_field(index, document, item_path, param, &seed);
+            Some(Collection {
+                path: path.to_string(),
+                item_path: item_path.to_string(),
+                param: param.to_string(),
+                id_field: id_field.to_string(),
+                methods: load_methods(index, path),
+                item_methods: load_methods(index, item_path),
+                seed: dedup_items(seed, id_field),
+            })
+        })
+        .collect()
+}
+
+/// Get the methods declared for a path, with the status of their first
+/// success response.
+fn load_methods(index: &Index, key: &str) -> Vec<(Method, StatusCode)> {
+    index
+        .methods(key)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|method| {
+            let operation = index.operation(key, &method)?;
+            let status = success_key(operation)
+                .map(|key| status_code(key, true))
+                .unwrap_or(match method {
+                    Method::POST => StatusCode::CREATED,
+                    Method::DELETE => StatusCode::NO_CONTENT,
+                    _ => StatusCode::OK,
+                });
+            Some((method, status))
+        })
+        .collect()
+}
+
+fn success_key(operation: &OperationEntry) -> Option<&str> {
+    operation
+        .responses
+        .iter()
+        .map(|response| response.key.as_str())
+        .find(|key| is_success_key(key))
+}
+
+/// Collect the objects of the named examples returned by the `GET`
+/// operations of a collection.
+fn load_seed(index: &Index, path: &str, item_path: &str) -> Vec<Value> {
+    let examples = |key: &str| -> Vec<Value> {
+        let Some(operation) = index.operation(key, &Method::GET) else {
+            return Vec::new();
+        };
+        operation
+            .responses
+            .iter()
+            .filter(|response| is_success_key(&response.key))
+            .flat_map(|response| &response.content)
+            .flat_map(|content| &content.examples)
+            .map(|example| example.value.clone())
+            .collect()
+    };
+
+    let listed = examples(path).into_iter().flat_map(|value| match value {
+        Value::Array(items) => items,
+        _ => Vec::new(),
+    });
+    listed
+        .chain(examples(item_path))
+        .filter(Value::is_object)
+        .collect()
+}
+
+/// Pick the field identifying the items: the field named after the path
+/// parameter if the items or the item schema declare it, `id` otherwise.
+fn load_id_field<'a>(
+    index: &Index,
+    document: &Value,
+    item_path: &str,
+    param: &'a str,
+    seed: &[Value],
+) -> &'a str {
+    let schema = index
+        .operation(item_path, &Method::GET)
+        .and_then(|operation| success_key(operation).and_then(|key| operation.response(key)))
+        .and_then(|response| response.content.first())
+        .and_then(|content| content.schema.as_ref())
+        .and_then(|schema| resolve_ref(document, schema));
+    let declared = |field: &str| {
+        seed.iter().any(|item| item.get(field).is_some())
+            || schema
+                .and_then(|schema| schema.get("properties"))
+                .and_then(|properties| properties.get(field))
+                .is_some()
+    };
+
+    match declared(param) {
+        true => param,
+        false => "id",
+    }
+}
+
+/// Remove the items whose identifier is already taken by a previous item.
+fn dedup_items(items: Vec<Value>, id_field: &str) -> Vec<Value> {
+    let mut unique: Vec<Value> = Vec::new();
+    for item in items {
+        let taken = item
+            .get(id_field)
+            .is_some_and(|id| unique.iter().any(|other| other.get(id_field) == Some(id)));
+        if !taken {
+            unique.push(item);
+        }
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::document::load_document;
+
+    fn load_collections(path: &str) -> Vec<Collection> {
+        let spec = oas3::from_path(path).unwrap();
+        let document = load_document(path).unwrap();
+        infer_collections(&Index::build(&spec, &document).unwrap(), &document)
+    }
+
+    #[test]
+    fn test_infer_collections() {
+        let collections = load_collections("tests/testdata/petstore.yaml");
+        assert_eq!(collections.len(), 1);
+
+        let pets = &collections[0];
+        assert_eq!(pets.path, "/pets");
+        assert_eq!(pets.item_path, "/pets/{petId}");
+        assert_eq!(pets.param, "petId");
+        assert_eq!(pets.id_field, "id");
+        assert_eq!(
+            pets.methods,
+            vec![
+                (Method::GET, StatusCode::OK),
+                (Method::POST, StatusCode::CREATED)
+            ]
+        );
+        assert_eq!(
+            pets.item_methods,
+            vec![
+                (Method::GET, StatusCode::OK),
+                (Method::PUT, StatusCode::OK),
+                (Method::DELETE, StatusCode::NO_CONTENT)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collection_seed() {
+        let collections = load_collections("tests/testdata/petstore.yaml");
+        let names: Vec<&str> = collections[0]
+            .seed
+            .iter()
+            .map(|item| item["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["doggie", "kitty", "batty"]);
+    }
+
+    #[test]
+    fn test_no_collection_without_parent() {
+        let collections = load_collections("tests/testdata/routes.yaml");
+        assert!(collections.is_empty());
+    }
+}
@@ -0,0 +1,332 @@
+use serde_json::Value;
+
+/// The media type used when neither the spec nor the request
+/// name a concrete media type.
+const DEFAULT_MEDIA_TYPE: &str = "application/octet-stream";
+
+/// A media range of an `Accept` header, e.g. `application/*;q=0.5`.
+#[derive(Debug, Clone, PartialEq)]
+struct MediaRange {
+    kind: String,
+    subtype: String,
+    quality: f32,
+}
+
+impl MediaRange {
+    fn parse(range: &str) -> Option<Self> {
+        let mut params = range.split(';');
+        let (kind, subtype) = split_media_type(params.next()?)?;
+        let quality = params
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+            .and_then(|(_, value)| value.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        Some(Self {
+            kind,
+            subtype,
+            quality,
+        })
+    }
+
+    /// How specific the range is: `*/*` < `type/*` < `type/subtype`.
+    fn specificity(&self) -> u8 {
+        match (self.kind.as_str(), self.subtype.as_str()) {
+            ("*", _) => 0,
+            (_, "*") => 1,
+            _ => 2,
+        }
+    }
+
+    fn matches(&self, kind: &str, subtype: &str) -> bool {
+        let matches = |range: &str, value: &str| range == "*" || value == "*" || range == value;
+        matches(&self.kind, kind) && matches(&self.subtype, subtype)
+    }
+}
+
+/// Split a media type into its lowercase type and subtype,
+/// ignoring its parameters.
+fn split_media_type(media_type: &str) -> Option<(String, String)> {
+    let media_type = media_type.split(';').next()?.trim().to_lowercase();
+    let (kind, subtype) = media_type.split_once('/')?;
+    Some((kind.to_string(), subtype.to_string()))
+}
+
+/// Select the media type to respond with.
+/// # Arguments
+/// * `accept` - The `Accept` header of the request
+/// * `available` - The media types declared for the response, in order of preference
+/// # Returns
+/// The declared media type with the highest quality in the `Accept` header,
+/// or None if no declared media type is acceptable.
+/// # Example
+/// ```rust
+/// use openapi_mocker::openapi::content::negotiate;
+/// let available = ["application/json", "text/plain"];
+/// assert_eq!(negotiate("text/*", &available), Some("text/plain"));
+/// assert_eq!(negotiate("*/*", &available), Some("application/json"));
+/// assert_eq!(negotiate("application/xml", &available), None);
+/// ```
+pub fn negotiate<'a>(accept: &str, available: &[&'a str]) -> Option<&'a str> {
+    let ranges: Vec<MediaRange> = accept.split(',').filter_map(MediaRange::parse).collect();
+    let ranges = if ranges.is_empty() {
+        vec![MediaRange::parse("*/*")?]
+    } else {
+        ranges
+    };
+
+    let mut selected: Option<(&str, f32)> = None;
+    for media_type in available {
+        let Some((kind, subtype)) = split_media_type(media_type) else {
+            continue;
+        };
+        let quality = ranges
+            .iter()
+            .filter(|range| range.matches(&kind, &subtype))
+            .max_by_key(|range| range.specificity())
+            .map(|range| range.quality)
+            .unwrap_or(0.0);
+
+        if quality > 0.0 && selected.is_none_or(|(_, selected)| quality > selected) {
+            selected = Some((media_type, quality));
+        }
+    }
+    selected.map(|(media_type, _)| media_type)
+}
+
+/// Get the `Content-Type` for a negotiated media type.
+/// Declared wildcards such as `image/*` are replaced by the first concrete
+/// media type of the `Accept` header they match.
+pub fn content_type(accept: &str, media_type: &str) -> String {
+    let Some((kind, subtype)) = split_media_type(media_type) else {
+        return DEFAULT_MEDIA_TYPE.to_string();
+    };
+    if kind != "*" && subtype != "*" {
+        return media_type.to_string();
+    }
+
+    accept
+        .split(',')
+        .filter_map(MediaRange::parse)
+        .find(|range| range.specificity() == 2 && range.matches(&kind, &subtype))
+        .map(|range| format!("{}/{}", range.kind, range.subtype))
+        .unwrap_or(DEFAULT_MEDIA_TYPE.to_string())
+}
+
+/// Serialize an example value for a media type.
+///
+/// - JSON media types (`application/json`, `*+json`) are serialized as JSON.
+/// - String values are used as they are for any other media type.
+/// - XML media types (`application/xml`, `text/xml`, `*+xml`) are converted to XML.
+/// - `text/csv` converts arrays of objects or arrays to rows.
+/// - Any other value is serialized as JSON.
+/// # Example
+/// ```rust
+/// use openapi_mocker::openapi::content::serialize;
+/// let value = serde_json::json!({"id": 1});
+/// assert_eq!(serialize(&value, "application/json"), br#"{"id":1}"#);
+/// assert_eq!(serialize(&value, "application/xml"), b"<root><id>1</id></root>");
+/// ```
+pub fn serialize(value: &Value, media_type: &str) -> Vec<u8> {
+    let (kind, subtype) = split_media_type(media_type).unwrap_or_default();
+
+    if subtype == "json" || subtype.ends_with("+json") {
+        return serde_json::to_vec(value).unwrap_or_default();
+    }
+
+    if let Value::String(value) = value {
+        return value.as_bytes().to_vec();
+    }
+
+    if subtype == "xml" || subtype.ends_with("+xml") {
+        let mut xml = String::new();
+        write_xml("root", value, &mut xml);
+        return xml.into_bytes();
+    }
+
+    if kind == "text" && subtype == "csv" {
+        return to_csv(value).into_bytes();
+    }
+
+    serde_json::to_vec(value).unwrap_or_default()
+}
+
+/// Deserialize a body of a media type into a value, the reverse of
+/// `serialize`: JSON bodies are parsed, and any other body is kept as a
+/// string.
+/// # Example
+/// ```rust
+/// use openapi_mocker::openapi::content::deserialize;
+/// assert_eq!(deserialize(br#"{"id":1}"#, "application/json")["id"], 1);
+/// assert_eq!(deserialize(b"<id>1</id>", "application/xml"), "<id>1</id>");
+/// ```
+pub fn deserialize(body: &[u8], media_type: &str) -> Value {
+    let (_, subtype) = split_media_type(media_type).unwrap_or_default();
+    let text = || Value::String(String::from_utf8_lossy(body).to_string());
+
+    match subtype == "json" || subtype.ends_with("+json") {
+        true => serde_json::from_slice(body).unwrap_or_else(|_| text()),
+        false => text(),
+    }
+}
+
+fn write_xml(name: &str, value: &Value, xml: &mut String) {
+    match value {
+        Value::Array(items) if name == "root" => {
+            xml.push_str("<root>");
+            items.iter().for_each(|item| write_xml("item", item, xml));
+            xml.push_str("</root>");
+        }
+        // Arrays repeat the element of their property
+        Value::Array(items) => items.iter().for_each(|item| write_xml(name, item, xml)),
+        Value::Object(object) => {
+            xml.push_str(&format!("<{}>", name));
+            object
+                .iter()
+                .for_each(|(name, value)| write_xml(name, value, xml));
+            xml.push_str(&format!("</{}>", name));
+        }
+        Value::Null => xml.push_str(&format!("<{}/>", name)),
+        Value::String(value) => xml.push_str(&format!("<{0}>{1}</{0}>", name, escape_xml(value))),
+        value => xml.push_str(&format!("<{0}>{1}</{0}>", name, value)),
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn to_csv(value: &Value) -> String {
+    let rows = match value {
+        Value::Array(rows) => rows.clone(),
+        value => vec![value.clone()],
+    };
+
+    let mut columns: Vec<String> = Vec::new();
+    for row in &rows {
+        if let Value::Object(object) = row {
+            for column in object.keys() {
+                if !columns.contains(column) {
+                    columns.push(column.clone());
+                }
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+    if !columns.is_empty() {
+        lines.push(
+            columns
+                .iter()
+                .map(|c| csv_field(c))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+    }
+    for row in &rows {
+        let fields: Vec<String> = match row {
+            Value::Object(object) => columns
+                .iter()
+                .map(|column| object.get(column).map(csv_value).unwrap_or_default())
+                .collect(),
+            Value::Array(fields) => fields.iter().map(csv_value).collect(),
+            value => vec![csv_value(value)],
+        };
+        lines.push(fields.join(","));
+    }
+    lines.join("\n")
+}
+
+fn csv_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(value) => csv_field(value),
+        value => csv_field(&value.to_string()),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_negotiate_quality() {
+        let available = ["application/json", "application/xml"];
+        let accept = "application/json;q=0.5, application/xml";
+        assert_eq!(negotiate(accept, &available), Some("application/xml"));
+    }
+
+    #[test]
+    fn test_negotiate_wildcards() {
+        let available = ["text/plain", "application/xml"];
+        assert_eq!(
+            negotiate("application/*", &available),
+            Some("application/xml")
+        );
+        assert_eq!(negotiate("", &available), Some("text/plain"));
+        assert_eq!(negotiate("image/png", &["image/*"]), Some("image/*"));
+    }
+
+    #[test]
+    fn test_negotiate_most_specific_range() {
+        let available = ["application/json", "text/plain"];
+        let accept = "*/*;q=0.1, text/*;q=0, application/json;q=0.2";
+        assert_eq!(negotiate(accept, &available), Some("application/json"));
+        let accept = "*/*, text/plain;q=0";
+        assert_eq!(negotiate(accept, &["text/plain"]), None);
+    }
+
+    #[test]
+    fn test_negotiate_ignores_parameters() {
+        let available = ["application/json; charset=utf-8"];
+        assert_eq!(
+            negotiate("application/json", &available),
+            Some("application/json; charset=utf-8")
+        );
+    }
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(content_type("*/*", "text/plain"), "text/plain");
+        assert_eq!(content_type("image/png", "image/*"), "image/png");
+        assert_eq!(content_type("*/*", "*/*"), DEFAULT_MEDIA_TYPE);
+    }
+
+    #[test]
+    fn test_serialize_text() {
+        assert_eq!(serialize(&json!("hello"), "text/plain"), b"hello");
+        assert_eq!(serialize(&json!("hello"), "application/json"), b"\"hello\"");
+        assert_eq!(serialize(&json!({"a": 1}), "text/plain"), br#"{"a":1}"#);
+    }
+
+    #[test]
+    fn test_serialize_xml() {
+        let value = json!([{"id": 1, "name": "a & b", "tags": ["x", "y"]}]);
+        assert_eq!(
+            serialize(&value, "application/xml"),
+            b"<root><item><id>1</id><name>a &amp; b</name><tags>x</tags><tags>y</tags></item></root>"
+        );
+    }
+
+    #[test]
+    fn test_serialize_csv() {
+        let value = json!([{"id": 1, "name": "doggie"}, {"id": 2, "name": "kitty, the cat"}]);
+        assert_eq!(
+            serialize(&value, "text/csv"),
+            b"id,name\n1,doggie\n2,\"kitty, the cat\""
+        );
+    }
+}
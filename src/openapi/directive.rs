@@ -0,0 +1,157 @@
+//! Selection of the response and example by the client.
+//!
+//! A request can name the response and example to return, rather than
+//! being matched against the examples: a test exercising an error path
+//! asks for `404` instead of crafting a request the `404` example matches.
+//! The directive is read once per request, and wins over the examples
+//! matching the request and over the proxy.
+use actix_web::{http::header, web, HttpRequest};
+
+/// The header forcing the status key of the response.
+pub const STATUS_HEADER: &str = "x-mock-status";
+/// The header forcing the name of the example.
+pub const EXAMPLE_HEADER: &str = "x-mock-example";
+
+/// A directive of the client selecting the response and example to return,
+/// instead of matching the examples against the request.
+///
+/// It is read from, in order of precedence:
+/// - the `X-Mock-Status` and `X-Mock-Example` headers,
+/// - the `code` and `example` preferences of the `Prefer` header, e.g.
+///   `Prefer: code=404, example=notFound`,
+/// - the `__code` and `__example` query parameters.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Directive {
+    /// The key of the response, as declared in the spec, e.g. `404`.
+    pub code: Option<String>,
+    /// The name of the example.
+    pub example: Option<String>,
+}
+
+impl Directive {
+    /// Read the directive of a request.
+    /// # Returns
+    /// The directive, or None if the request selects neither a response
+    /// nor an example.
+    /// # Example
+    /// ```rust
+    /// use actix_web::test::TestRequest;
+    /// use openapi_mocker::openapi::directive::Directive;
+    /// let req = TestRequest::with_uri("/pets?__example=kitty")
+    ///     .insert_header(("prefer", "code=404, example=notFound"))
+    ///     .to_http_request();
+    /// let directive = Directive::from_request(&req).unwrap();
+    /// assert_eq!(directive.code.as_deref(), Some("404"));
+    /// assert_eq!(directive.example.as_deref(), Some("notFound"));
+    /// ```
+    pub fn from_request(req: &HttpRequest) -> Option<Self> {
+        let header = |name: &str| {
+            let value = req.headers().get(name)?.to_str().ok()?.trim();
+            (!value.is_empty()).then(|| value.to_string())
+        };
+        let prefer: Vec<(String, String)> = req
+            .headers()
+            .get_all(header::HeaderName::from_static("prefer"))
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(parse_prefer)
+            .collect();
+        let preference = |name: &str| {
+            prefer
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+        };
+        let query = web::Query::<Vec<(String, String)>>::from_query(req.query_string())
+            .map(|query| query.into_inner())
+            .unwrap_or_default();
+        let param = |name: &str| {
+            query
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+        };
+
+        let directive = Self {
+            code: header(STATUS_HEADER)
+                .or_else(|| preference("code"))
+                .or_else(|| param("__code")),
+            example: header(EXAMPLE_HEADER)
+                .or_else(|| preference("example"))
+                .or_else(|| param("__example")),
+        };
+        match directive {
+            Self {
+                code: None,
+                example: None,
+            } => None,
+            directive => Some(directive),
+        }
+    }
+
+    /// Describe the directive, e.g. `code=404, example=notFound`.
+    pub fn describe(&self) -> String {
+        let code = self.code.as_ref().map(|code| format!("code={}", code));
+        let example = self
+            .example
+            .as_ref()
+            .map(|name| format!("example={}", name));
+        code.into_iter()
+            .chain(example)
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// Parse the preferences of a `Prefer` header, e.g. `code=404, example=a`.
+/// Preferences are separated by commas, and their parameters, which are
+/// ignored, by semicolons.
+fn parse_prefer(prefer: &str) -> Vec<(String, String)> {
+    prefer
+        .split(',')
+        .filter_map(|preference| {
+            let preference = preference.split(';').next()?;
+            let (name, value) = preference.split_once('=')?;
+            let value = value.trim().trim_matches('"');
+            Some((name.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_parse_prefer() {
+        assert_eq!(
+            parse_prefer(r#"code=404, example="not found"; lenient, dynamic=true"#),
+            vec![
+                ("code".to_string(), "404".to_string()),
+                ("example".to_string(), "not found".to_string()),
+                ("dynamic".to_string(), "true".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_directive_precedence() {
+        let req = TestRequest::with_uri("/pets?__code=500&__example=a")
+            .insert_header(("prefer", "code=404"))
+            .insert_header(("x-mock-example", "b"))
+            .to_http_request();
+        let directive = Directive::from_request(&req).unwrap();
+        assert_eq!(directive.code.as_deref(), Some("404"));
+        assert_eq!(directive.example.as_deref(), Some("b"));
+        assert_eq!(directive.describe(), "code=404, example=b");
+
+        let req = TestRequest::with_uri("/pets?__code=500").to_http_request();
+        let directive = Directive::from_request(&req).unwrap();
+        assert_eq!(directive.code.as_deref(), Some("500"));
+
+        let req = TestRequest::with_uri("/pets?page=1")
+            .insert_header(("prefer", "return=minimal"))
+            .to_http_request();
+        assert!(Directive::from_request(&req).is_none());
+    }
+}
@@ -0,0 +1,99 @@
+//! Helpers to navigate the raw OpenAPI document.
+//!
+//! The `oas3` structures drop the fields they do not model, such as the
+//! `example` of a header or the vendor extensions (`x-*`) of an example.
+//! Those fields are read from the raw document instead.
+use serde_json::Value;
+
+/// Maximum number of `$ref`s followed when resolving a value,
+/// guarding against reference cycles.
+const MAX_REF_DEPTH: usize = 16;
+
+/// Load the raw document from an OpenAPI spec file (YAML or JSON).
+pub fn load_document(path: &str) -> Option<Value> {
+    let file = std::fs::File::open(path).ok()?;
+    serde_yaml::from_reader(file).ok()
+}
+
+/// Follow the local `$ref` of a value, if any, to the referenced value.
+/// Returns None if the reference cannot be resolved.
+pub fn resolve_ref<'a>(document: &'a Value, value: &'a Value) -> Option<&'a Value> {
+    let mut value = value;
+    for _ in 0..MAX_REF_DEPTH {
+        match value.get("$ref").and_then(Value::as_str) {
+            Some(ref_path) => value = document.pointer(ref_path.strip_prefix('#')?)?,
+            None => return Some(value),
+        }
+    }
+    None
+}
+
+/// Get the value at a path of unescaped tokens, following the `$ref`s
+/// found along the way.
+/// # Example
+/// ```rust
+/// use openapi_mocker::openapi::document::{get, load_document};
+/// let document = load_document("tests/testdata/petstore.yaml").unwrap();
+/// let operation = get(&document, &["paths", "/pets/{petId}", "get"]).unwrap();
+/// assert_eq!(operation["operationId"], "showPetById");
+/// ```
+pub fn get<'a>(document: &'a Value, tokens: &[&str]) -> Option<&'a Value> {
+    let mut value = resolve_ref(document, document)?;
+    for token in tokens {
+        value = resolve_ref(document, value.get(*token)?)?;
+    }
+    Some(value)
+}
+
+/// Convert a JSON value to a header value.
+/// Strings are used as they are, arrays are joined with commas,
+/// and any other value is serialized as JSON.
+pub fn header_value(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        Value::Array(values) => values
+            .iter()
+            .map(header_value)
+            .collect::<Vec<String>>()
+            .join(","),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_load_document() {
+        let document = load_document("tests/testdata/petstore.yaml").unwrap();
+        assert_eq!(document["openapi"], "3.0.0");
+    }
+
+    #[test]
+    fn test_get_follows_refs() {
+        let document = json!({
+            "paths": {"/pets": {"get": {"responses": {"200": {"$ref": "#/components/responses/Ok"}}}}},
+            "components": {"responses": {"Ok": {"description": "OK"}}}
+        });
+        let value = get(&document, &["paths", "/pets", "get", "responses", "200"]).unwrap();
+        assert_eq!(value["description"], "OK");
+    }
+
+    #[test]
+    fn test_resolve_ref_cycle() {
+        let document = json!({
+            "components": {"responses": {"A": {"$ref": "#/components/responses/B"}, "B": {"$ref": "#/components/responses/A"}}}
+        });
+        let value = json!({"$ref": "#/components/responses/A"});
+        assert!(resolve_ref(&document, &value).is_none());
+    }
+
+    #[test]
+    fn test_header_value() {
+        assert_eq!(header_value(&json!("abc")), "abc");
+        assert_eq!(header_value(&json!(100)), "100");
+        assert_eq!(header_value(&json!(["a", 1])), "a,1");
+    }
+}
@@ -0,0 +1,680 @@
+use oas3::spec::{ObjectOrReference, SchemaType};
+use oas3::Schema;
+use regex_syntax::hir::{Class, Hir, HirKind};
+use serde_json::{json, Map, Number, Value};
+
+/// Maximum nesting depth of generated values, counting the properties,
+/// items, compositions and references followed.
+/// Recursive schemas stop generating nested values past this depth.
+const MAX_DEPTH: usize = 8;
+
+/// Maximum number of repetitions generated for an unbounded pattern.
+const MAX_REPETITIONS: u32 = 32;
+
+/// Generate an example value from a schema.
+///
+/// The schema `example` is used when declared, otherwise the value is
+/// generated from the schema keywords. Objects and arrays are composed
+/// from the examples of their properties and items.
+/// # Arguments
+/// * `schema` - The schema to generate a value for
+/// * `spec` - The OpenAPI spec used to resolve `$ref`s to `components.schemas`
+/// # Returns
+/// A JSON value valid for the schema
+/// # Example
+/// ```rust
+/// use oas3::Schema;
+/// use openapi_mocker::openapi::generator::generate;
+/// let spec = oas3::from_path("tests/testdata/petstore.yaml").unwrap();
+/// let schema: Schema = serde_json::from_str(r#"{"type": "integer", "minimum": 5}"#).unwrap();
+/// assert_eq!(generate(&schema, &spec), serde_json::json!(5));
+/// ```
+pub fn generate(schema: &Schema, spec: &oas3::OpenApiV3Spec) -> Value {
+    Generator { spec }.generate(schema, 0)
+}
+
+/// Resolve a schema reference to `components.schemas`.
+/// Returns None if the reference cannot be resolved.
+pub fn resolve_schema(
+    schema: &ObjectOrReference<Schema>,
+    spec: &oas3::OpenApiV3Spec,
+) -> Option<Schema> {
+    resolve_schema_depth(schema, spec, 0)
+}
+
+fn resolve_schema_depth(
+    schema: &ObjectOrReference<Schema>,
+    spec: &oas3::OpenApiV3Spec,
+    depth: usize,
+) -> Option<Schema> {
+    match schema {
+        ObjectOrReference::Object(schema) => Some(schema.clone()),
+        ObjectOrReference::Ref { ref_path } if depth < MAX_DEPTH => ref_path
+            .strip_prefix("#/components/schemas/")
+            .and_then(|name| spec.components.as_ref()?.schemas.get(name))
+            .and_then(|schema| resolve_schema_depth(schema, spec, depth + 1)),
+        ObjectOrReference::Ref { .. } => None,
+    }
+}
+
+struct Generator<'a> {
+    spec: &'a oas3::OpenApiV3Spec,
+}
+
+impl Generator<'_> {
+    fn generate(&self, schema: &Schema, depth: usize) -> Value {
+        if let Some(example) = &schema.example {
+            return example.clone();
+        }
+
+        if let Some(default) = &schema.default {
+            return default.clone();
+        }
+
+        if let Some(value) = schema.enum_values.first() {
+            return enum_value(schema, value);
+        }
+
+        let composed =
+            !schema.all_of.is_empty() || !schema.one_of.is_empty() || !schema.any_of.is_empty();
+        if composed && depth >= MAX_DEPTH {
+            // A recursive composition, e.g. a oneOf referencing its schema
+            return Value::Null;
+        }
+
+        if !schema.all_of.is_empty() {
+            return self.generate_all_of(schema, depth);
+        }
+
+        if let Some(schema) = schema.one_of.first().or(schema.any_of.first()) {
+            return self.generate_ref(schema, depth + 1);
+        }
+
+        match schema_type(schema) {
+            Some(SchemaType::Boolean) => Value::Bool(true),
+            Some(SchemaType::Integer) => generate_integer(schema),
+            Some(SchemaType::Number) => generate_number(schema),
+            Some(SchemaType::String) => Value::String(generate_string(schema)),
+            Some(SchemaType::Array) => self.generate_array(schema, depth),
+            Some(SchemaType::Object) => self.generate_object(schema, depth),
+            None => Value::Null,
+        }
+    }
+
+    /// Generate a value for a schema or a reference, a reference counting
+    /// as one level of depth.
+    fn generate_ref(&self, schema: &ObjectOrReference<Schema>, depth: usize) -> Value {
+        match schema {
+            ObjectOrReference::Object(schema) => self.generate(schema, depth),
+            // Past the maximum depth, the reference is not resolved
+            ObjectOrReference::Ref { .. } => resolve_schema_depth(schema, self.spec, depth)
+                .map(|schema| self.generate(&schema, depth + 1))
+                .unwrap_or(Value::Null),
+        }
+    }
+
+    fn generate_all_of(&self, schema: &Schema, depth: usize) -> Value {
+        let own = Schema {
+            all_of: Vec::new(),
+            ..schema.clone()
+        };
+        let mut values = vec![self.generate(&own, depth)];
+        for schema in &schema.all_of {
+            values.push(self.generate_ref(schema, depth + 1));
+        }
+
+        let mut merged = Map::new();
+        for value in values {
+            match value {
+                Value::Object(object) => merged.extend(object),
+                Value::Null => {}
+                // allOf of non object schemas, the last value wins
+                value => return value,
+            }
+        }
+        Value::Object(merged)
+    }
+
+    fn generate_array(&self, schema: &Schema, depth: usize) -> Value {
+        if depth >= MAX_DEPTH {
+            return empty_value(schema, Value::Array(Vec::new()));
+        }
+
+        let mut len = schema.min_items.unwrap_or(0).max(1);
+        if schema.unique_items == Some(true) {
+            len = schema.min_items.unwrap_or(1);
+        }
+        if let Some(max_items) = schema.max_items {
+            len = len.min(max_items);
+        }
+
+        let item = match &schema.items {
+            Some(items) => self.generate_ref(items, depth + 1),
+            None => Value::String(generate_string(&Schema::default())),
+        };
+        if schema.unique_items != Some(true) || len < 2 {
+            return Value::Array((0..len).map(|_| item.clone()).collect());
+        }
+
+        // Unique items are varied, and the array is cut short at the first
+        // item that cannot be varied
+        let items = match &schema.items {
+            Some(items) => resolve_schema(items, self.spec).unwrap_or_default(),
+            None => Schema::default(),
+        };
+        let mut values = vec![item];
+        for index in 1..len {
+            match vary(&items, &values[0], index as usize) {
+                Some(value) if !values.contains(&value) => values.push(value),
+                _ => break,
+            }
+        }
+        Value::Array(values)
+    }
+
+    fn generate_object(&self, schema: &Schema, depth: usize) -> Value {
+        if depth >= MAX_DEPTH {
+            return empty_value(schema, Value::Object(Map::new()));
+        }
+
+        let mut object = Map::new();
+        for (name, property) in &schema.properties {
+            if schema.required.is_empty() || schema.required.contains(name) {
+                object.insert(name.clone(), self.generate_ref(property, depth + 1));
+            }
+        }
+
+        for name in &schema.required {
+            if !object.contains_key(name) {
+                let value = match &schema.additional_properties {
+                    Some(additional) => self.generate_ref(additional, depth + 1),
+                    None => Value::Null,
+                };
+                object.insert(name.clone(), value);
+            }
+        }
+        Value::Object(object)
+    }
+}
+
+/// Vary the value generated for a schema, for the items of an array with
+/// `uniqueItems`: the next enum value, or the value offset by the index.
+/// Returns None if the value cannot be varied within the schema.
+fn vary(schema: &Schema, value: &Value, index: usize) -> Option<Value> {
+    if !schema.enum_values.is_empty() {
+        return schema
+            .enum_values
+            .get(index)
+            .map(|value| enum_value(schema, value));
+    }
+
+    let (_, maximum) = bounds(schema, 1.0);
+    let step = schema
+        .multiple_of
+        .as_ref()
+        .and_then(Number::as_f64)
+        .filter(|step| *step > 0.0)
+        .unwrap_or(1.0);
+    match (schema_type(schema), value) {
+        (Some(SchemaType::Boolean), Value::Bool(value)) => {
+            (index == 1).then(|| Value::Bool(!value))
+        }
+        (Some(SchemaType::Integer), Value::Number(number)) => {
+            let varied = number.as_f64()? + step.ceil() * index as f64;
+            maximum
+                .is_none_or(|maximum| varied <= maximum)
+                .then(|| json!(varied as i64))
+        }
+        (Some(SchemaType::Number), Value::Number(number)) => {
+            let varied = number.as_f64()? + step * index as f64;
+            maximum
+                .is_none_or(|maximum| varied <= maximum)
+                .then(|| Number::from_f64(varied).map(Value::Number))?
+        }
+        (Some(SchemaType::String), Value::String(value))
+            if schema.pattern.is_none() && schema.format.is_none() =>
+        {
+            let varied = format!("{}{}", value, index);
+            let max_length = schema.max_length.unwrap_or(u64::MAX);
+            (varied.len() as u64 <= max_length).then_some(Value::String(varied))
+        }
+        _ => None,
+    }
+}
+
+/// The type of a schema, inferred from its keywords when not declared.
+fn schema_type(schema: &Schema) -> Option<SchemaType> {
+    if schema.schema_type.is_some() {
+        return schema.schema_type;
+    }
+
+    if !schema.properties.is_empty()
+        || !schema.required.is_empty()
+        || schema.additional_properties.is_some()
+    {
+        Some(SchemaType::Object)
+    } else if schema.items.is_some() {
+        Some(SchemaType::Array)
+    } else if schema.format.is_some() || schema.pattern.is_some() {
+        Some(SchemaType::String)
+    } else {
+        None
+    }
+}
+
+/// The value used when a nested value cannot be generated:
+/// null for nullable schemas, the given empty value otherwise.
+fn empty_value(schema: &Schema, empty: Value) -> Value {
+    if schema.nullable == Some(true) {
+        Value::Null
+    } else {
+        empty
+    }
+}
+
+/// Convert an enum value to the type of the schema.
+/// Enum values are always parsed as strings.
+fn enum_value(schema: &Schema, value: &str) -> Value {
+    match schema_type(schema) {
+        Some(SchemaType::Integer) | Some(SchemaType::Number) | Some(SchemaType::Boolean) => {
+            serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()))
+        }
+        _ => Value::String(value.to_string()),
+    }
+}
+
+/// The lower and upper bounds of a numeric schema, `step` apart from
+/// exclusive bounds.
+fn bounds(schema: &Schema, step: f64) -> (Option<f64>, Option<f64>) {
+    let minimum = schema.minimum.as_ref().and_then(Number::as_f64).map(|min| {
+        if schema.exclusive_minimum == Some(true) {
+            min + step
+        } else {
+            min
+        }
+    });
+    let maximum = schema.maximum.as_ref().and_then(Number::as_f64).map(|max| {
+        if schema.exclusive_maximum == Some(true) {
+            max - step
+        } else {
+            max
+        }
+    });
+    (minimum, maximum)
+}
+
+/// A number as close to zero as the bounds and `multipleOf` allow.
+fn generate_f64(schema: &Schema, step: f64) -> f64 {
+    let (minimum, maximum) = bounds(schema, step);
+    let mut value = 0.0;
+    if let Some(minimum) = minimum {
+        value = f64::max(value, minimum);
+    }
+    if let Some(maximum) = maximum {
+        value = f64::min(value, maximum);
+    }
+
+    if let Some(multiple_of) = schema.multiple_of.as_ref().and_then(Number::as_f64) {
+        if multiple_of > 0.0 {
+            let multiple = (value / multiple_of).ceil() * multiple_of;
+            value = match maximum {
+                Some(maximum) if multiple > maximum => (value / multiple_of).floor() * multiple_of,
+                _ => multiple,
+            };
+        }
+    }
+    value
+}
+
+fn generate_integer(schema: &Schema) -> Value {
+    let value = generate_f64(schema, 1.0);
+    Value::Number(Number::from(value.ceil() as i64))
+}
+
+fn generate_number(schema: &Schema) -> Value {
+    let value = generate_f64(schema, 1.0);
+    Number::from_f64(value)
+        .map(Value::Number)
+        .unwrap_or(Value::Null)
+}
+
+fn generate_string(schema: &Schema) -> String {
+    if let Some(value) = schema.pattern.as_deref().and_then(generate_pattern) {
+        return value;
+    }
+
+    let value = match schema.format.as_deref() {
+        Some("date-time") => "2024-01-01T00:00:00Z",
+        Some("date") => "2024-01-01",
+        Some("time") => "00:00:00",
+        Some("email") => "user@example.com",
+        Some("uuid") => "00000000-0000-4000-8000-000000000000",
+        Some("uri") | Some("url") => "https://example.com",
+        Some("hostname") => "example.com",
+        Some("ipv4") => "192.0.2.1",
+        Some("ipv6") => "2001:db8::1",
+        Some("byte") => "c3RyaW5n",
+        Some("password") => "password",
+        _ => "string",
+    };
+
+    let mut value = value.to_string();
+    if let Some(min_length) = schema.min_length {
+        while (value.len() as u64) < min_length {
+            value.push('a');
+        }
+    }
+    if let Some(max_length) = schema.max_length {
+        value.truncate(max_length as usize);
+    }
+    value
+}
+
+/// Generate a string matching a regular expression.
+/// Returns None if the pattern cannot be parsed.
+fn generate_pattern(pattern: &str) -> Option<String> {
+    let hir = regex_syntax::Parser::new().parse(pattern).ok()?;
+    let mut value = String::new();
+    write_hir(&hir, &mut value);
+    Some(value)
+}
+
+fn write_hir(hir: &Hir, value: &mut String) {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => {}
+        HirKind::Literal(literal) => value.push_str(&String::from_utf8_lossy(&literal.0)),
+        HirKind::Class(Class::Unicode(class)) => {
+            let ranges: Vec<(char, char)> = class
+                .ranges()
+                .iter()
+                .map(|range| (range.start(), range.end()))
+                .collect();
+            if let Some(c) = pick_char(&ranges) {
+                value.push(c);
+            }
+        }
+        HirKind::Class(Class::Bytes(class)) => {
+            let ranges: Vec<(char, char)> = class
+                .ranges()
+                .iter()
+                .map(|range| (range.start() as char, range.end() as char))
+                .collect();
+            if let Some(c) = pick_char(&ranges) {
+                value.push(c);
+            }
+        }
+        HirKind::Repetition(repetition) => {
+            for _ in 0..repetition.min.min(MAX_REPETITIONS) {
+                write_hir(&repetition.sub, value);
+            }
+        }
+        HirKind::Capture(capture) => write_hir(&capture.sub, value),
+        HirKind::Concat(hirs) => hirs.iter().for_each(|hir| write_hir(hir, value)),
+        HirKind::Alternation(hirs) => {
+            if let Some(hir) = hirs.first() {
+                write_hir(hir, value);
+            }
+        }
+    }
+}
+
+/// Pick a readable character from a character class,
+/// preferring letters and digits over symbols.
+fn pick_char(ranges: &[(char, char)]) -> Option<char> {
+    let contains = |c: char| ranges.iter().any(|(start, end)| *start <= c && c <= *end);
+    ['a', 'A', '0']
+        .into_iter()
+        .chain('!'..='~')
+        .find(|c| contains(*c))
+        .or_else(|| ranges.first().map(|(start, _)| *start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn spec() -> oas3::OpenApiV3Spec {
+        oas3::from_path("tests/testdata/petstore.yaml").unwrap()
+    }
+
+    fn schema(value: Value) -> Schema {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_generate_scalars() {
+        let spec = spec();
+        assert_eq!(
+            generate(&schema(json!({"type": "boolean"})), &spec),
+            json!(true)
+        );
+        assert_eq!(
+            generate(&schema(json!({"type": "integer"})), &spec),
+            json!(0)
+        );
+        assert_eq!(
+            generate(&schema(json!({"type": "number"})), &spec),
+            json!(0.0)
+        );
+        assert_eq!(
+            generate(&schema(json!({"type": "string"})), &spec),
+            json!("string")
+        );
+    }
+
+    #[test]
+    fn test_generate_example() {
+        let spec = spec();
+        let value = generate(
+            &schema(json!({"type": "string", "example": "doggie", "default": "kitty"})),
+            &spec,
+        );
+        assert_eq!(value, json!("doggie"));
+        let value = generate(
+            &schema(json!({
+                "type": "object",
+                "properties": {
+                    "id": {"type": "integer", "example": 7},
+                    "tags": {"type": "array", "items": {"type": "string", "example": "dog"}}
+                }
+            })),
+            &spec,
+        );
+        assert_eq!(value, json!({"id": 7, "tags": ["dog"]}));
+    }
+
+    #[test]
+    fn test_generate_format() {
+        let spec = spec();
+        let value = generate(&schema(json!({"type": "string", "format": "email"})), &spec);
+        assert_eq!(value, json!("user@example.com"));
+        let value = generate(&schema(json!({"type": "string", "format": "date"})), &spec);
+        assert_eq!(value, json!("2024-01-01"));
+    }
+
+    #[test]
+    fn test_generate_enum() {
+        let spec = spec();
+        let value = generate(
+            &schema(json!({"type": "string", "enum": ["b", "a"]})),
+            &spec,
+        );
+        assert_eq!(value, json!("b"));
+        let value = generate(
+            &schema(json!({"type": "integer", "enum": ["3", "4"]})),
+            &spec,
+        );
+        assert_eq!(value, json!(3));
+    }
+
+    #[test]
+    fn test_generate_bounds() {
+        let spec = spec();
+        let value = generate(&schema(json!({"type": "integer", "minimum": 10})), &spec);
+        assert_eq!(value, json!(10));
+        let value = generate(
+            &schema(json!({"type": "integer", "minimum": 10, "exclusiveMinimum": true})),
+            &spec,
+        );
+        assert_eq!(value, json!(11));
+        let value = generate(&schema(json!({"type": "integer", "maximum": -5})), &spec);
+        assert_eq!(value, json!(-5));
+        let value = generate(
+            &schema(json!({"type": "number", "minimum": 1, "multipleOf": 0.5})),
+            &spec,
+        );
+        assert_eq!(value, json!(1.0));
+    }
+
+    #[test]
+    fn test_generate_string_length() {
+        let spec = spec();
+        let value = generate(&schema(json!({"type": "string", "minLength": 10})), &spec);
+        assert_eq!(value, json!("stringaaaa"));
+        let value = generate(&schema(json!({"type": "string", "maxLength": 3})), &spec);
+        assert_eq!(value, json!("str"));
+    }
+
+    #[test]
+    fn test_generate_pattern() {
+        let spec = spec();
+        let pattern = r"^[A-Z]{3}-\d{4}(foo|bar)?$";
+        let value = generate(
+            &schema(json!({"type": "string", "pattern": pattern})),
+            &spec,
+        );
+        assert_eq!(value, json!("AAA-0000"));
+    }
+
+    #[test]
+    fn test_generate_array() {
+        let spec = spec();
+        let value = generate(
+            &schema(json!({"type": "array", "items": {"type": "integer"}, "minItems": 3})),
+            &spec,
+        );
+        assert_eq!(value, json!([0, 0, 0]));
+        let value = generate(
+            &schema(json!({"type": "array", "items": {"type": "integer"}, "maxItems": 0})),
+            &spec,
+        );
+        assert_eq!(value, json!([]));
+    }
+
+    #[test]
+    fn test_generate_unique_items() {
+        let spec = spec();
+        let unique = |items: Value| {
+            generate(
+                &schema(
+                    json!({"type": "array", "items": items, "minItems": 3, "uniqueItems": true}),
+                ),
+                &spec,
+            )
+        };
+        assert_eq!(unique(json!({"type": "integer"})), json!([0, 1, 2]));
+        assert_eq!(
+            unique(json!({"type": "integer", "multipleOf": 5, "maximum": 5})),
+            json!([0, 5])
+        );
+        assert_eq!(
+            unique(json!({"type": "string", "enum": ["a", "b", "c"]})),
+            json!(["a", "b", "c"])
+        );
+        assert_eq!(
+            unique(json!({"type": "string"})),
+            json!(["string", "string1", "string2"])
+        );
+        assert_eq!(unique(json!({"type": "boolean"})), json!([true, false]));
+        // An item that cannot be varied is not repeated
+        assert_eq!(
+            unique(json!({"type": "string", "format": "email"})),
+            json!(["user@example.com"])
+        );
+        assert_eq!(
+            unique(json!({"$ref": "#/components/schemas/Pet"})),
+            json!([{"id": 0, "name": "string"}])
+        );
+    }
+
+    #[test]
+    fn test_generate_object_required() {
+        let spec = spec();
+        let pet = resolve_schema(
+            &ObjectOrReference::Ref {
+                ref_path: "#/components/schemas/Pet".to_string(),
+            },
+            &spec,
+        )
+        .unwrap();
+        assert_eq!(generate(&pet, &spec), json!({"id": 0, "name": "string"}));
+    }
+
+    #[test]
+    fn test_generate_resolves_refs() {
+        let spec = spec();
+        let value = generate(
+            &schema(json!({"type": "array", "items": {"$ref": "#/components/schemas/Pet"}})),
+            &spec,
+        );
+        assert_eq!(value, json!([{"id": 0, "name": "string"}]));
+    }
+
+    #[test]
+    fn test_generate_max_depth() {
+        let spec = spec();
+        let generator = Generator { spec: &spec };
+        let value = generator.generate(&schema(json!({"type": "object"})), MAX_DEPTH);
+        assert_eq!(value, json!({}));
+        let value = generator.generate(
+            &schema(json!({"type": "object", "nullable": true})),
+            MAX_DEPTH,
+        );
+        assert_eq!(value, Value::Null);
+    }
+
+    #[test]
+    fn test_generate_recursive_composition() {
+        let spec: oas3::OpenApiV3Spec = serde_json::from_value(json!({
+            "openapi": "3.0.0",
+            "info": {"title": "Nodes", "version": "1.0.0"},
+            "paths": {},
+            "components": {"schemas": {
+                "Node": {"oneOf": [{"$ref": "#/components/schemas/Node"}, {"type": "string"}]},
+                "Tree": {"allOf": [
+                    {"$ref": "#/components/schemas/Tree"},
+                    {"properties": {"id": {"type": "integer"}}}
+                ]}
+            }}
+        }))
+        .unwrap();
+        let node = schema(json!({"$ref": "#/components/schemas/Node"}));
+        let value = generate(&schema(json!({"items": node, "type": "array"})), &spec);
+        assert_eq!(value, json!([null]));
+        let tree = schema(json!({"allOf": [{"$ref": "#/components/schemas/Tree"}]}));
+        assert_eq!(generate(&tree, &spec), json!({"id": 0}));
+    }
+
+    #[test]
+    fn test_generate_nullable() {
+        let spec = spec();
+        let value = generate(&schema(json!({"nullable": true})), &spec);
+        assert_eq!(value, Value::Null);
+    }
+
+    #[test]
+    fn test_generate_all_of() {
+        let spec = spec();
+        let value = generate(
+            &schema(json!({
+                "allOf": [
+                    {"$ref": "#/components/schemas/Pet"},
+                    {"properties": {"age": {"type": "integer", "minimum": 1}}}
+                ]
+            })),
+            &spec,
+        );
+        assert_eq!(value, json!({"id": 0, "name": "string", "age": 1}));
+    }
+}
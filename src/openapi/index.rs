@@ -0,0 +1,638 @@
+//! The precompiled index of a spec.
+//!
+//! The index is built once when the spec is loaded: the paths are compiled
+//! into a route trie, and the responses of every operation are resolved
+//! along with their headers, media types, named examples and generated
+//! values. Serving a request looks values up in the index, and never walks
+//! nor clones the spec.
+use std::collections::HashMap;
+
+use actix_web::http::Method;
+use oas3::spec::{MediaTypeExamples, ObjectOrReference, Operation, PathItem, Response, Server};
+use serde_json::Value;
+
+use super::document::{self, header_value, resolve_ref};
+use super::generator::{generate, resolve_schema};
+use super::matcher::Matcher;
+use super::request::load_parameters;
+use super::response::is_success_key;
+use super::router::{RouteMatch, Router};
+use super::version::normalize_schema;
+use super::webhook::{load_webhooks, Webhook};
+
+/// The precompiled index of a spec.
+#[derive(Debug, Default)]
+pub struct Index {
+    router: Router,
+    paths: HashMap<String, PathEntry>,
+    /// The base paths the operations are mounted under, longest first.
+    base_paths: Vec<String>,
+    /// The prefix the spec is mounted under, ahead of its base paths.
+    prefix: String,
+    /// The webhooks of the spec, sorted by name.
+    webhooks: Vec<Webhook>,
+}
+
+/// The operations declared for a path.
+#[derive(Debug, Default)]
+pub struct PathEntry {
+    /// The operations by method, in the order of `PathItem::methods`.
+    pub operations: Vec<(Method, OperationEntry)>,
+}
+
+/// An operation with its resolved responses.
+#[derive(Debug)]
+pub struct OperationEntry {
+    /// The `operationId` of the operation, if declared.
+    pub operation_id: Option<String>,
+    /// Whether the operation declares a `2XX` response.
+    pub has_success: bool,
+    /// The responses of the operation, in the order they are declared in.
+    pub responses: Vec<ResponseEntry>,
+    /// The index of the response returned when no example matches the
+    /// request: the success response of the lowest status, or the `default`
+    /// response.
+    pub fallback: Option<usize>,
+}
+
+/// A response with its headers and content.
+#[derive(Debug)]
+pub struct ResponseEntry {
+    /// The response key as declared in the spec, e.g. `200` or `default`.
+    pub key: String,
+    /// The values of the declared headers.
+    pub headers: Vec<(String, String)>,
+    /// The content of the response by media type.
+    pub content: Vec<ContentEntry>,
+}
+
+/// The content of a response for a media type.
+#[derive(Debug)]
+pub struct ContentEntry {
+    /// The media type as declared in the spec.
+    pub media_type: String,
+    /// The named examples.
+    pub examples: Vec<ExampleEntry>,
+    /// The value returned when no named example matches the request:
+    /// the media type `example`, or a value generated from the schema.
+    pub value: Value,
+    /// The raw schema of the content, used to validate responses.
+    pub schema: Option<Value>,
+}
+
+/// A named example.
+#[derive(Debug)]
+pub struct ExampleEntry {
+    pub name: String,
+    pub value: Value,
+    /// The headers pinned by the `x-mock-headers` extension.
+    pub headers: Vec<(String, String)>,
+    /// The rules a request must match to be served the example, from the
+    /// name of the example and its `x-mock-match` and `x-mock-body`
+    /// extensions. None if the example declares no rules.
+    pub matcher: Option<Matcher>,
+}
+
+impl Index {
+    /// Build the index of a spec.
+    /// # Arguments
+    /// * `spec` - The OpenAPI spec
+    /// * `document` - The raw OpenAPI document the spec was parsed from
+    /// # Errors
+    /// Returns an error naming the operation, response and example if the
+    /// rules of an example are invalid.
+    pub fn build(spec: &oas3::OpenApiV3Spec, document: &Value) -> Result<Self, String> {
+        let router = Router::new(spec.paths.keys().map(String::as_str));
+        let paths = spec
+            .paths
+            .iter()
+            .map(|(key, path)| Ok((key.clone(), load_path_entry(spec, document, key, path)?)))
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            router,
+            paths,
+            base_paths: Vec::new(),
+            prefix: String::new(),
+            webhooks: load_webhooks(spec, document),
+        }
+        .with_base_paths(load_base_paths(spec)))
+    }
+
+    /// Replace the base paths the operations are mounted under.
+    pub fn with_base_paths(mut self, base_paths: Vec<String>) -> Self {
+        let mut base_paths: Vec<String> = base_paths
+            .iter()
+            .map(|base_path| normalize_base_path(base_path))
+            .filter(|base_path| !base_path.is_empty())
+            .collect();
+        base_paths.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        base_paths.dedup();
+        self.base_paths = base_paths;
+        self
+    }
+
+    /// The base paths the operations are mounted under, longest first.
+    pub fn base_paths(&self) -> &[String] {
+        &self.base_paths
+    }
+
+    /// Mount the spec under a prefix, ahead of its base paths.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = normalize_base_path(prefix);
+        self
+    }
+
+    /// The prefix the spec is mounted under, or an empty string.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Find the path matching a request path.
+    /// The prefix is stripped from the request path, which is then matched
+    /// under each base path, and then as is.
+    pub fn find(&self, path: &str) -> Option<RouteMatch<'_>> {
+        let path = strip_base_path(path, &self.prefix)?;
+        self.base_paths
+            .iter()
+            .filter_map(|base_path| strip_base_path(path, base_path))
+            .chain(std::iter::once(path))
+            .find_map(|path| self.router.find(path))
+    }
+
+    /// The webhooks of the spec, sorted by name.
+    pub fn webhooks(&self) -> &[Webhook] {
+        &self.webhooks
+    }
+
+    /// The paths declared in the spec, in no particular order.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.paths.keys().map(String::as_str)
+    }
+
+    /// Get the operation declared for a path and method.
+    /// # Arguments
+    /// * `key` - The path as declared in the spec, e.g. `/pets/{petId}`
+    /// * `method` - The HTTP method
+    pub fn operation(&self, key: &str, method: &Method) -> Option<&OperationEntry> {
+        self.paths
+            .get(key)?
+            .operations
+            .iter()
+            .find(|(m, _)| m == method)
+            .map(|(_, operation)| operation)
+    }
+
+    /// Get the methods declared for a path, or None if the path is not declared.
+    pub fn methods(&self, key: &str) -> Option<Vec<Method>> {
+        let path = self.paths.get(key)?;
+        Some(path.operations.iter().map(|(m, _)| m.clone()).collect())
+    }
+}
+
+impl OperationEntry {
+    /// Get a response by key.
+    pub fn response(&self, key: &str) -> Option<&ResponseEntry> {
+        self.responses.iter().find(|response| response.key == key)
+    }
+}
+
+impl ResponseEntry {
+    /// Get the content of a media type, as declared in the spec.
+    pub fn content(&self, media_type: &str) -> Option<&ContentEntry> {
+        self.content.iter().find(|c| c.media_type == media_type)
+    }
+
+    /// The media types declared for the response.
+    pub fn media_types(&self) -> Vec<&str> {
+        self.content.iter().map(|c| c.media_type.as_str()).collect()
+    }
+}
+
+/// Derive the base paths of the spec from the URLs of its `servers`.
+/// Server variables are substituted by each of their `enum` values,
+/// or by their `default` value.
+fn load_base_paths(spec: &oas3::OpenApiV3Spec) -> Vec<String> {
+    spec.servers
+        .iter()
+        .flat_map(expand_server_url)
+        .map(|url| url_path(&url).to_string())
+        .collect()
+}
+
+/// Expand the URL of a server for every value of its variables.
+fn expand_server_url(server: &Server) -> Vec<String> {
+    let mut urls = vec![server.url.clone()];
+    for (name, variable) in &server.variables {
+        let values = match variable.substitutions_enum.is_empty() {
+            true => vec![variable.default.clone()],
+            false => variable.substitutions_enum.clone(),
+        };
+        let placeholder = format!("{{{}}}", name);
+        urls = urls
+            .iter()
+            .flat_map(|url| values.iter().map(|value| url.replace(&placeholder, value)))
+            .collect();
+    }
+    urls
+}
+
+/// Get the path of a URL, e.g. `/v1` for `http://petstore.swagger.io/v1`.
+/// Relative URLs are paths already.
+fn url_path(url: &str) -> &str {
+    match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("", |start| &rest[start..]),
+        None => url,
+    }
+}
+
+/// Normalize a base path to a leading slash and no trailing slash.
+/// The root base path `/` normalizes to an empty string.
+fn normalize_base_path(base_path: &str) -> String {
+    let base_path = base_path.trim().trim_matches('/');
+    match base_path.is_empty() {
+        true => String::new(),
+        false => format!("/{}", base_path),
+    }
+}
+
+/// Strip a base path from a request path, if the request path is under it.
+fn strip_base_path<'a>(path: &'a str, base_path: &str) -> Option<&'a str> {
+    let rest = path.strip_prefix(base_path)?;
+    (rest.is_empty() || rest.starts_with('/')).then_some(rest)
+}
+
+fn load_path_entry(
+    spec: &oas3::OpenApiV3Spec,
+    document: &Value,
+    key: &str,
+    path: &PathItem,
+) -> Result<PathEntry, String> {
+    let mut operations: Vec<(Method, OperationEntry)> = Vec::new();
+    let path_item = document::get(document, &["paths", key]);
+    for (method, operation) in path.methods() {
+        if operations.iter().any(|(m, _)| *m == method) {
+            continue;
+        }
+        let tokens = ["paths", key, &method.as_str().to_lowercase()];
+        let raw = document::get(document, &tokens).and_then(|op| resolve_ref(document, op));
+        let declared = Declared {
+            params: template_params(key),
+            cookies: match (path_item, raw) {
+                (Some(path_item), Some(raw)) => load_parameters(document, path_item, raw)
+                    .into_iter()
+                    .filter(|parameter| parameter.get("in") == Some(&Value::from("cookie")))
+                    .filter_map(|parameter| parameter.get("name")?.as_str())
+                    .map(str::to_string)
+                    .collect(),
+                _ => Vec::new(),
+            },
+        };
+        let operation = load_operation_entry(spec, document, &tokens, &declared, operation)
+            .map_err(|error| format!("{} {}, {}", method, key, error))?;
+        operations.push((method, operation));
+    }
+    Ok(PathEntry { operations })
+}
+
+/// The parameters of an operation the examples can be matched against.
+struct Declared {
+    /// The names of the parameters of the path template.
+    params: Vec<String>,
+    /// The names of the `cookie` parameters of the operation.
+    cookies: Vec<String>,
+}
+
+/// Get the names of the parameters of a path template, e.g. `petId` for
+/// `/pets/{petId}`.
+fn template_params(key: &str) -> Vec<String> {
+    key.split('{')
+        .skip(1)
+        .filter_map(|rest| rest.split_once('}'))
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+fn load_operation_entry(
+    spec: &oas3::OpenApiV3Spec,
+    document: &Value,
+    operation_tokens: &[&str],
+    declared: &Declared,
+    operation: &Operation,
+) -> Result<OperationEntry, String> {
+    let mut responses: Vec<ResponseEntry> = operation
+        .responses
+        .iter()
+        .filter_map(|(key, response)| {
+            let response = extract_response(response, spec)?;
+            let tokens = [operation_tokens, &["responses", key]].concat();
+            Some(load_response_entry(
+                spec, document, &tokens, declared, key, &response,
+            ))
+        })
+        .collect::<Result<_, String>>()?;
+    // Keep the responses in the order they are declared in
+    let tokens = [operation_tokens, &["responses"]].concat();
+    if let Some(Value::Object(declared)) = document::get(document, &tokens) {
+        responses.sort_by_key(|response| declared.keys().position(|key| *key == response.key));
+    }
+
+    let fallback = responses
+        .iter()
+        .enumerate()
+        .filter(|(_, response)| is_success_key(&response.key))
+        .min_by(|(_, a), (_, b)| a.key.cmp(&b.key))
+        .map(|(position, _)| position)
+        .or_else(|| responses.iter().position(|r| r.key == "default"));
+
+    Ok(OperationEntry {
+        operation_id: operation.operation_id.clone(),
+        has_success: operation.responses.keys().any(|key| is_success_key(key)),
+        responses,
+        fallback,
+    })
+}
+
+fn load_response_entry(
+    spec: &oas3::OpenApiV3Spec,
+    document: &Value,
+    response_tokens: &[&str],
+    declared: &Declared,
+    key: &str,
+    response: &Response,
+) -> Result<ResponseEntry, String> {
+    let content = response
+        .content
+        .iter()
+        .map(|(media_type, content)| {
+            let tokens = [response_tokens, &["content", media_type]].concat();
+            let raw = document::get(document, &tokens);
+
+            let mut examples = match &content.examples {
+                Some(MediaTypeExamples::Examples { examples }) => examples
+                    .iter()
+                    .filter_map(|(name, example)| {
+                        let value = example.resolve(spec).ok()?.value?;
+                        let example = raw.and_then(|raw| raw.get("examples")?.get(name));
+                        let matcher =
+                            load_matcher(document, declared, name, example).map_err(|error| {
+                                format!("response {}, example {}: {}", key, name, error)
+                            });
+                        Some(matcher.map(|matcher| ExampleEntry {
+                            name: name.clone(),
+                            value,
+                            headers: load_example_headers(document, example),
+                            matcher,
+                        }))
+                    })
+                    .collect::<Result<_, String>>()?,
+                _ => Vec::new(),
+            };
+            // Keep the examples in the order they are declared in
+            if let Some(Value::Object(declared)) = raw.and_then(|raw| raw.get("examples")) {
+                examples
+                    .sort_by_key(|example| declared.keys().position(|name| *name == example.name));
+            }
+
+            let value = match &content.examples {
+                Some(MediaTypeExamples::Example { example }) => example.clone(),
+                _ => content
+                    .schema
+                    .as_ref()
+                    .and_then(|schema| resolve_schema(schema, spec))
+                    .map(|schema| generate(&schema, spec))
+                    .unwrap_or(Value::Null),
+            };
+
+            Ok(ContentEntry {
+                media_type: media_type.clone(),
+                examples,
+                value,
+                schema: raw.and_then(|raw| raw.get("schema")).cloned(),
+            })
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(ResponseEntry {
+        key: key.to_string(),
+        headers: load_headers(spec, document, response_tokens),
+        content,
+    })
+}
+
+/// Load the headers declared for a response in the raw document.
+/// # Arguments
+/// * `spec` - The OpenAPI spec, used to generate values from schemas
+/// * `document` - The raw OpenAPI document
+/// * `response` - The path of the response in the document
+fn load_headers(
+    spec: &oas3::OpenApiV3Spec,
+    document: &Value,
+    response: &[&str],
+) -> Vec<(String, String)> {
+    let headers = document::get(document, response)
+        .and_then(|response| response.get("headers"))
+        .and_then(|headers| headers.as_object());
+
+    headers
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, header)| {
+            let header = resolve_ref(document, header)?;
+            let value = load_header_example(spec, document, header)?;
+            Some((name.clone(), header_value(&value)))
+        })
+        .collect()
+}
+
+/// Get the example value of a header: its `example`, its first
+/// `examples` entry, or a value generated from its schema.
+fn load_header_example(
+    spec: &oas3::OpenApiV3Spec,
+    document: &Value,
+    header: &Value,
+) -> Option<Value> {
+    if let Some(example) = header.get("example") {
+        return Some(example.clone());
+    }
+
+    let examples = header
+        .get("examples")
+        .and_then(|examples| examples.as_object());
+    if let Some(example) = examples.and_then(|examples| examples.values().next()) {
+        return resolve_ref(document, example)?.get("value").cloned();
+    }
+
+    let schema: ObjectOrReference<oas3::Schema> =
+        serde_json::from_value(normalize_schema(header.get("schema")?)).ok()?;
+    let value = generate(&resolve_schema(&schema, spec)?, spec);
+    (!value.is_null()).then_some(value)
+}
+
+/// Load the headers pinned by the `x-mock-headers` extension of an example.
+fn load_example_headers(document: &Value, example: Option<&Value>) -> Vec<(String, String)> {
+    let headers = example
+        .and_then(|example| resolve_ref(document, example))
+        .and_then(|example| example.get("x-mock-headers"))
+        .and_then(|headers| headers.as_object());
+
+    headers
+        .into_iter()
+        .flatten()
+        .map(|(name, value)| (name.clone(), header_value(value)))
+        .collect()
+}
+
+/// Load the rules matching the requests served an example, from its name
+/// and its `x-mock-match` and `x-mock-body` extensions, which must all
+/// match. Returns an error if the rules are invalid. Rules on path
+/// parameters and cookies the operation does not declare are reported.
+fn load_matcher(
+    document: &Value,
+    declared: &Declared,
+    name: &str,
+    example: Option<&Value>,
+) -> Result<Option<Matcher>, String> {
+    let example = example.and_then(|example| resolve_ref(document, example));
+    let extension = |key: &str| example.and_then(|example| example.get(key));
+    let matchers = Matcher::from_name(name).and_then(|matcher| {
+        let mut matchers: Vec<Matcher> = matcher.into_iter().collect();
+        if let Some(extension) = extension("x-mock-match") {
+            matchers.push(Matcher::from_extension(extension)?);
+        }
+        if let Some(extension) = extension("x-mock-body") {
+            matchers.push(Matcher::from_body_extension(extension)?);
+        }
+        Ok(matchers)
+    });
+    let matcher = match matchers? {
+        matchers if matchers.is_empty() => return Ok(None),
+        mut matchers if matchers.len() == 1 => matchers.remove(0),
+        matchers => Matcher::All(matchers),
+    };
+    for undeclared in matcher.undeclared(&declared.params, &declared.cookies) {
+        println!(
+            "Warning: example {} matches the undeclared {}",
+            name, undeclared
+        );
+    }
+    Ok(Some(matcher))
+}
+
+fn extract_response(
+    response: &ObjectOrReference<Response>,
+    spec: &oas3::OpenApiV3Spec,
+) -> Option<Response> {
+    match response {
+        ObjectOrReference::Object(response) => Some(response.clone()),
+        ObjectOrReference::Ref { ref_path } => {
+            let components = &spec.components;
+            components
+                .as_ref()
+                .zip(ref_path.strip_prefix("#/components/responses/"))
+                .and_then(|(components, name)| components.responses.get(name))
+                .and_then(|resp| extract_response(resp, spec))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::document::load_document;
+
+    fn load_index(path: &str) -> Index {
+        let spec = oas3::from_path(path).unwrap();
+        let document = load_document(path).unwrap();
+        Index::build(&spec, &document).unwrap()
+    }
+
+    #[test]
+    fn test_index_paths() {
+        let index = load_index("tests/testdata/petstore.yaml");
+        assert_eq!(index.find("/pets").unwrap().key, "/pets");
+        assert_eq!(index.find("/pets/123").unwrap().key, "/pets/{petId}");
+        assert_eq!(index.find("/pets/{petId}").unwrap().key, "/pets/{petId}");
+        assert!(index.find("/notfound").is_none());
+    }
+
+    #[test]
+    fn test_index_base_paths() {
+        let index = load_index("tests/testdata/petstore.yaml");
+        assert_eq!(index.base_paths(), ["/v1"]);
+        let route = index.find("/v1/pets/1").unwrap();
+        assert_eq!(route.key, "/pets/{petId}");
+        assert_eq!(route.path, "/pets/1");
+        assert_eq!(index.find("/pets").unwrap().key, "/pets");
+        assert!(index.find("/v1pets").is_none());
+
+        let index = index.with_base_paths(vec!["api/".to_string()]);
+        assert_eq!(index.base_paths(), ["/api"]);
+        assert_eq!(index.find("/api/pets").unwrap().key, "/pets");
+        assert!(index.find("/v1/pets").is_none());
+    }
+
+    #[test]
+    fn test_index_prefix() {
+        let index = load_index("tests/testdata/petstore.yaml").with_prefix("/store/");
+        assert_eq!(index.prefix(), "/store");
+        assert_eq!(index.find("/store/v1/pets/1").unwrap().path, "/pets/1");
+        assert_eq!(index.find("/store/pets").unwrap().key, "/pets");
+        assert!(index.find("/pets").is_none());
+        assert!(index.find("/storepets").is_none());
+    }
+
+    #[test]
+    fn test_expand_server_url() {
+        let server: Server = serde_json::from_value(serde_json::json!({
+            "url": "https://{env}.example.com:{port}/{version}/api",
+            "variables": {
+                "env": {"default": "prod"},
+                "port": {"default": "443"},
+                "version": {"default": "v1", "enum": ["v1", "v2"]}
+            }
+        }))
+        .unwrap();
+        let urls = expand_server_url(&server);
+        let paths: Vec<&str> = urls.iter().map(|url| url_path(url)).collect();
+        assert_eq!(paths, vec!["/v1/api", "/v2/api"]);
+        assert_eq!(url_path("/v1"), "/v1");
+        assert_eq!(url_path("http://localhost:8080"), "");
+    }
+
+    #[test]
+    fn test_index_operations() {
+        let index = load_index("tests/testdata/petstore.yaml");
+        assert!(index.operation("/pets", &Method::GET).is_some());
+        assert!(index.operation("/pets", &Method::POST).is_some());
+        assert!(index.operation("/pets", &Method::PATCH).is_none());
+        assert_eq!(
+            index.methods("/pets/{petId}").unwrap(),
+            vec![Method::GET, Method::PUT, Method::DELETE]
+        );
+    }
+
+    #[test]
+    fn test_index_responses() {
+        let index = load_index("tests/testdata/petstore.yaml");
+        let operation = index.operation("/pets", &Method::GET).unwrap();
+        assert!(operation.has_success);
+        assert_eq!(operation.responses[operation.fallback.unwrap()].key, "200");
+
+        let response = operation.response("200").unwrap();
+        assert_eq!(
+            response.media_types(),
+            vec!["application/json", "text/plain"]
+        );
+        let content = response.content("application/json").unwrap();
+        let names: Vec<&str> = content.examples.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["default", "query:page=1", "query:limit=1&page=1"]
+        );
+        assert_eq!(
+            content.examples[1].headers,
+            vec![("x-next".to_string(), "/pets?page=2".to_string())]
+        );
+        assert_eq!(response.content("text/plain").unwrap().value, "string");
+    }
+}
@@ -0,0 +1,723 @@
+//! Matching of named examples against requests.
+//!
+//! The rules of an example are parsed once, when the spec is loaded, into
+//! a `Matcher` tree. They are declared by the `x-mock-match` extension of
+//! the example, and by the name of the example as a shorthand:
+//! - `/pets/2` matches the request path.
+//! - `query:page=1&limit=10` matches query parameters.
+//! - `header:x-api-key=123` matches headers.
+//! - `cookie:session=abc` matches cookies.
+//! - `param:petId=2` matches the path parameters of the path template, e.g.
+//!   any `/pets/2` request to `/pets/{petId}`, under any base path.
+//! - `body:/kind=cat` matches a body holding `cat` at the JSON pointer
+//!   `/kind`. The value is compared as JSON, or as a string if it is not
+//!   valid JSON: `body:/age=3` matches the number 3.
+//! - `body:/name=~^kit` matches a body whose value at `/name` matches the
+//!   regular expression. Values that are not strings are matched as JSON.
+//! - `body:{"kind": "cat"}` matches a body containing the object: the body
+//!   holds each of its fields, recursively. Arrays contain an array whose
+//!   items are each contained by one of their items.
+//!
+//! The pointer of a body rule may be empty to match the whole body, e.g.
+//! `body:=~^\{`. The `x-mock-body` extension holds body rules as well.
+//!
+//! The `x-mock-match` extension holds `path`, `params`, `query`, `headers`,
+//! `cookies` and `body` clauses, which must all hold, along with the
+//! `allOf`, `anyOf` and `not` combinators:
+//! ```yaml
+//! x-mock-match:
+//!   query:
+//!     page: 1
+//!     debug: {absent: true}
+//!   headers:
+//!     x-api-key: {regex: "^key-"}
+//!   anyOf:
+//!     - cookies:
+//!         session: {exists: true}
+//!     - body:
+//!         /kind: {oneOf: [cat, dog]}
+//! ```
+//! A clause maps names, or JSON pointers for the body, to a condition: a
+//! value to equal, or an object of `equals`, `regex`, `exists`, `absent`,
+//! `oneOf` and `contains`, which must all hold.
+use actix_web::{web, HttpRequest};
+use regex::Regex;
+use serde_json::{json, Value};
+
+/// A condition on the values of a request part, e.g. the values of a query
+/// parameter.
+#[derive(Debug, Clone)]
+pub enum Condition {
+    /// A value equals the value. Strings equal numbers and booleans with
+    /// the same text, since query parameters and headers are strings.
+    Equals(Value),
+    /// A value matches the regular expression. Values that are not strings
+    /// are matched as JSON.
+    Regex(Regex),
+    /// The part has a value.
+    Exists,
+    /// The part has no value.
+    Absent,
+    /// A value equals one of the values.
+    OneOf(Vec<Value>),
+    /// A value contains the value, see the module documentation.
+    Contains(Value),
+    /// All the conditions hold.
+    All(Vec<Condition>),
+}
+
+/// A tree of rules matching requests.
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    /// All the matchers match.
+    All(Vec<Matcher>),
+    /// Any of the matchers matches.
+    Any(Vec<Matcher>),
+    /// The matcher does not match.
+    Not(Box<Matcher>),
+    /// The request path, or the path relative to the base path, satisfies
+    /// the condition.
+    Path(Condition),
+    /// A path parameter satisfies the condition.
+    Param(String, Condition),
+    /// A query parameter satisfies the condition.
+    Query(String, Condition),
+    /// A header, by lowercase name, satisfies the condition.
+    Header(String, Condition),
+    /// A cookie satisfies the condition.
+    Cookie(String, Condition),
+    /// The value of the body at a JSON pointer satisfies the condition.
+    Body(String, Condition),
+}
+
+/// The body of a request, parsed once to be matched against the rules.
+#[derive(Debug, Clone, Default)]
+pub struct RequestBody {
+    /// The body as text, empty if it is not valid UTF-8.
+    pub text: String,
+    /// The body as JSON, or None if it is not valid JSON.
+    pub json: Option<Value>,
+}
+
+impl RequestBody {
+    /// Parse the body of a request.
+    pub fn from_bytes(body: &[u8]) -> Self {
+        Self {
+            text: String::from_utf8_lossy(body).to_string(),
+            json: serde_json::from_slice(body).ok(),
+        }
+    }
+}
+
+/// The parts of a request the matchers look at, read once per request.
+#[derive(Debug, Clone, Default)]
+pub struct RequestParts {
+    pub path: String,
+    /// The request path relative to the base path of the spec.
+    pub route_path: String,
+    /// The values of the path parameters.
+    pub params: Vec<(String, String)>,
+    pub query: Vec<(String, String)>,
+    /// The headers, by lowercase name.
+    pub headers: Vec<(String, String)>,
+    pub cookies: Vec<(String, String)>,
+    pub body: RequestBody,
+}
+
+impl RequestParts {
+    /// Read the parts of a request.
+    /// # Arguments
+    /// * `req` - The request
+    /// * `route_path` - The request path relative to the base path
+    /// * `body` - The request body
+    pub fn new(req: &HttpRequest, route_path: &str, body: &[u8]) -> Self {
+        let query = web::Query::<Vec<(String, String)>>::from_query(req.query_string())
+            .map(|query| query.into_inner())
+            .unwrap_or_default();
+        let headers = req
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = value.to_str().unwrap_or_default();
+                (name.as_str().to_lowercase(), value.to_string())
+            })
+            .collect();
+        let cookies = req
+            .cookies()
+            .map(|cookies| {
+                cookies
+                    .iter()
+                    .map(|cookie| (cookie.name().to_string(), cookie.value().to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            path: req.path().to_string(),
+            route_path: route_path.to_string(),
+            params: Vec::new(),
+            query,
+            headers,
+            cookies,
+            body: RequestBody::from_bytes(body),
+        }
+    }
+
+    /// Set the values of the path parameters.
+    pub fn with_params(self, params: Vec<(String, String)>) -> Self {
+        Self { params, ..self }
+    }
+}
+
+impl Matcher {
+    /// Parse the rules an example name is a shorthand for.
+    /// # Arguments
+    /// * `example_name` - The example name
+    /// # Returns
+    /// The matcher, None if the name is not a shorthand for rules
+    /// # Errors
+    /// Returns an error if the rules of the name are invalid.
+    /// # Example
+    /// ```rust
+    /// use actix_web::test::TestRequest;
+    /// use openapi_mocker::openapi::matcher::{Matcher, RequestParts};
+    /// let matcher = Matcher::from_name("body:/kind=cat").unwrap().unwrap();
+    /// let req = TestRequest::post().uri("/pets").to_http_request();
+    /// let parts = RequestParts::new(&req, "/pets", br#"{"kind": "cat"}"#);
+    /// assert!(matcher.matches(&parts));
+    /// assert!(Matcher::from_name("kitty").unwrap().is_none());
+    /// ```
+    pub fn from_name(example_name: &str) -> Result<Option<Self>, String> {
+        if example_name.starts_with('/') {
+            let path = Value::String(example_name.to_string());
+            return Ok(Some(Matcher::Path(Condition::Equals(path))));
+        }
+        if let Some(query) = example_name.strip_prefix("query:") {
+            return Ok(Some(parse_pairs(query, |name, value| {
+                Matcher::Query(name.to_string(), value)
+            })));
+        }
+        if let Some(headers) = example_name.strip_prefix("header:") {
+            return Ok(Some(parse_pairs(headers, |name, value| {
+                Matcher::Header(name.to_lowercase(), value)
+            })));
+        }
+        if let Some(cookies) = example_name.strip_prefix("cookie:") {
+            return Ok(Some(parse_pairs(cookies, |name, value| {
+                Matcher::Cookie(name.to_string(), value)
+            })));
+        }
+        if let Some(params) = example_name.strip_prefix("param:") {
+            return Ok(Some(parse_pairs(params, |name, value| {
+                Matcher::Param(name.to_string(), value)
+            })));
+        }
+        let Some(rule) = example_name.strip_prefix("body:") else {
+            return Ok(None);
+        };
+
+        if rule.starts_with(['{', '[']) {
+            let value = serde_json::from_str(rule)
+                .map_err(|e| format!("Invalid body in {}: {}", example_name, e))?;
+            return Ok(Some(Matcher::Body(
+                String::new(),
+                Condition::Contains(value),
+            )));
+        }
+        let (pointer, value) = rule
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid body rule {}", example_name))?;
+        let condition = match value.strip_prefix('~') {
+            Some(pattern) => Condition::Regex(compile(pattern)?),
+            None => Condition::Equals(
+                serde_json::from_str(value).unwrap_or(Value::String(value.to_string())),
+            ),
+        };
+        Ok(Some(Matcher::Body(pointer.to_string(), condition)))
+    }
+
+    /// Parse an `x-mock-match` extension, see the module documentation.
+    /// # Errors
+    /// Returns an error naming the invalid clause or condition.
+    /// # Example
+    /// ```rust
+    /// use actix_web::test::TestRequest;
+    /// use serde_json::json;
+    /// use openapi_mocker::openapi::matcher::{Matcher, RequestParts};
+    /// let matcher = Matcher::from_extension(&json!({
+    ///     "query": {"page": 1},
+    ///     "headers": {"x-api-key": {"regex": "^key-"}}
+    /// }))
+    /// .unwrap();
+    /// let req = TestRequest::with_uri("/pets?page=1")
+    ///     .insert_header(("x-api-key", "key-123"))
+    ///     .to_http_request();
+    /// assert!(matcher.matches(&RequestParts::new(&req, "/pets", &[])));
+    /// ```
+    pub fn from_extension(extension: &Value) -> Result<Self, String> {
+        let clauses = extension
+            .as_object()
+            .ok_or("x-mock-match must be an object")?;
+        // An empty set of rules would match every request
+        if clauses.is_empty() {
+            return Err("x-mock-match must declare at least one condition".to_string());
+        }
+        let mut matchers = Vec::new();
+        for (clause, value) in clauses {
+            let matcher = match clause.as_str() {
+                "path" => Matcher::Path(Condition::parse(value)?),
+                "params" => {
+                    parse_clause(clause, value, |name, c| Matcher::Param(name.to_string(), c))?
+                }
+                "query" => {
+                    parse_clause(clause, value, |name, c| Matcher::Query(name.to_string(), c))?
+                }
+                "headers" => parse_clause(clause, value, |name, c| {
+                    Matcher::Header(name.to_lowercase(), c)
+                })?,
+                "cookies" => parse_clause(clause, value, |name, c| {
+                    Matcher::Cookie(name.to_string(), c)
+                })?,
+                "body" => parse_clause(clause, value, |pointer, c| {
+                    Matcher::Body(pointer.to_string(), c)
+                })?,
+                "allOf" | "anyOf" => {
+                    let matchers = value
+                        .as_array()
+                        .ok_or_else(|| format!("{} must be a list", clause))?
+                        .iter()
+                        .map(Self::from_extension)
+                        .collect::<Result<Vec<Matcher>, String>>()?;
+                    if matchers.is_empty() {
+                        return Err(format!("{} must declare at least one condition", clause));
+                    }
+                    match clause.as_str() {
+                        "allOf" => Matcher::All(matchers),
+                        _ => Matcher::Any(matchers),
+                    }
+                }
+                "not" => Matcher::Not(Box::new(Self::from_extension(value)?)),
+                clause => return Err(format!("Unknown clause {}", clause)),
+            };
+            matchers.push(matcher);
+        }
+        Ok(Matcher::All(matchers))
+    }
+
+    /// Parse an `x-mock-body` extension: a body rule, or a list of body
+    /// rules that must all match. A rule holds an optional `pointer`, the
+    /// whole body by default, and one of `equals`, `contains` or `matches`
+    /// (a regular expression). It is an alias of the `body` clause of
+    /// `x-mock-match`, whose `equals`, `contains` and `regex` conditions
+    /// the rules are parsed as.
+    /// # Errors
+    /// Returns an error if a rule is invalid.
+    pub fn from_body_extension(extension: &Value) -> Result<Self, String> {
+        let rules: Vec<&Value> = match extension {
+            Value::Array(rules) => rules.iter().collect(),
+            rule => vec![rule],
+        };
+        if rules.is_empty() {
+            return Err("x-mock-body must declare at least one rule".to_string());
+        }
+        let mut matchers = Vec::new();
+        for rule in rules {
+            let pointer = match rule.get("pointer") {
+                Some(Value::String(pointer)) => pointer.clone(),
+                Some(_) => return Err("The pointer of a body rule must be a string".to_string()),
+                None => String::new(),
+            };
+            let condition = match (
+                rule.get("equals"),
+                rule.get("contains"),
+                rule.get("matches"),
+            ) {
+                (Some(value), _, _) => json!({ "equals": value }),
+                (_, Some(value), _) => json!({ "contains": value }),
+                (_, _, Some(Value::String(pattern))) => json!({ "regex": pattern }),
+                (_, _, Some(_)) => {
+                    return Err("The matches of a body rule must be a string".to_string())
+                }
+                _ => return Err("A body rule needs one of equals, contains or matches".to_string()),
+            };
+            matchers.push(Matcher::Body(pointer, Condition::parse(&condition)?));
+        }
+        Ok(Matcher::All(matchers))
+    }
+
+    /// List the path parameters and cookies the matcher looks at which the
+    /// operation does not declare, e.g. `path parameter id`.
+    /// # Arguments
+    /// * `params` - The names of the parameters of the path template
+    /// * `cookies` - The names of the `cookie` parameters of the operation
+    pub fn undeclared(&self, params: &[String], cookies: &[String]) -> Vec<String> {
+        match self {
+            Matcher::All(matchers) | Matcher::Any(matchers) => matchers
+                .iter()
+                .flat_map(|m| m.undeclared(params, cookies))
+                .collect(),
+            Matcher::Not(matcher) => matcher.undeclared(params, cookies),
+            Matcher::Param(name, _) if !params.contains(name) => {
+                vec![format!("path parameter {}", name)]
+            }
+            Matcher::Cookie(name, _) if !cookies.contains(name) => {
+                vec![format!("cookie {}", name)]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Check whether a request matches.
+    pub fn matches(&self, parts: &RequestParts) -> bool {
+        self.score(parts).is_some()
+    }
+
+    /// Score how well a request matches, by the number of conditions it
+    /// satisfies: the conditions of all the matchers of `All`, of the best
+    /// matching matcher of `Any`, and one for a `Not`.
+    /// # Returns
+    /// The number of satisfied conditions, None if the request does not
+    /// match
+    /// # Example
+    /// ```rust
+    /// use actix_web::test::TestRequest;
+    /// use openapi_mocker::openapi::matcher::{Matcher, RequestParts};
+    /// let req = TestRequest::with_uri("/pets?page=1&limit=1").to_http_request();
+    /// let parts = RequestParts::new(&req, "/pets", &[]);
+    /// let score = |name: &str| Matcher::from_name(name).unwrap().unwrap().score(&parts);
+    /// assert_eq!(score("query:page=1"), Some(1));
+    /// assert_eq!(score("query:limit=1&page=1"), Some(2));
+    /// assert_eq!(score("query:page=2"), None);
+    /// ```
+    pub fn score(&self, parts: &RequestParts) -> Option<usize> {
+        let holds = |condition: &Condition, values: &[Value]| {
+            condition.holds(values).then(|| condition.count())
+        };
+        match self {
+            Matcher::All(matchers) => matchers.iter().map(|m| m.score(parts)).sum(),
+            Matcher::Any(matchers) => matchers.iter().filter_map(|m| m.score(parts)).max(),
+            Matcher::Not(matcher) => matcher.score(parts).is_none().then_some(1),
+            Matcher::Path(condition) => holds(condition, &[Value::String(parts.path.clone())])
+                .or_else(|| holds(condition, &[Value::String(parts.route_path.clone())])),
+            Matcher::Param(name, condition) => holds(condition, &values(&parts.params, name)),
+            Matcher::Query(name, condition) => holds(condition, &values(&parts.query, name)),
+            Matcher::Header(name, condition) => holds(condition, &values(&parts.headers, name)),
+            Matcher::Cookie(name, condition) => holds(condition, &values(&parts.cookies, name)),
+            Matcher::Body(pointer, condition) => {
+                let value = lookup(&parts.body, pointer);
+                holds(condition, &value.into_iter().collect::<Vec<Value>>())
+            }
+        }
+    }
+}
+
+impl Condition {
+    /// Parse a condition: a value to equal, or an object of conditions.
+    /// # Errors
+    /// Returns an error if a regular expression is invalid, or the object
+    /// holds an unknown condition.
+    pub fn parse(value: &Value) -> Result<Self, String> {
+        let Some(object) = value.as_object() else {
+            return Ok(Condition::Equals(value.clone()));
+        };
+        let mut conditions = Vec::new();
+        for (key, value) in object {
+            let condition = match (key.as_str(), value) {
+                ("equals", value) => Condition::Equals(value.clone()),
+                ("regex", Value::String(pattern)) => Condition::Regex(compile(pattern)?),
+                ("exists", Value::Bool(true)) | ("absent", Value::Bool(false)) => Condition::Exists,
+                ("exists", Value::Bool(false)) | ("absent", Value::Bool(true)) => Condition::Absent,
+                ("oneOf", Value::Array(values)) => Condition::OneOf(values.clone()),
+                ("contains", value) => Condition::Contains(value.clone()),
+                (key, value) => return Err(format!("Invalid condition {}: {}", key, value)),
+            };
+            conditions.push(condition);
+        }
+        match conditions.len() {
+            0 => Err("A condition must declare at least one rule".to_string()),
+            1 => Ok(conditions.remove(0)),
+            _ => Ok(Condition::All(conditions)),
+        }
+    }
+
+    /// Count the conditions, for the score of a matcher.
+    fn count(&self) -> usize {
+        match self {
+            Condition::All(conditions) => conditions.iter().map(Condition::count).sum(),
+            _ => 1,
+        }
+    }
+
+    /// Check whether the values of a request part satisfy the condition:
+    /// `Exists` and `Absent` look at whether there are values, and the
+    /// other conditions hold if any of the values satisfies them.
+    pub fn holds(&self, values: &[Value]) -> bool {
+        match self {
+            Condition::Exists => !values.is_empty(),
+            Condition::Absent => values.is_empty(),
+            Condition::All(conditions) => conditions.iter().all(|c| c.holds(values)),
+            Condition::Equals(expected) => values.iter().any(|value| equals(value, expected)),
+            Condition::OneOf(expected) => values
+                .iter()
+                .any(|value| expected.iter().any(|expected| equals(value, expected))),
+            Condition::Contains(expected) => values.iter().any(|value| contains(value, expected)),
+            Condition::Regex(regex) => values.iter().any(|value| match value {
+                Value::String(text) => regex.is_match(text),
+                value => regex.is_match(&value.to_string()),
+            }),
+        }
+    }
+}
+
+/// Parse `name=value` pairs joined by `&`, which must all match.
+fn parse_pairs(pairs: &str, matcher: impl Fn(&str, Condition) -> Matcher) -> Matcher {
+    let matchers = pairs
+        .split('&')
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = Value::String(value.to_string());
+            matcher(name, Condition::Equals(value))
+        })
+        .collect();
+    Matcher::All(matchers)
+}
+
+/// Parse a clause mapping names to conditions, which must all hold.
+fn parse_clause(
+    clause: &str,
+    value: &Value,
+    matcher: impl Fn(&str, Condition) -> Matcher,
+) -> Result<Matcher, String> {
+    let conditions = value
+        .as_object()
+        .ok_or_else(|| format!("The {} clause must be an object", clause))?;
+    if conditions.is_empty() {
+        return Err(format!(
+            "The {} clause must declare at least one condition",
+            clause
+        ));
+    }
+    let matchers = conditions
+        .iter()
+        .map(|(name, condition)| {
+            let condition = Condition::parse(condition)
+                .map_err(|e| format!("{} of {}: {}", name, clause, e))?;
+            Ok(matcher(name, condition))
+        })
+        .collect::<Result<Vec<Matcher>, String>>()?;
+    Ok(Matcher::All(matchers))
+}
+
+fn compile(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("Invalid regular expression {}: {}", pattern, e))
+}
+
+/// Get the values of a name among pairs.
+fn values(pairs: &[(String, String)], name: &str) -> Vec<Value> {
+    pairs
+        .iter()
+        .filter(|(key, _)| key == name)
+        .map(|(_, value)| Value::String(value.clone()))
+        .collect()
+}
+
+/// Get the value at a JSON pointer of the body. The whole body is taken as
+/// a string if it is not valid JSON.
+fn lookup(body: &RequestBody, pointer: &str) -> Option<Value> {
+    match (&body.json, pointer) {
+        (Some(json), pointer) => json.pointer(pointer).cloned(),
+        (None, "") if !body.text.is_empty() => Some(Value::String(body.text.clone())),
+        (None, _) => None,
+    }
+}
+
+/// Check whether a value equals the expected one, comparing a string with
+/// the text of an expected number or boolean.
+fn equals(value: &Value, expected: &Value) -> bool {
+    match (value, expected) {
+        (Value::String(text), Value::Number(_) | Value::Bool(_)) => {
+            text.as_str() == expected.to_string().as_str()
+        }
+        (value, expected) => value == expected,
+    }
+}
+
+/// Check whether a value contains another: objects hold each field of the
+/// other, arrays hold an item containing each item of the other, and other
+/// values are equal.
+fn contains(value: &Value, other: &Value) -> bool {
+    match (value, other) {
+        (Value::Object(value), Value::Object(other)) => other
+            .iter()
+            .all(|(key, other)| value.get(key).is_some_and(|value| contains(value, other))),
+        (Value::Array(values), Value::Array(others)) => others
+            .iter()
+            .all(|other| values.iter().any(|value| contains(value, other))),
+        (value, other) => value == other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn body(value: Value) -> RequestParts {
+        RequestParts {
+            body: RequestBody::from_bytes(value.to_string().as_bytes()),
+            ..RequestParts::default()
+        }
+    }
+
+    #[test]
+    fn test_matcher_from_body_name() {
+        let cat = body(json!({"kind": "cat", "age": 3, "tags": ["a", "b"], "owner": {"id": 1}}));
+        let rule = |name: &str| Matcher::from_name(name).unwrap().unwrap();
+        assert!(rule("body:/kind=cat").matches(&cat));
+        assert!(rule("body:/age=3").matches(&cat));
+        assert!(!rule("body:/age=4").matches(&cat));
+        assert!(rule("body:/tags/1=b").matches(&cat));
+        assert!(rule("body:/kind=~^c").matches(&cat));
+        assert!(rule("body:/owner=~\"id\":1").matches(&cat));
+        assert!(rule(r#"body:{"owner": {"id": 1}, "tags": ["b"]}"#).matches(&cat));
+        assert!(!rule(r#"body:{"owner": {"id": 2}}"#).matches(&cat));
+        assert!(!rule("body:/missing=cat").matches(&cat));
+
+        // A body that is not JSON is matched as text
+        let text = RequestParts {
+            body: RequestBody::from_bytes(b"name=kitty"),
+            ..RequestParts::default()
+        };
+        assert!(rule("body:=~kitty").matches(&text));
+        assert!(!rule("body:/name=kitty").matches(&text));
+
+        assert!(Matcher::from_name("body:/kind").is_err());
+        assert!(Matcher::from_name("body:/kind=~(").is_err());
+    }
+
+    #[test]
+    fn test_matcher_from_body_extension() {
+        let matcher = Matcher::from_body_extension(&json!([
+            {"pointer": "/kind", "equals": "cat"},
+            {"contains": {"owner": {"id": 1}}}
+        ]))
+        .unwrap();
+        assert!(matcher.matches(&body(
+            json!({"kind": "cat", "owner": {"id": 1, "name": "jon"}})
+        )));
+        assert!(!matcher.matches(&body(json!({"kind": "cat", "owner": {"id": 2}}))));
+
+        assert!(Matcher::from_body_extension(&json!({"pointer": "/kind"})).is_err());
+        assert!(Matcher::from_body_extension(&json!({"matches": 1})).is_err());
+    }
+
+    #[test]
+    fn test_matcher_body_extension_alias() {
+        let alias = Matcher::from_body_extension(&json!([
+            {"pointer": "/kind", "equals": "bird"},
+            {"pointer": "/name", "matches": "^tw"},
+            {"contains": {"owner": {"id": 1}}}
+        ]))
+        .unwrap();
+        let clause = Matcher::from_extension(&json!({"body": {
+            "/kind": {"equals": "bird"},
+            "/name": {"regex": "^tw"},
+            "": {"contains": {"owner": {"id": 1}}}
+        }}))
+        .unwrap();
+        let req = TestRequest::post().uri("/pets").to_http_request();
+        for body in [
+            json!({"kind": "bird", "name": "tweety", "owner": {"id": 1}}),
+            json!({"kind": "bird", "name": "tweety", "owner": {"id": 2}}),
+            json!({"kind": "bird", "name": "polly", "owner": {"id": 1}}),
+            json!({"kind": "cat", "name": "tweety", "owner": {"id": 1}}),
+        ] {
+            let body = body.to_string();
+            let parts = RequestParts::new(&req, "/pets", body.as_bytes());
+            assert_eq!(alias.score(&parts), clause.score(&parts), "{}", body);
+        }
+    }
+
+    #[test]
+    fn test_matcher_from_extension() {
+        let matcher = Matcher::from_extension(&json!({
+            "path": {"regex": "^/pets/[0-9]+$"},
+            "query": {"page": 1, "debug": {"absent": true}},
+            "headers": {"X-Api-Key": {"exists": true}},
+            "anyOf": [
+                {"cookies": {"session": {"oneOf": ["a", "b"]}}},
+                {"body": {"/kind": "cat"}}
+            ],
+            "not": {"body": {"/name": {"regex": "^bat"}}}
+        }))
+        .unwrap();
+        let parts = |uri: &str, cookie: &str, body: &str| {
+            let req = TestRequest::post()
+                .uri(uri)
+                .insert_header(("x-api-key", "123"))
+                .insert_header(("cookie", cookie))
+                .to_http_request();
+            RequestParts::new(&req, req.path(), body.as_bytes())
+        };
+        assert!(matcher.matches(&parts("/pets/2?page=1", "session=a", "")));
+        assert!(matcher.matches(&parts("/pets/2?page=1", "", r#"{"kind": "cat"}"#)));
+        assert!(!matcher.matches(&parts("/pets/2?page=1", "", r#"{"kind": "dog"}"#)));
+        assert!(!matcher.matches(&parts("/pets/2?page=1&debug", "session=a", "")));
+        assert!(!matcher.matches(&parts("/pets/two?page=1", "session=a", "")));
+        let bat = r#"{"kind": "cat", "name": "batty"}"#;
+        assert!(!matcher.matches(&parts("/pets/2?page=1", "", bat)));
+
+        assert!(Matcher::from_extension(&json!({"query": {"page": {"like": 1}}})).is_err());
+        assert!(Matcher::from_extension(&json!({"param": {}})).is_err());
+        assert!(Matcher::from_extension(&json!({"anyOf": {}})).is_err());
+
+        // Empty rules would match every request
+        assert_eq!(
+            Matcher::from_extension(&json!({})).unwrap_err(),
+            "x-mock-match must declare at least one condition"
+        );
+        assert!(Matcher::from_extension(&json!({"query": {}})).is_err());
+        assert!(Matcher::from_extension(&json!({"anyOf": []})).is_err());
+        assert!(Matcher::from_extension(&json!({"not": {}})).is_err());
+        assert!(Matcher::from_extension(&json!({"headers": {"x-api-key": {}}})).is_err());
+        assert!(Matcher::from_body_extension(&json!([])).is_err());
+    }
+
+    #[test]
+    fn test_matcher_undeclared() {
+        let matcher = Matcher::from_extension(&json!({
+            "params": {"petId": 2, "id": 2},
+            "anyOf": [{"cookies": {"session": "a"}}, {"cookies": {"token": "b"}}]
+        }))
+        .unwrap();
+        let declared = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            matcher.undeclared(&declared(&["petId"]), &declared(&["session"])),
+            vec!["path parameter id", "cookie token"]
+        );
+    }
+
+    #[test]
+    fn test_matcher_from_name() {
+        let req = TestRequest::with_uri("/v1/pets/2?page=1&limit=10")
+            .insert_header(("x-api-key", "123"))
+            .insert_header(("cookie", "session=abc"))
+            .to_http_request();
+        let parts = RequestParts::new(&req, "/pets/2", &[]);
+        let matches = |name: &str| Matcher::from_name(name).unwrap().unwrap().matches(&parts);
+        assert!(matches("/pets/2"));
+        assert!(matches("/v1/pets/2"));
+        assert!(!matches("/pets/3"));
+        assert!(matches("query:page=1"));
+        assert!(matches("query:limit=10&page=1"));
+        assert!(!matches("query:page=2"));
+        assert!(matches("header:X-Api-Key=123"));
+        assert!(matches("cookie:session=abc"));
+        assert!(!matches("cookie:session=xyz"));
+        assert!(!matches("param:petId=2"));
+        let parts = parts.with_params(vec![("petId".to_string(), "2".to_string())]);
+        let matches = |name: &str| Matcher::from_name(name).unwrap().unwrap().matches(&parts);
+        assert!(matches("param:petId=2"));
+        assert!(!matches("param:petId=3"));
+        assert!(Matcher::from_name("default").unwrap().is_none());
+    }
+}
@@ -1 +1,17 @@
+pub mod bundler;
+pub mod collection;
+pub mod content;
+pub mod directive;
+pub mod document;
+pub mod generator;
+pub mod index;
+pub mod matcher;
+pub mod recorder;
+pub mod request;
+pub mod response;
+pub mod router;
 pub mod spec;
+pub mod swagger;
+pub mod validator;
+pub mod version;
+pub mod webhook;
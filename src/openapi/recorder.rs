@@ -0,0 +1,422 @@
+//! Recording of upstream responses as named examples.
+//!
+//! In proxy mode, the responses of the upstream are written back as named
+//! examples in a copy of the OpenAPI document, so that running the mocker
+//! against the copy replays them. The examples are named after the request
+//! with the conventions the example matching understands: `query:` for a
+//! request with a query string, `header:` for a request carrying one of the
+//! recorded headers, and the request path otherwise.
+//!
+//! The copy starts from the spec file as written, keeping its version and
+//! its `$ref`s to other files. A Swagger 2.0 spec, whose responses declare
+//! examples differently, is copied as the OpenAPI 3.0 document it is served
+//! as.
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use actix_web::HttpRequest;
+use serde_json::{json, Value};
+
+use super::content::deserialize;
+use super::document::load_document;
+use super::spec::Spec;
+use super::version::{detect, Version};
+
+/// The description of the responses created for recorded examples.
+const RECORDED_DESCRIPTION: &str = "Recorded response";
+
+/// Records upstream responses into a copy of an OpenAPI document.
+#[derive(Debug)]
+pub struct Recorder {
+    path: PathBuf,
+    document: Value,
+    /// The headers naming the examples of the requests carrying them.
+    headers: Vec<String>,
+    /// The spec file the document is a copy of, if any.
+    source: Option<PathBuf>,
+    /// The responses recorded, replayed on the document when the spec is
+    /// reloaded.
+    recordings: Vec<OwnedRecording>,
+    /// The number of responses recorded.
+    version: u64,
+    /// The version of the document last written to the file, shared with
+    /// the snapshots so that an older one never overwrites a newer one.
+    written: Arc<Mutex<u64>>,
+}
+
+/// A copy of the recorded document, to write to the file without holding
+/// the recorder.
+#[derive(Debug)]
+pub struct Snapshot {
+    path: PathBuf,
+    document: Value,
+    version: u64,
+    written: Arc<Mutex<u64>>,
+}
+
+/// A response captured from the upstream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording<'a> {
+    /// The path of the spec, e.g. `/pets/{petId}`.
+    pub key: &'a str,
+    /// The lowercase HTTP method, e.g. `get`.
+    pub method: &'a str,
+    /// The name of the example.
+    pub name: &'a str,
+    pub status: u16,
+    /// The media type of the body, without its parameters.
+    pub media_type: Option<&'a str>,
+    pub body: &'a [u8],
+}
+
+/// A recorded response, kept by the recorder.
+#[derive(Debug, Clone, PartialEq)]
+struct OwnedRecording {
+    key: String,
+    method: String,
+    name: String,
+    status: u16,
+    media_type: Option<String>,
+    body: Vec<u8>,
+}
+
+impl OwnedRecording {
+    fn new(recording: &Recording) -> Self {
+        Self {
+            key: recording.key.to_string(),
+            method: recording.method.to_string(),
+            name: recording.name.to_string(),
+            status: recording.status,
+            media_type: recording.media_type.map(str::to_string),
+            body: recording.body.to_vec(),
+        }
+    }
+
+    /// Whether two recordings declare the same example, the later one
+    /// replacing the earlier.
+    fn same_example(&self, other: &Self) -> bool {
+        self.key == other.key
+            && self.method == other.method
+            && self.status == other.status
+            && self.media_type == other.media_type
+            && self.name == other.name
+    }
+
+    fn borrow(&self) -> Recording<'_> {
+        Recording {
+            key: &self.key,
+            method: &self.method,
+            name: &self.name,
+            status: self.status,
+            media_type: self.media_type.as_deref(),
+            body: &self.body,
+        }
+    }
+}
+
+impl Recorder {
+    /// Create a recorder writing a copy of a document to a file.
+    /// # Arguments
+    /// * `path` - The file to write, as YAML if its extension is `.yaml`
+    ///   or `.yml`, as JSON otherwise
+    /// * `document` - The raw OpenAPI document to copy
+    /// * `headers` - The headers naming the examples of the requests
+    ///   carrying them, e.g. `x-tenant`
+    pub fn new(path: impl Into<PathBuf>, document: Value, headers: Vec<String>) -> Self {
+        Self {
+            path: path.into(),
+            document,
+            headers,
+            source: None,
+            recordings: Vec::new(),
+            version: 0,
+            written: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    /// Create a recorder writing a copy of the file of a spec, see
+    /// `source_document`.
+    /// # Arguments
+    /// * `path` - The file to write, see `new`
+    /// * `spec` - The spec to copy
+    /// * `headers` - The headers naming the examples of the requests
+    ///   carrying them
+    pub fn for_spec(path: impl Into<PathBuf>, spec: &Spec, headers: Vec<String>) -> Self {
+        Self {
+            source: spec.files().first().cloned(),
+            ..Self::new(path, source_document(spec), headers)
+        }
+    }
+
+    /// Copy a reloaded spec again, keeping the responses recorded so far.
+    /// A spec loaded from another file than the copied one is ignored.
+    pub fn refresh(&mut self, spec: &Spec) {
+        if self.source.is_none() || self.source.as_ref() != spec.files().first() {
+            return;
+        }
+        self.document = source_document(spec);
+        for recording in &self.recordings {
+            apply(&mut self.document, &recording.borrow());
+        }
+    }
+
+    /// Get the default path of the copy of a spec file, next to it:
+    /// `openapi.yaml` is recorded to `openapi.recorded.yaml`.
+    /// # Example
+    /// ```rust
+    /// use std::path::Path;
+    /// use openapi_mocker::openapi::recorder::Recorder;
+    /// let path = Recorder::default_path(Path::new("specs/openapi.yaml"));
+    /// assert_eq!(path, Path::new("specs/openapi.recorded.yaml"));
+    /// ```
+    pub fn default_path(spec: &Path) -> PathBuf {
+        let stem = spec.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match spec.extension() {
+            Some(extension) => format!("{}.recorded.{}", stem, extension.to_string_lossy()),
+            None => format!("{}.recorded", stem),
+        };
+        spec.with_file_name(name)
+    }
+
+    /// The recorded document.
+    pub fn document(&self) -> &Value {
+        &self.document
+    }
+
+    /// Name the example recorded for a request.
+    /// # Arguments
+    /// * `req` - The request
+    /// * `path` - The request path, relative to the base path it matched
+    /// # Returns
+    /// `query:<query string>` if the request has a query string,
+    /// `header:<name>=<value>` if it carries one of the recorded headers,
+    /// or the request path otherwise.
+    pub fn example_name(&self, req: &HttpRequest, path: &str) -> String {
+        if !req.query_string().is_empty() {
+            return format!("query:{}", req.query_string());
+        }
+
+        let header = self.headers.iter().find_map(|name| {
+            let value = req.headers().get(name.as_str())?.to_str().ok()?;
+            Some(format!("header:{}={}", name.to_lowercase(), value))
+        });
+        header.unwrap_or_else(|| path.to_string())
+    }
+
+    /// Record a response as a named example of its operation. The path,
+    /// operation, response and media type are declared if missing, and an
+    /// example of the same name is replaced.
+    /// # Returns
+    /// The snapshot of the recorded document, to write to the file.
+    pub fn record(&mut self, recording: &Recording) -> Snapshot {
+        apply(&mut self.document, recording);
+        let recorded = OwnedRecording::new(recording);
+        self.recordings.retain(|r| !r.same_example(&recorded));
+        self.recordings.push(recorded);
+
+        self.version += 1;
+        Snapshot {
+            path: self.path.clone(),
+            document: self.document.clone(),
+            version: self.version,
+            written: self.written.clone(),
+        }
+    }
+}
+
+/// Declare a recorded response as a named example of its operation in a
+/// document, see `Recorder::record`.
+fn apply(document: &mut Value, recording: &Recording) {
+    let status = recording.status.to_string();
+    let response =
+        &mut document["paths"][recording.key][recording.method]["responses"][status.as_str()];
+    if response.get("description").is_none() {
+        response["description"] = json!(RECORDED_DESCRIPTION);
+    }
+
+    if let (Some(media_type), false) = (recording.media_type, recording.body.is_empty()) {
+        let content = &mut response["content"][media_type];
+        // A media type declares either `example` or `examples`
+        if let Some(example) = content.as_object_mut().and_then(|c| c.remove("example")) {
+            content["examples"]["default"] = json!({ "value": example });
+        }
+        let value = deserialize(recording.body, media_type);
+        content["examples"][recording.name] = json!({ "value": value });
+    }
+}
+
+/// Get the document a recorder copies for a spec: its file as written, or
+/// the document it is served as for a Swagger 2.0 spec or a file that can
+/// no longer be read.
+/// # Example
+/// ```rust
+/// use openapi_mocker::openapi::{recorder::source_document, spec::Spec};
+/// let spec = Spec::from_path("tests/testdata/bundle/openapi.yaml").unwrap();
+/// let document = source_document(&spec);
+/// let limit = &document["paths"]["/pets"]["get"]["parameters"][0];
+/// assert_eq!(limit["$ref"], "./components/parameters.yaml#/limit");
+/// ```
+pub fn source_document(spec: &Spec) -> Value {
+    let raw = spec
+        .files()
+        .first()
+        .and_then(|path| load_document(path.to_str()?));
+    match raw {
+        Some(raw) if matches!(detect(&raw), Ok(Version::V30 | Version::V31)) => raw,
+        _ => spec.document().clone(),
+    }
+}
+
+impl Snapshot {
+    /// Write the document to the file, unless a newer snapshot was written
+    /// already. This blocks, and is meant to run off the async runtime.
+    pub fn write(self) -> std::io::Result<()> {
+        let mut written = self.written.lock().unwrap_or_else(|e| e.into_inner());
+        if *written >= self.version {
+            return Ok(());
+        }
+
+        let yaml = self
+            .path
+            .extension()
+            .is_some_and(|extension| extension == "yaml" || extension == "yml");
+        let contents = match yaml {
+            true => serde_yaml::to_string(&self.document).map_err(std::io::Error::other)?,
+            false => serde_json::to_string_pretty(&self.document)?,
+        };
+        std::fs::write(&self.path, contents)?;
+        *written = self.version;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::{document::load_document, spec::Spec};
+    use actix_web::test::TestRequest;
+
+    fn load_recorder(name: &str) -> Recorder {
+        let document = load_document("tests/testdata/petstore.yaml").unwrap();
+        let path = std::env::temp_dir().join(name);
+        Recorder::new(path, document, vec!["x-tenant".to_string()])
+    }
+
+    #[test]
+    fn test_example_name() {
+        let recorder = load_recorder("openapi-mocker-names.yaml");
+        let req = TestRequest::with_uri("/v1/pets?page=3").to_http_request();
+        assert_eq!(recorder.example_name(&req, "/pets"), "query:page=3");
+
+        let req = TestRequest::with_uri("/v1/pets/7")
+            .insert_header(("X-Tenant", "acme"))
+            .to_http_request();
+        assert_eq!(
+            recorder.example_name(&req, "/pets/7"),
+            "header:x-tenant=acme"
+        );
+
+        let req = TestRequest::with_uri("/v1/pets/7").to_http_request();
+        assert_eq!(recorder.example_name(&req, "/pets/7"), "/pets/7");
+    }
+
+    #[test]
+    fn test_record_replay() {
+        let mut recorder = load_recorder("openapi-mocker-record.yaml");
+        recorder
+            .record(&Recording {
+                key: "/pets/{petId}",
+                method: "get",
+                name: "/pets/7",
+                status: 200,
+                media_type: Some("application/json"),
+                body: br#"{"id": 7, "name": "recorded"}"#,
+            })
+            .write()
+            .unwrap();
+        recorder
+            .record(&Recording {
+                key: "/pets/{petId}",
+                method: "get",
+                name: "/pets/8",
+                status: 404,
+                media_type: Some("application/json"),
+                body: br#"{"code": 404, "message": "not found"}"#,
+            })
+            .write()
+            .unwrap();
+
+        let path = std::env::temp_dir().join("openapi-mocker-record.yaml");
+        let spec = Spec::from_path(path.to_str().unwrap()).unwrap();
+        let req = TestRequest::with_uri("/pets/7").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.value.unwrap()["name"], "recorded");
+
+        let req = TestRequest::with_uri("/pets/8").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.status, 404);
+        assert_eq!(example.value.unwrap()["message"], "not found");
+    }
+
+    #[test]
+    fn test_record_source_document() {
+        let spec = Spec::from_path("tests/testdata/openapi31.yaml").unwrap();
+        assert_eq!(source_document(&spec)["openapi"], "3.1.0");
+        let spec = Spec::from_path("tests/testdata/swagger.yaml").unwrap();
+        let document = source_document(&spec);
+        assert!(document.get("swagger").is_none());
+        assert_eq!(&document, spec.document());
+    }
+
+    #[test]
+    fn test_record_refresh() {
+        let path = std::env::temp_dir().join("openapi-mocker-refresh.yaml");
+        std::fs::copy("tests/testdata/petstore.yaml", &path).unwrap();
+        let spec = Spec::from_path(path.to_str().unwrap()).unwrap();
+        let recorded = std::env::temp_dir().join("openapi-mocker-refresh.recorded.yaml");
+        let mut recorder = Recorder::for_spec(recorded, &spec, Vec::new());
+        let _ = recorder.record(&Recording {
+            key: "/pets/{petId}",
+            method: "get",
+            name: "/pets/7",
+            status: 200,
+            media_type: Some("application/json"),
+            body: br#"{"id": 7, "name": "recorded"}"#,
+        });
+
+        // Another spec is not copied
+        recorder.refresh(&Spec::from_path("tests/testdata/routes.yaml").unwrap());
+        assert!(recorder.document()["paths"].get("/pets/mine").is_none());
+
+        std::fs::copy("tests/testdata/routes.yaml", &path).unwrap();
+        recorder.refresh(&Spec::from_path(path.to_str().unwrap()).unwrap());
+        let paths = &recorder.document()["paths"];
+        assert!(paths.get("/pets/mine").is_some());
+        let examples = &paths["/pets/{petId}"]["get"]["responses"]["200"]["content"]
+            ["application/json"]["examples"];
+        assert_eq!(examples["/pets/7"]["value"]["name"], "recorded");
+    }
+
+    #[test]
+    fn test_record_snapshot_order() {
+        let mut recorder = load_recorder("openapi-mocker-snapshots.yaml");
+        let recording = |name| Recording {
+            key: "/pets/{petId}",
+            method: "get",
+            name,
+            status: 200,
+            media_type: Some("application/json"),
+            body: br#"{"id": 7}"#,
+        };
+        let older = recorder.record(&recording("/pets/7"));
+        let newer = recorder.record(&recording("/pets/8"));
+        newer.write().unwrap();
+        older.write().unwrap();
+
+        let path = std::env::temp_dir().join("openapi-mocker-snapshots.yaml");
+        let document = load_document(path.to_str().unwrap()).unwrap();
+        let examples = &document["paths"]["/pets/{petId}"]["get"]["responses"]["200"]["content"]
+            ["application/json"]["examples"];
+        assert!(examples.get("/pets/8").is_some());
+    }
+}
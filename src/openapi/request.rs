@@ -0,0 +1,266 @@
+//! Validation of incoming requests against the parameters and request
+//! body declared for their operation.
+use actix_web::{http::header, web, HttpRequest};
+use serde_json::Value;
+
+use super::content::negotiate;
+use super::document::{self, resolve_ref};
+use super::router::RouteMatch;
+use super::validator::{escape_pointer, validate, Violation};
+
+/// Validate a request against its operation in the raw document.
+/// # Arguments
+/// * `document` - The raw OpenAPI document
+/// * `route` - The path matching the request, with its path parameters
+/// * `req` - The request to validate
+/// * `body` - The request body
+/// # Returns
+/// The violations found, empty if the request is valid or the operation
+/// is not declared
+pub fn validate_request(
+    document: &Value,
+    route: &RouteMatch,
+    req: &HttpRequest,
+    body: &[u8],
+) -> Vec<Violation> {
+    let method = req.method().as_str().to_lowercase();
+    let Some(path_item) = document::get(document, &["paths", route.key]) else {
+        return Vec::new();
+    };
+    let Some(operation) = path_item
+        .get(&method)
+        .and_then(|op| resolve_ref(document, op))
+    else {
+        return Vec::new();
+    };
+
+    let mut violations = Vec::new();
+    for parameter in load_parameters(document, path_item, operation) {
+        violations.extend(validate_parameter(document, parameter, route, req));
+    }
+    if let Some(request_body) = operation
+        .get("requestBody")
+        .and_then(|body| resolve_ref(document, body))
+    {
+        violations.extend(validate_body(document, request_body, req, body));
+    }
+    violations
+}
+
+/// Load the parameters of an operation, including the parameters declared
+/// on its path. Operation parameters override path parameters with the
+/// same name and location.
+pub fn load_parameters<'a>(
+    document: &'a Value,
+    path_item: &'a Value,
+    operation: &'a Value,
+) -> Vec<&'a Value> {
+    let declared = |item: &'a Value| -> Vec<&'a Value> {
+        item.get("parameters")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|parameter| resolve_ref(document, parameter))
+            .collect()
+    };
+    let key = |parameter: &Value| (parameter.get("name").cloned(), parameter.get("in").cloned());
+
+    let operation_parameters = declared(operation);
+    let mut parameters: Vec<&Value> = declared(path_item)
+        .into_iter()
+        .filter(|parameter| {
+            !operation_parameters
+                .iter()
+                .any(|p| key(p) == key(parameter))
+        })
+        .collect();
+    parameters.extend(operation_parameters);
+    parameters
+}
+
+fn validate_parameter(
+    document: &Value,
+    parameter: &Value,
+    route: &RouteMatch,
+    req: &HttpRequest,
+) -> Vec<Violation> {
+    let (Some(name), Some(location)) = (
+        parameter.get("name").and_then(Value::as_str),
+        parameter.get("in").and_then(Value::as_str),
+    ) else {
+        return Vec::new();
+    };
+    let pointer = format!("/{}/{}", location, escape_pointer(name));
+    let required = location == "path" || parameter.get("required") == Some(&Value::Bool(true));
+
+    let values = parameter_values(req, route, name, location);
+    if values.is_empty() {
+        if required {
+            let message = format!("missing required {} parameter {:?}", location, name);
+            return vec![Violation::new(&pointer, "required", message)];
+        }
+        return Vec::new();
+    }
+
+    match parameter.get("schema") {
+        Some(schema) => {
+            let value = coerce(document, schema, values);
+            validate(document, schema, &value, &pointer)
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Get the raw values of a parameter from a request.
+fn parameter_values(
+    req: &HttpRequest,
+    route: &RouteMatch,
+    name: &str,
+    location: &str,
+) -> Vec<String> {
+    match location {
+        "path" => route.param(name).map(str::to_string).into_iter().collect(),
+        "query" => web::Query::<Vec<(String, String)>>::from_query(req.query_string())
+            .map(|query| query.into_inner())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(key, _)| key == name)
+            .map(|(_, value)| value)
+            .collect(),
+        "header" => req
+            .headers()
+            .get_all(name)
+            .filter_map(|value| value.to_str().ok())
+            .map(str::to_string)
+            .collect(),
+        "cookie" => req
+            .cookie(name)
+            .map(|cookie| vec![cookie.value().to_string()])
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// Convert the raw values of a parameter to the type of its schema.
+/// Values that cannot be converted are kept as strings, so they are
+/// reported by the `type` validation.
+fn coerce(document: &Value, schema: &Value, values: Vec<String>) -> Value {
+    let schema = resolve_ref(document, schema).unwrap_or(schema);
+    match schema.get("type").and_then(Value::as_str) {
+        Some("array") => {
+            let values = match values.as_slice() {
+                [value] => value.split(',').map(str::to_string).collect(),
+                _ => values,
+            };
+            let items = schema.get("items").unwrap_or(&Value::Null);
+            let items = values
+                .into_iter()
+                .map(|value| coerce(document, items, vec![value]))
+                .collect();
+            Value::Array(items)
+        }
+        kind => {
+            let value = values.into_iter().next().unwrap_or_default();
+            let coerced = match kind {
+                Some("integer") => value.parse::<i64>().ok().map(Value::from),
+                Some("number") => value.parse::<f64>().ok().map(Value::from),
+                Some("boolean") => value.parse::<bool>().ok().map(Value::from),
+                _ => None,
+            };
+            coerced.unwrap_or(Value::String(value))
+        }
+    }
+}
+
+/// Validate a request body against the JSON schema of the request body.
+/// Bodies of media types other than JSON are only checked against the
+/// declared media types.
+fn validate_body(
+    document: &Value,
+    request_body: &Value,
+    req: &HttpRequest,
+    body: &[u8],
+) -> Vec<Violation> {
+    if body.is_empty() {
+        if request_body.get("required") == Some(&Value::Bool(true)) {
+            return vec![Violation::new(
+                "/body",
+                "required",
+                "missing required request body",
+            )];
+        }
+        return Vec::new();
+    }
+
+    let Some(content) = request_body.get("content").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    let declared: Vec<&str> = content.keys().map(String::as_str).collect();
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("application/json");
+    let Some(media_type) = negotiate(content_type, &declared) else {
+        let message = format!(
+            "unsupported media type {:?}, expected one of {:?}",
+            content_type, declared
+        );
+        return vec![Violation::new("/body", "contentType", message)];
+    };
+
+    let is_json = media_type
+        .split(';')
+        .next()
+        .is_some_and(|media_type| media_type.ends_with("/json") || media_type.ends_with("+json"));
+    let schema = content[media_type].get("schema");
+    match (is_json, schema) {
+        (true, Some(schema)) => match serde_json::from_slice::<Value>(body) {
+            Ok(value) => validate(document, schema, &value, "/body"),
+            Err(err) => vec![Violation::new(
+                "/body",
+                "json",
+                format!("invalid JSON: {}", err),
+            )],
+        },
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_coerce() {
+        let document = json!({});
+        let schema = json!({"type": "array", "items": {"type": "integer"}});
+        let values = vec!["1,2,x".to_string()];
+        assert_eq!(coerce(&document, &schema, values), json!([1, 2, "x"]));
+
+        let values = vec!["1".to_string(), "2".to_string()];
+        assert_eq!(coerce(&document, &schema, values), json!([1, 2]));
+
+        let schema = json!({"type": "boolean"});
+        assert_eq!(coerce(&document, &schema, vec!["true".into()]), json!(true));
+    }
+
+    #[test]
+    fn test_load_parameters_override() {
+        let document = json!({});
+        let path_item = json!({"parameters": [
+            {"name": "id", "in": "query", "required": true},
+            {"name": "x-id", "in": "header"}
+        ]});
+        let operation = json!({"parameters": [{"name": "id", "in": "query"}]});
+        let parameters = load_parameters(&document, &path_item, &operation);
+        assert_eq!(
+            parameters,
+            vec![
+                &json!({"name": "x-id", "in": "header"}),
+                &json!({"name": "id", "in": "query"})
+            ]
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use actix_web::http::{Method, StatusCode};
+
+/// A mock response picked from the spec for a request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockResponse {
+    /// The response key as declared in the spec, e.g. `200`, `4XX` or `default`.
+    pub key: String,
+    /// The HTTP status code to respond with.
+    pub status: StatusCode,
+    /// The example value, or None if the response declares no content.
+    pub value: Option<serde_json::Value>,
+    /// The response headers.
+    pub headers: Vec<(String, String)>,
+    /// The `Content-Type` of the value, or None if the response has no content.
+    pub content_type: Option<String>,
+    /// The media type the value was declared for in the spec, which may be
+    /// a range such as `image/*`. None if the response has no content.
+    pub media_type: Option<String>,
+    /// The name of the named example returned, or None if no named
+    /// example matched the request.
+    pub example: Option<String>,
+    /// Why the response and example were selected for the request, e.g.
+    /// the conditions of the matching examples. Only set when explaining
+    /// the selection, see `Spec::explain_example`.
+    pub reason: Option<String>,
+}
+
+/// The reasons no mock response can be returned for a request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockError {
+    /// No path or response in the spec matches the request.
+    NotFound,
+    /// The path exists, but does not declare the request method.
+    /// Holds the methods declared for the path.
+    MethodNotAllowed(Vec<Method>),
+    /// None of the media types declared for the response is acceptable.
+    /// Holds the declared media types.
+    NotAcceptable(Vec<String>),
+    /// The request selects a response or example the operation does not
+    /// declare. Holds the response keys along with the names of their
+    /// examples, None for a response without named examples.
+    ExampleNotFound(Vec<(String, Option<String>)>),
+}
+
+impl MockResponse {
+    /// Create a new MockResponse for a response key.
+    /// # Arguments
+    /// * `key` - The response key as declared in the spec
+    /// * `value` - The example value, or None if the response has no content
+    /// * `has_success` - Whether the operation declares a `2XX` response
+    /// # Returns
+    /// A MockResponse with the status code mapped from the key
+    /// # Example
+    /// ```rust
+    /// use actix_web::http::StatusCode;
+    /// use openapi_mocker::openapi::response::MockResponse;
+    /// let response = MockResponse::new("4XX", None, true);
+    /// assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    /// ```
+    pub fn new(key: &str, value: Option<serde_json::Value>, has_success: bool) -> Self {
+        Self {
+            key: key.to_string(),
+            status: status_code(key, has_success),
+            value,
+            headers: Vec::new(),
+            content_type: None,
+            media_type: None,
+            example: None,
+            reason: None,
+        }
+    }
+}
+
+/// Map a response key to a concrete HTTP status code.
+///
+/// Range keys such as `2XX` or `5XX` map to the first code of the range.
+/// The `default` key describes every undeclared status, so it maps to
+/// `500` when the operation already declares a success response, and
+/// to `200` when it is the only response of the operation.
+/// Keys that are not valid status codes are treated as `default`.
+pub fn status_code(key: &str, has_success: bool) -> StatusCode {
+    let key = key.to_uppercase();
+    let code = match key.strip_suffix("XX") {
+        Some(range) => range.parse::<u16>().ok().map(|range| range * 100),
+        None => key.parse::<u16>().ok(),
+    };
+
+    match code.and_then(|code| StatusCode::from_u16(code).ok()) {
+        Some(status) => status,
+        None if has_success => StatusCode::INTERNAL_SERVER_ERROR,
+        None => StatusCode::OK,
+    }
+}
+
+/// Check whether a response key describes a success response.
+pub fn is_success_key(key: &str) -> bool {
+    key.starts_with('2')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_code() {
+        assert_eq!(status_code("200", true), StatusCode::OK);
+        assert_eq!(status_code("401", true), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_status_code_range() {
+        assert_eq!(status_code("2XX", true), StatusCode::OK);
+        assert_eq!(status_code("4xx", true), StatusCode::BAD_REQUEST);
+        assert_eq!(status_code("5XX", true), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_status_code_default() {
+        assert_eq!(
+            status_code("default", true),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(status_code("default", false), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_is_success_key() {
+        assert!(is_success_key("201"));
+        assert!(is_success_key("2XX"));
+        assert!(!is_success_key("default"));
+        assert!(!is_success_key("404"));
+    }
+}
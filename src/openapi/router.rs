@@ -0,0 +1,288 @@
+//! Matching of request paths against the path templates of a spec.
+//!
+//! Routes are ranked by specificity, segment by segment from the left:
+//! a literal segment (`/pets/mine`) ranks over a segment embedding a
+//! template (`/files/{name}.json`), which ranks over a plain template
+//! (`/pets/{petId}`). The first route matching a path in that order wins,
+//! whatever the order the paths are declared in.
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use regex::Regex;
+
+/// A compiled set of path templates, stored as a trie of path segments.
+#[derive(Debug, Default)]
+pub struct Router {
+    root: Node,
+    routes: Vec<Route>,
+}
+
+/// A path template matching a request path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteMatch<'a> {
+    /// The path template as declared in the spec, e.g. `/pets/{petId}`.
+    pub key: &'a str,
+    /// The values of the path parameters, in the order of the template.
+    pub params: Vec<(String, String)>,
+    /// The request path matched, relative to the base path it was mounted under.
+    pub path: String,
+}
+
+impl RouteMatch<'_> {
+    /// Get the value of a path parameter.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug)]
+struct Route {
+    key: String,
+    /// The names of the path parameters, in the order of the template.
+    names: Vec<String>,
+}
+
+/// A node of the trie, reached by the segments of a path prefix.
+#[derive(Debug, Default)]
+struct Node {
+    /// The route ending at this node, as an index in `Router::routes`.
+    route: Option<usize>,
+    /// Children reached by a literal segment.
+    literals: HashMap<String, Node>,
+    /// Children reached by a segment embedding templates, most specific first.
+    templates: Vec<(Template, Node)>,
+    /// The child reached by a plain template segment.
+    param: Option<Box<Node>>,
+}
+
+/// A segment embedding templates within literal text, e.g. `{name}.{ext}`.
+#[derive(Debug)]
+struct Template {
+    source: String,
+    regex: Regex,
+    literal_len: usize,
+}
+
+#[derive(Debug)]
+enum Segment {
+    /// A segment without templates, matched exactly.
+    Literal(String),
+    /// A segment made of a single template, e.g. `{petId}`.
+    Param(String),
+    /// A segment embedding templates, with the names of its templates.
+    Template(Template, Vec<String>),
+}
+
+impl Segment {
+    fn parse(segment: &str) -> Self {
+        let mut names = Vec::new();
+        let mut pattern = String::from("^");
+        let mut literal = String::new();
+        let mut rest = segment;
+
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+                break;
+            };
+            literal.push_str(&rest[..start]);
+            pattern.push_str(&regex::escape(&rest[..start]));
+            pattern.push_str("(.+)");
+            names.push(rest[start + 1..end].to_string());
+            rest = &rest[end + 1..];
+        }
+        literal.push_str(rest);
+        pattern.push_str(&regex::escape(rest));
+        pattern.push('$');
+
+        match names.len() {
+            0 => Segment::Literal(segment.to_string()),
+            1 if literal.is_empty() => Segment::Param(names.remove(0)),
+            _ => match Regex::new(&pattern) {
+                Ok(regex) => {
+                    let template = Template {
+                        source: segment.to_string(),
+                        regex,
+                        literal_len: literal.len(),
+                    };
+                    Segment::Template(template, names)
+                }
+                Err(_) => Segment::Literal(segment.to_string()),
+            },
+        }
+    }
+}
+
+impl Node {
+    /// Get the child of a segment, inserting it if needed.
+    fn child(&mut self, segment: Segment, names: &mut Vec<String>) -> &mut Node {
+        match segment {
+            Segment::Literal(literal) => self.literals.entry(literal).or_default(),
+            Segment::Param(name) => {
+                names.push(name);
+                self.param.get_or_insert_with(Default::default)
+            }
+            Segment::Template(template, template_names) => {
+                names.extend(template_names);
+                let position = match self
+                    .templates
+                    .iter()
+                    .position(|(t, _)| t.source == template.source)
+                {
+                    Some(position) => position,
+                    None => {
+                        // Longer literal text is more specific
+                        let rank = |t: &Template| (Reverse(t.literal_len), t.source.clone());
+                        let position = self
+                            .templates
+                            .partition_point(|(t, _)| rank(t) < rank(&template));
+                        self.templates.insert(position, (template, Node::default()));
+                        position
+                    }
+                };
+                &mut self.templates[position].1
+            }
+        }
+    }
+
+    /// Find the route matching the remaining segments of a path, trying
+    /// literal children first, then templates, then plain templates.
+    /// The values of the path parameters are pushed to `values`.
+    fn find(&self, segments: &[&str], values: &mut Vec<String>) -> Option<usize> {
+        let Some((segment, rest)) = segments.split_first() else {
+            return self.route;
+        };
+
+        if let Some(route) = self
+            .literals
+            .get(*segment)
+            .and_then(|node| node.find(rest, values))
+        {
+            return Some(route);
+        }
+
+        let len = values.len();
+        for (template, node) in &self.templates {
+            if let Some(captures) = template.regex.captures(segment) {
+                let captured = captures.iter().skip(1);
+                values.extend(captured.map(|c| c.map_or("", |c| c.as_str()).to_string()));
+                if let Some(route) = node.find(rest, values) {
+                    return Some(route);
+                }
+                values.truncate(len);
+            }
+        }
+
+        if let Some(node) = &self.param {
+            values.push(segment.to_string());
+            if let Some(route) = node.find(rest, values) {
+                return Some(route);
+            }
+            values.truncate(len);
+        }
+        None
+    }
+}
+
+/// Split a path into its non empty segments.
+fn split(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+impl Router {
+    /// Compile a router from path templates.
+    /// # Arguments
+    /// * `keys` - The path templates, e.g. the keys of the spec `paths`
+    /// # Example
+    /// ```rust
+    /// use openapi_mocker::openapi::router::Router;
+    /// let router = Router::new(["/pets/{petId}", "/pets/mine", "/files/{name}.{ext}"]);
+    /// assert_eq!(router.find("/pets/mine").unwrap().key, "/pets/mine");
+    /// assert_eq!(router.find("/pets/1").unwrap().key, "/pets/{petId}");
+    ///
+    /// let route = router.find("/files/report.tar.gz").unwrap();
+    /// assert_eq!(route.param("name"), Some("report.tar"));
+    /// assert_eq!(route.param("ext"), Some("gz"));
+    /// ```
+    pub fn new<'a>(keys: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut keys: Vec<&str> = keys.into_iter().collect();
+        // Templates differing only by their parameter names share a node,
+        // the first key in order wins
+        keys.sort_unstable();
+
+        let mut router = Router::default();
+        for key in keys {
+            let mut names = Vec::new();
+            let mut node = &mut router.root;
+            for segment in split(key) {
+                node = node.child(Segment::parse(segment), &mut names);
+            }
+            if node.route.is_none() {
+                node.route = Some(router.routes.len());
+                router.routes.push(Route {
+                    key: key.to_string(),
+                    names,
+                });
+            }
+        }
+        router
+    }
+
+    /// Find the most specific route matching a request path.
+    /// Returns None if no route matches.
+    pub fn find(&self, path: &str) -> Option<RouteMatch<'_>> {
+        let segments: Vec<&str> = split(path).collect();
+        let mut values = Vec::new();
+        let route = &self.routes[self.root.find(&segments, &mut values)?];
+        Some(RouteMatch {
+            key: &route.key,
+            params: route.names.iter().cloned().zip(values).collect(),
+            path: path.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literal_over_template() {
+        let router = Router::new(["/{kind}/mine", "/pets/{petId}", "/pets/mine"]);
+        assert_eq!(router.find("/pets/mine").unwrap().key, "/pets/mine");
+        assert_eq!(router.find("/pets/1").unwrap().key, "/pets/{petId}");
+        assert_eq!(router.find("/users/mine").unwrap().key, "/{kind}/mine");
+    }
+
+    #[test]
+    fn test_leftmost_literal_wins() {
+        let router = Router::new(["/{a}/b", "/x/{c}"]);
+        let route = router.find("/x/b").unwrap();
+        assert_eq!(route.key, "/x/{c}");
+        assert_eq!(route.params, vec![("c".to_string(), "b".to_string())]);
+    }
+
+    #[test]
+    fn test_embedded_templates() {
+        let router = Router::new(["/files/{file}", "/files/{name}.{ext}", "/files/{name}.json"]);
+        assert_eq!(
+            router.find("/files/a.json").unwrap().key,
+            "/files/{name}.json"
+        );
+        assert_eq!(
+            router.find("/files/a.xml").unwrap().key,
+            "/files/{name}.{ext}"
+        );
+        assert_eq!(router.find("/files/readme").unwrap().key, "/files/{file}");
+    }
+
+    #[test]
+    fn test_segment_count() {
+        let router = Router::new(["/pets", "/pets/{petId}"]);
+        assert_eq!(router.find("/pets/").unwrap().key, "/pets");
+        assert!(router.find("/pets/1/photos").is_none());
+        assert!(router.find("/").is_none());
+    }
+}
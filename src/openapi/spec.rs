@@ -1,12 +1,31 @@
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-use actix_web::HttpRequest;
-use oas3::spec::{Example, MediaTypeExamples, ObjectOrReference, Operation, PathItem, Response};
+use actix_web::{
+    http::{header, Method},
+    HttpRequest,
+};
+
+use super::bundler::bundle_files;
+use super::collection::{infer_collections, Collection};
+use super::content::{content_type, negotiate};
+use super::directive::Directive;
+use super::index::{ContentEntry, ExampleEntry, Index, OperationEntry, ResponseEntry};
+use super::matcher::{Matcher, RequestParts};
+use super::request::validate_request;
+use super::response::{MockError, MockResponse};
+use super::router::RouteMatch;
+use super::swagger::convert;
+use super::validator::{validate, Violation};
+use super::version::{detect, normalize, Version};
+use super::webhook::Webhook;
 
 pub type SpecResult<T> = Result<T, Box<dyn std::error::Error>>;
 
 pub struct Spec {
-    spec: oas3::OpenApiV3Spec,
+    document: serde_json::Value,
+    index: Index,
+    /// The files the spec was loaded from, the spec file first.
+    files: Vec<PathBuf>,
 }
 
 impl Spec {
@@ -25,15 +44,165 @@ impl Spec {
     /// This will create a new Spec instance from the Petstore spec.
     /// You can then use the `get_example` method to get example responses.
     pub fn from_path(path: &str) -> SpecResult<Self> {
-        let spec = load_spec(path).ok_or("Failed to load spec")?;
-        Ok(Self { spec })
+        let (document, files) = bundle_files(Path::new(path))?;
+        let (document, spec) =
+            load_spec(document).map_err(|e| format!("Failed to load spec: {}", e))?;
+        let index = Index::build(&spec, &document)
+            .map_err(|e| format!("Invalid example rules in {}", e))?;
+        Ok(Self {
+            document,
+            index,
+            files,
+        })
+    }
+
+    /// Mount the operations under a base path, replacing the base paths
+    /// derived from the spec `servers`.
+    /// # Arguments
+    /// * `base_path` - The base path, e.g. `/api`
+    /// # Returns
+    /// The Spec serving its operations under the base path
+    /// # Example
+    /// ```rust
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/petstore.yaml")
+    ///     .unwrap()
+    ///     .with_base_path("/api");
+    /// assert_eq!(spec.base_paths(), ["/api"]);
+    /// assert_eq!(spec.match_path("/api/pets").unwrap().key, "/pets");
+    /// ```
+    pub fn with_base_path(self, base_path: &str) -> Self {
+        Self {
+            index: self.index.with_base_paths(vec![base_path.to_string()]),
+            ..self
+        }
+    }
+
+    /// Get the `operationId` of the operation declared for a path and method.
+    /// # Arguments
+    /// * `key` - The path as declared in the spec, e.g. `/pets/{petId}`
+    /// * `method` - The HTTP method
+    /// # Example
+    /// ```rust
+    /// use actix_web::http::Method;
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+    /// assert_eq!(spec.operation_id("/pets", &Method::GET), Some("listPets"));
+    /// ```
+    pub fn operation_id(&self, key: &str, method: &Method) -> Option<&str> {
+        self.index.operation(key, method)?.operation_id.as_deref()
+    }
+
+    /// Get the files the spec was loaded from: the spec file, followed by
+    /// the files it references.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Get the raw OpenAPI document the spec was loaded from.
+    pub fn document(&self) -> &serde_json::Value {
+        &self.document
+    }
+
+    /// Get the paths declared in the spec, sorted, with their methods.
+    /// # Example
+    /// ```rust
+    /// use actix_web::http::Method;
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+    /// let paths = spec.paths();
+    /// assert_eq!(paths[0], ("/pets", vec![Method::GET, Method::POST]));
+    /// ```
+    pub fn paths(&self) -> Vec<(&str, Vec<Method>)> {
+        let mut paths: Vec<(&str, Vec<Method>)> = self
+            .index
+            .paths()
+            .map(|key| (key, self.index.methods(key).unwrap_or_default()))
+            .collect();
+        paths.sort_by(|a, b| a.0.cmp(b.0));
+        paths
+    }
+
+    /// Get the base paths the operations are mounted under, longest first.
+    /// They are derived from the spec `servers`, expanding the server
+    /// variables with their `enum` values or their `default` value.
+    /// # Example
+    /// ```rust
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+    /// assert_eq!(spec.base_paths(), ["/v1"]);
+    /// ```
+    pub fn base_paths(&self) -> &[String] {
+        self.index.base_paths()
+    }
+
+    /// Mount the spec under a prefix, to serve several specs from one
+    /// server. The prefix comes ahead of the base paths of the spec.
+    /// # Arguments
+    /// * `prefix` - The prefix, e.g. `/users`
+    /// # Returns
+    /// The Spec serving its operations under the prefix
+    /// # Example
+    /// ```rust
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/petstore.yaml")
+    ///     .unwrap()
+    ///     .with_prefix("/store");
+    /// assert_eq!(spec.prefix(), "/store");
+    /// assert_eq!(spec.match_path("/store/v1/pets").unwrap().key, "/pets");
+    /// assert!(spec.match_path("/pets").is_none());
+    /// ```
+    pub fn with_prefix(self, prefix: &str) -> Self {
+        Self {
+            index: self.index.with_prefix(prefix),
+            ..self
+        }
+    }
+
+    /// Get the prefix the spec is mounted under, or an empty string.
+    pub fn prefix(&self) -> &str {
+        self.index.prefix()
+    }
+
+    /// Get the webhooks declared by an OpenAPI 3.1 spec, sorted by name.
+    /// # Example
+    /// ```rust
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/openapi31.yaml").unwrap();
+    /// let webhook = &spec.webhooks()[0];
+    /// assert_eq!(webhook.name, "newPet");
+    /// assert_eq!(webhook.payload(None).unwrap()["name"], "doggie");
+    /// ```
+    pub fn webhooks(&self) -> &[Webhook] {
+        self.index.webhooks()
     }
 
     /// Get an example response for a request.
     /// # Arguments
     /// * `req` - The HTTP request
     /// # Returns
-    /// An example response as a JSON value
+    /// A MockResponse with the example value and the status code
+    /// of the response the example was declared in.
+    /// If no example matches the request, the value is generated from the
+    /// schema of the first success response, or is None if that response
+    /// declares no content.
+    ///
+    /// The response headers declared in the spec are set from their
+    /// `example`, their first `examples` entry, or generated from their
+    /// schema. A named example can pin header values with the
+    /// `x-mock-headers` extension.
+    ///
+    /// The media type of the response is negotiated with the request
+    /// `Accept` header, among the media types declared for each response.
+    ///
+    /// The request can select the response and example to return with a
+    /// directive instead, see `Directive`.
+    /// # Errors
+    /// - `MockError::NotFound` if no path or response matches the request
+    /// - `MockError::MethodNotAllowed` if the path does not declare the method
+    /// - `MockError::NotAcceptable` if no declared media type is acceptable
+    /// - `MockError::ExampleNotFound` if the directive selects a response or
+    ///   example the operation does not declare
     /// # Example
     /// ```rust
     /// use actix_web::test::TestRequest;
@@ -51,7 +220,7 @@ impl Spec {
     /// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
     /// let req = TestRequest::with_uri("/pets/2").to_http_request();
     /// let example = spec.get_example(&req).unwrap();
-    /// assert_eq!(example["id"], serde_json::Value::Number(serde_json::Number::from(2)));
+    /// assert_eq!(example.value.unwrap()["id"], serde_json::Value::Number(serde_json::Number::from(2)));
     /// ```
     ///
     /// # Example with query parameters
@@ -60,122 +229,380 @@ impl Spec {
     /// use openapi_mocker::openapi::spec::Spec;
     /// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
     /// let req = TestRequest::with_uri("/pets?page=1").to_http_request();
-    /// let examples = spec.get_example(&req).unwrap();
-    /// let example = examples.as_array().unwrap().get(0).unwrap();
+    /// let examples = spec.get_example(&req).unwrap().value.unwrap();
+    /// let example = examples.as_array().unwrap().first().unwrap();
     /// assert_eq!(example["id"], serde_json::Value::Number(serde_json::Number::from(1)));
     /// ```
-    pub fn get_example(&self, req: &HttpRequest) -> Option<serde_json::Value> {
-        let path = req.uri().path();
-        let method = req.method().as_str().to_lowercase();
-        let media_type = "application/json";
-
-        Some(&self.spec)
-            .and_then(load_path(path))
-            .and_then(load_method(&method))
-            .and_then(load_responses())
-            .and_then(load_examples(&self.spec, media_type))
-            .and_then(find_example_match(req))
-            .and_then(|example| example.resolve(&self.spec).ok())
-            .and_then(|example| example.value)
+    ///
+    /// # Example with content negotiation
+    /// ```rust
+    /// use actix_web::test::TestRequest;
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+    /// let req = TestRequest::with_uri("/pets")
+    ///     .insert_header(("accept", "text/plain"))
+    ///     .to_http_request();
+    /// let example = spec.get_example(&req).unwrap();
+    /// assert_eq!(example.content_type.unwrap(), "text/plain");
+    /// ```
+    ///
+    /// # Example with a non-success response
+    /// ```rust
+    /// use actix_web::{http::StatusCode, test::TestRequest};
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+    /// let req = TestRequest::with_uri("/pets/5").to_http_request();
+    /// let example = spec.get_example(&req).unwrap();
+    /// assert_eq!(example.key, "401");
+    /// assert_eq!(example.status, StatusCode::UNAUTHORIZED);
+    /// ```
+    ///
+    /// # Example with a directive
+    /// ```rust
+    /// use actix_web::test::TestRequest;
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+    /// let req = TestRequest::with_uri("/pets/1")
+    ///     .insert_header(("prefer", "code=200, example=/pets/2"))
+    ///     .to_http_request();
+    /// let example = spec.get_example(&req).unwrap();
+    /// assert_eq!(example.value.unwrap()["name"], "kitty");
+    /// ```
+    pub fn get_example(&self, req: &HttpRequest) -> Result<MockResponse, MockError> {
+        self.get_example_with_body(req, &[])
     }
-}
 
-fn load_spec(path: &str) -> Option<oas3::OpenApiV3Spec> {
-    match oas3::from_path(path) {
-        Ok(spec) => Some(spec),
-        Err(_) => None,
+    /// Get the example response for a request, matching the named
+    /// examples against the request body as well, see `get_example`.
+    /// # Arguments
+    /// * `req` - The request
+    /// * `body` - The request body
+    /// # Example
+    /// ```rust
+    /// use actix_web::test::TestRequest;
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/matching.yaml").unwrap();
+    /// let req = TestRequest::post().uri("/pets").to_http_request();
+    /// let example = spec.get_example_with_body(&req, br#"{"kind": "cat"}"#).unwrap();
+    /// assert_eq!(example.example.as_deref(), Some("body:/kind=cat"));
+    /// ```
+    pub fn get_example_with_body(
+        &self,
+        req: &HttpRequest,
+        body: &[u8],
+    ) -> Result<MockResponse, MockError> {
+        let directive = Directive::from_request(req);
+        self.select_example(req, body, directive.as_ref(), false)
     }
-}
 
-fn load_path<'a>(path: &'a str) -> impl Fn(&oas3::OpenApiV3Spec) -> Option<PathItem> + 'a {
-    move |spec: &oas3::OpenApiV3Spec| {
-        spec.paths
-            .iter()
-            .find(|(key, _)| match_url(path, &[*key]))
-            .map(|(_, value)| value.clone())
+    /// Get the example response for a request along with the reason it
+    /// was selected, see `get_example_with_body`. The reason is only built
+    /// on request, as it lists the conditions of all the matching examples.
+    /// # Arguments
+    /// * `req` - The request
+    /// * `body` - The request body
+    /// # Example
+    /// ```rust
+    /// use actix_web::test::TestRequest;
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+    /// let req = TestRequest::with_uri("/pets").to_http_request();
+    /// let example = spec.explain_example(&req, &[]).unwrap();
+    /// assert_eq!(
+    ///     example.reason.as_deref(),
+    ///     Some("no example matched, default example of response 200")
+    /// );
+    /// assert!(spec.get_example(&req).unwrap().reason.is_none());
+    /// ```
+    pub fn explain_example(
+        &self,
+        req: &HttpRequest,
+        body: &[u8],
+    ) -> Result<MockResponse, MockError> {
+        let directive = Directive::from_request(req);
+        self.select_example(req, body, directive.as_ref(), true)
+    }
+
+    /// Get the example response for a request, selected by a directive
+    /// rather than matched against the request, see `get_example`.
+    /// # Arguments
+    /// * `req` - The request
+    /// * `body` - The request body
+    /// * `directive` - The response and example to select, or None to match
+    ///   the examples against the request
+    /// # Example
+    /// ```rust
+    /// use actix_web::{http::StatusCode, test::TestRequest};
+    /// use openapi_mocker::openapi::{directive::Directive, response::MockError, spec::Spec};
+    /// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+    /// let req = TestRequest::with_uri("/pets/1").to_http_request();
+    /// let directive = Directive {
+    ///     code: Some("401".to_string()),
+    ///     example: None,
+    /// };
+    /// let example = spec.get_selected_example(&req, &[], Some(&directive)).unwrap();
+    /// assert_eq!(example.status, StatusCode::UNAUTHORIZED);
+    ///
+    /// let directive = Directive {
+    ///     code: Some("404".to_string()),
+    ///     example: None,
+    /// };
+    /// let error = spec.get_selected_example(&req, &[], Some(&directive));
+    /// assert!(matches!(error, Err(MockError::ExampleNotFound(_))));
+    /// ```
+    pub fn get_selected_example(
+        &self,
+        req: &HttpRequest,
+        body: &[u8],
+        directive: Option<&Directive>,
+    ) -> Result<MockResponse, MockError> {
+        self.select_example(req, body, directive, false)
     }
-}
 
-fn match_url(url: &str, routes: &[&str]) -> bool {
-    let url_parts: Vec<&str> = url.split('/').filter(|s| !s.is_empty()).collect();
+    /// Select the example response for a request, along with the reason
+    /// it was selected if `explain` is set.
+    fn select_example(
+        &self,
+        req: &HttpRequest,
+        body: &[u8],
+        directive: Option<&Directive>,
+        explain: bool,
+    ) -> Result<MockResponse, MockError> {
+        let accept = req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .unwrap_or("*/*");
 
-    for route in routes {
-        let route_parts: Vec<&str> = route.split('/').filter(|s| !s.is_empty()).collect();
-        if url_parts.len() == route_parts.len()
-            && route_parts
-                .iter()
-                .zip(url_parts.iter())
-                .all(|(r, u)| r.starts_with('{') && r.ends_with('}') || r == u)
-        {
-            return true;
+        let route = self.index.find(req.path()).ok_or(MockError::NotFound)?;
+        let operation = self
+            .index
+            .operation(route.key, req.method())
+            .ok_or_else(|| {
+                MockError::MethodNotAllowed(self.index.methods(route.key).unwrap_or_default())
+            })?;
+
+        let parts = RequestParts::new(req, &route.path, body).with_params(route.params.clone());
+        let selection = match directive {
+            Some(directive) => select_directed(directive, accept, explain)(operation)?,
+            None => match find_example_match(&parts, accept, explain)(operation) {
+                Some(selection) => selection,
+                None => select_fallback(accept, explain)(operation)?,
+            },
+        };
+
+        let mut headers = selection.response.headers.clone();
+        for (name, value) in selection.example.iter().flat_map(|e| &e.headers) {
+            headers.retain(|(header, _)| !header.eq_ignore_ascii_case(name));
+            headers.push((name.clone(), value.clone()));
         }
-    }
-    false
-}
 
-fn load_method<'a>(method: &'a str) -> impl Fn(PathItem) -> Option<Operation> + 'a {
-    move |path: PathItem| match method {
-        "get" => path.get.clone(),
-        "put" => path.put.clone(),
-        "post" => path.post.clone(),
-        "delete" => path.delete.clone(),
-        "options" => path.options.clone(),
-        "head" => path.head.clone(),
-        "patch" => path.patch.clone(),
-        "trace" => path.trace.clone(),
-        _ => None,
+        let value = match (selection.example, selection.content) {
+            (Some(example), _) => Some(example.value.clone()),
+            (None, Some(content)) => Some(content.value.clone()),
+            (None, None) => None,
+        };
+        let media_type = selection.content.map(|c| c.media_type.clone());
+        let content_type = media_type.as_ref().map(|m| content_type(accept, m));
+
+        Ok(MockResponse {
+            headers,
+            example: selection.example.map(|example| example.name.clone()),
+            reason: selection.reason,
+            content_type,
+            media_type,
+            ..MockResponse::new(&selection.response.key, value, operation.has_success)
+        })
     }
-}
 
-fn load_responses<'a>() -> impl Fn(Operation) -> Option<Vec<ObjectOrReference<Response>>> + 'a {
-    move |op: Operation| {
-        let mut responses = Vec::new();
-        for (_, response) in op.responses.iter() {
-            responses.push(response.clone());
+    /// Validate a mock response against the schema of the response and
+    /// media type it was picked from.
+    /// # Arguments
+    /// * `req` - The request the response was picked for
+    /// * `response` - The mock response returned by `get_example`
+    /// # Returns
+    /// The violations found, rooted at `/body`, empty if the value is valid
+    /// or no schema is declared for it
+    /// # Example
+    /// ```rust
+    /// use actix_web::test::TestRequest;
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/validation.yaml").unwrap();
+    /// let req = TestRequest::with_uri("/pets?status=sold").to_http_request();
+    /// let example = spec.get_example(&req).unwrap();
+    /// let violations = spec.validate_response(&req, &example);
+    /// assert_eq!(violations[0].pointer, "/body/0/name");
+    /// ```
+    pub fn validate_response(&self, req: &HttpRequest, response: &MockResponse) -> Vec<Violation> {
+        let (Some(route), Some(media_type), Some(value)) = (
+            self.index.find(req.path()),
+            &response.media_type,
+            &response.value,
+        ) else {
+            return Vec::new();
+        };
+
+        let schema = self
+            .index
+            .operation(route.key, req.method())
+            .and_then(|operation| operation.response(&response.key))
+            .and_then(|response| response.content(media_type))
+            .and_then(|content| content.schema.as_ref());
+        match schema {
+            Some(schema) => validate(&self.document, schema, value, "/body"),
+            None => Vec::new(),
         }
-        Some(responses)
     }
-}
 
-fn load_examples<'a>(
-    spec: &'a oas3::OpenApiV3Spec,
-    media_type: &'a str,
-) -> impl Fn(Vec<ObjectOrReference<Response>>) -> Option<Vec<MediaTypeExamples>> + 'a {
-    move |responses: Vec<ObjectOrReference<Response>>| {
-        let mut examples = Vec::new();
-        for response in responses {
-            extract_response(response, spec)
-                .as_ref()
-                .and_then(|r| r.content.get(media_type))
-                .and_then(|content| content.examples.as_ref())
-                .map(|media_type| examples.push(media_type.clone()));
+    /// Get the HTTP methods declared for a request path.
+    /// # Arguments
+    /// * `path` - The request path
+    /// # Returns
+    /// The methods declared in the spec for the matching path,
+    /// or None if no path matches.
+    /// # Example
+    /// ```rust
+    /// use actix_web::http::Method;
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+    /// let methods = spec.allowed_methods("/pets").unwrap();
+    /// assert_eq!(methods, vec![Method::GET, Method::POST]);
+    /// ```
+    pub fn allowed_methods(&self, path: &str) -> Option<Vec<Method>> {
+        self.index
+            .find(path)
+            .and_then(|route| self.index.methods(route.key))
+    }
+
+    /// Find the path template matching a request path.
+    /// Literal segments rank over templated ones, whatever the order the
+    /// paths are declared in.
+    /// # Arguments
+    /// * `path` - The request path
+    /// # Returns
+    /// The matching path template with the values of its path parameters,
+    /// or None if no path matches.
+    /// # Example
+    /// ```rust
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+    /// let route = spec.match_path("/pets/123").unwrap();
+    /// assert_eq!(route.key, "/pets/{petId}");
+    /// assert_eq!(route.param("petId"), Some("123"));
+    /// ```
+    pub fn match_path(&self, path: &str) -> Option<RouteMatch<'_>> {
+        self.index.find(path)
+    }
+
+    /// Infer the collections of the spec for the stateful mode, from the
+    /// paths shaped as `/pets` and `/pets/{petId}`.
+    /// # Returns
+    /// The collections, seeded with the items of their examples
+    /// # Example
+    /// ```rust
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+    /// let collections = spec.collections();
+    /// assert_eq!(collections[0].path, "/pets");
+    /// assert_eq!(collections[0].item_path, "/pets/{petId}");
+    /// ```
+    pub fn collections(&self) -> Vec<Collection> {
+        infer_collections(&self.index, &self.document)
+    }
+
+    /// Validate a request against the parameters and request body declared
+    /// for its operation.
+    /// # Arguments
+    /// * `req` - The request to validate
+    /// * `body` - The request body
+    /// # Returns
+    /// The violations found, empty if the request is valid or does not
+    /// match any operation
+    /// # Example
+    /// ```rust
+    /// use actix_web::test::TestRequest;
+    /// use openapi_mocker::openapi::spec::Spec;
+    /// let spec = Spec::from_path("tests/testdata/validation.yaml").unwrap();
+    /// let req = TestRequest::with_uri("/pets?limit=abc").to_http_request();
+    /// let violations = spec.validate_request(&req, &[]);
+    /// assert_eq!(violations[0].pointer, "/query/limit");
+    /// assert_eq!(violations[0].keyword, "type");
+    /// ```
+    pub fn validate_request(&self, req: &HttpRequest, body: &[u8]) -> Vec<Violation> {
+        match self.index.find(req.path()) {
+            Some(route) => validate_request(&self.document, &route, req, body),
+            None => Vec::new(),
         }
-        Some(examples)
     }
 }
 
-fn extract_response(
-    response: ObjectOrReference<Response>,
-    spec: &oas3::OpenApiV3Spec,
-) -> Option<Response> {
-    match response {
-        ObjectOrReference::Object(response) => Some(response),
-        ObjectOrReference::Ref { ref_path } => {
-            let components = &spec.components;
-            components
-                .as_ref()
-                .and_then(|components| components.responses.get(&ref_path).cloned())
-                .and_then(|resp| extract_response(resp, spec))
+/// Check whether a request matches the rules an example name is a
+/// shorthand for, e.g. `query:name=value` or `header:name=value`.
+/// # Arguments
+/// * `req` - The request
+/// * `route` - The path of the spec matching the request, if any, whose
+///   parameters the `param:` rules are matched against
+/// * `body` - The request body, matched by the `body:` rules
+/// * `example_name` - The example name
+/// # Example
+/// ```rust
+/// use actix_web::test::TestRequest;
+/// use openapi_mocker::openapi::spec::{match_example_name, Spec};
+/// let req = TestRequest::with_uri("/pets?page=1").to_http_request();
+/// assert!(match_example_name(&req, None, &[], "query:page=1"));
+/// assert!(!match_example_name(&req, None, &[], "header:x-api-key=123"));
+///
+/// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+/// let req = TestRequest::post().uri("/pets/2").to_http_request();
+/// let route = spec.match_path(req.path());
+/// assert!(match_example_name(&req, route.as_ref(), &[], "param:petId=2"));
+/// assert!(match_example_name(&req, None, br#"{"kind": "cat"}"#, "body:/kind=cat"));
+/// ```
+pub fn match_example_name(
+    req: &HttpRequest,
+    route: Option<&RouteMatch>,
+    body: &[u8],
+    example_name: &str,
+) -> bool {
+    let parts = match route {
+        Some(route) => RequestParts::new(req, &route.path, body).with_params(route.params.clone()),
+        None => RequestParts::new(req, req.path(), body),
+    };
+    Matcher::from_name(example_name)
+        .ok()
+        .flatten()
+        .is_some_and(|matcher| matcher.matches(&parts))
+}
+
+/// Parse the spec from the bundled document, normalizing an OpenAPI 3.1
+/// document to the shape of 3.0 the `oas3` structures target.
+///
+/// A Swagger 2.0 document is served as its OpenAPI 3.0 conversion, so the
+/// converted document is returned along with the spec.
+fn load_spec(
+    mut document: serde_json::Value,
+) -> SpecResult<(serde_json::Value, oas3::OpenApiV3Spec)> {
+    let normalized = match detect(&document)? {
+        Version::Swagger2 => {
+            document = convert(&document);
+            None
         }
-    }
+        Version::V30 => None,
+        Version::V31 => Some(normalize(&document)),
+    };
+    let parsed = normalized.as_ref().unwrap_or(&document);
+    let spec = oas3::from_reader(serde_json::to_vec(parsed)?.as_slice())?;
+    Ok((document, spec))
 }
 
 /// Find the example that matches the request.
 ///
-/// It matches the examples by comparing the request path, query,
-/// and headers with the example name.
-/// If the example name matches the request path, it returns the example.
-/// If the example name does not match the request path, it returns None.
+/// The examples are matched against the request by the rules they
+/// declare, in their name or their `x-mock-match` and `x-mock-body`
+/// extensions, see the `matcher` module. The example satisfying the most
+/// conditions is returned, the first declared one on a tie. If none
+/// matches, the example named `default` is returned, the last declared one
+/// if several responses declare it.
 ///
 /// # Matching exact route
 /// If the example name is the same as the request path, it returns the example.
@@ -187,191 +614,510 @@ fn extract_response(
 /// - Example name: `/pets`
 /// - Request path: `/pets/123`
 /// - Returns None
+///
+/// Under a base path, the example name is matched against the request path
+/// relative to the base path as well: `/pets/2` matches a `/v1/pets/2` request.
+///
+/// # Scoring
+/// An example named `query:limit=1&page=1` satisfies two conditions, and is
+/// preferred over an example named `query:page=1` for a `?page=1&limit=1`
+/// request, whatever their order.
+///
+/// The matched example is returned along with the response and the
+/// content it was declared in. Only the content of the media type
+/// negotiated with the `Accept` header is matched for each response.
 fn find_example_match<'a>(
-    req: &'a HttpRequest,
-) -> impl Fn(Vec<MediaTypeExamples>) -> Option<ObjectOrReference<Example>> {
-    let path = req.uri().path().to_string();
-    let query = QueryMatcher::from_request(req);
-    let headers = HeaderMatcher::from_request(req);
-
-    move |examples: Vec<MediaTypeExamples>| {
-        let mut default: Option<ObjectOrReference<Example>> = None;
-        for example in examples {
-            match example {
-                MediaTypeExamples::Examples { examples } => {
-                    for (example_name, e) in examples.iter() {
-                        // Match exact path
-                        if example_name == &path {
-                            return Some(e.clone());
-                        }
-
-                        // Match query parameters
-                        if query.match_example(&example_name) {
-                            return Some(e.clone());
-                        }
-
-                        // Match headers
-                        if headers.match_example(&example_name) {
-                            return Some(e.clone());
-                        }
-
-                        // Match default example
-                        if example_name == "default" {
-                            default = Some(e.clone());
-                        }
+    parts: &'a RequestParts,
+    accept: &'a str,
+    explain: bool,
+) -> impl Fn(&'a OperationEntry) -> Option<Selection<'a>> + 'a {
+    move |operation: &'a OperationEntry| {
+        let mut default: Option<Selection> = None;
+        let mut best: Option<(usize, Selection)> = None;
+        let mut candidates = Vec::new();
+        for response in &operation.responses {
+            let Some(content) =
+                negotiate(accept, &response.media_types()).and_then(|m| response.content(m))
+            else {
+                continue;
+            };
+            for example in &content.examples {
+                let selection = Selection {
+                    response,
+                    content: Some(content),
+                    example: Some(example),
+                    reason: None,
+                };
+
+                // Match the rules of the example
+                let score = example.matcher.as_ref().and_then(|m| m.score(parts));
+                if let Some(score) = score {
+                    if explain {
+                        candidates.push(format!("{} ({})", example.name, score));
+                    }
+                    if best.as_ref().is_none_or(|(best, _)| score > *best) {
+                        best = Some((score, selection));
                     }
+                    continue;
+                }
+
+                // Match default example, the last one declared wins
+                if example.name == "default" {
+                    default = Some(Selection {
+                        reason: explain.then(|| {
+                            format!(
+                                "no example matched, default example of response {}",
+                                response.key
+                            )
+                        }),
+                        ..selection
+                    });
                 }
-                _ => {}
             }
         }
-        default
+
+        match best {
+            Some((score, selection)) => Some(Selection {
+                reason: explain.then(|| {
+                    format!(
+                        "example {} of response {} satisfied the most conditions ({}), \
+                         matched: {}",
+                        selection.example.map_or("", |example| &example.name),
+                        selection.response.key,
+                        score,
+                        candidates.join(", ")
+                    )
+                }),
+                ..selection
+            }),
+            None => default,
+        }
     }
 }
 
-struct QueryMatcher {
-    params: HashMap<String, String>,
+/// Select the response returned when no example matches the request:
+/// the first success response, or the `default` response.
+///
+/// Its content is the media type `example`, the schema `example` composed
+/// from the property examples, or a value generated from the schema.
+/// The content is None if the response declares no content.
+/// Returns `MockError::NotAcceptable` if the response declares content,
+/// but none of its media types is acceptable.
+fn select_fallback<'a>(
+    accept: &'a str,
+    explain: bool,
+) -> impl Fn(&'a OperationEntry) -> Result<Selection<'a>, MockError> + 'a {
+    move |operation: &'a OperationEntry| {
+        let response = operation
+            .fallback
+            .map(|fallback| &operation.responses[fallback])
+            .ok_or(MockError::NotFound)?;
+        let reason =
+            explain.then(|| format!("no example matched, fallback response {}", response.key));
+        select_content(accept, response, reason, false)
+    }
 }
 
-impl QueryMatcher {
-    fn from_request(req: &HttpRequest) -> Self {
-        let mut params = HashMap::new();
-        for (key, value) in req.query_string().split('&').map(|pair| {
-            let mut split = pair.split('=');
-            (split.next().unwrap(), split.next().unwrap_or(""))
-        }) {
-            params.insert(key.to_string(), value.to_string());
-        }
-        Self { params }
-    }
-
-    fn match_example(&self, example_name: &str) -> bool {
-        if example_name.starts_with("query:") {
-            let query = example_name.trim_start_matches("query:");
-            let mut query_params = HashMap::new();
-            for pair in query.split('&').map(|pair| {
-                let mut split = pair.split('=');
-                (split.next().unwrap(), split.next().unwrap_or(""))
-            }) {
-                query_params.insert(pair.0.to_string(), pair.1.to_string());
-            }
-            query_params
+/// Select the response and example named by a directive. A directive
+/// naming only the response selects its first example, or its content as
+/// for `select_fallback`. The response key is compared case-insensitively.
+/// A named example is selected in the media type negotiated with the
+/// `Accept` header among the media types declaring it.
+///
+/// Returns `MockError::ExampleNotFound` listing the declared examples if
+/// the operation declares no such response or example, or
+/// `MockError::NotAcceptable` if the example is declared, but in none of
+/// the acceptable media types.
+fn select_directed<'a>(
+    directive: &'a Directive,
+    accept: &'a str,
+    explain: bool,
+) -> impl Fn(&'a OperationEntry) -> Result<Selection<'a>, MockError> + 'a {
+    move |operation: &'a OperationEntry| {
+        let reason = explain.then(|| format!("selected by the request: {}", directive.describe()));
+        let not_found = || MockError::ExampleNotFound(declared_examples(operation));
+        let mut responses = operation.responses.iter().filter(|response| {
+            let code = directive.code.as_ref();
+            code.is_none_or(|code| response.key.eq_ignore_ascii_case(code))
+        });
+
+        let Some(name) = &directive.example else {
+            let response = responses.next().ok_or_else(not_found)?;
+            return select_content(accept, response, reason, true);
+        };
+        let mut unacceptable: Vec<String> = Vec::new();
+        for response in responses {
+            let declaring: Vec<(&ContentEntry, &ExampleEntry)> = response
+                .content
+                .iter()
+                .filter_map(|c| Some((c, c.examples.iter().find(|e| e.name == *name)?)))
+                .collect();
+            let available: Vec<&str> = declaring
                 .iter()
-                .all(|(key, value)| self.params.get(key).map_or(false, |v| v == value))
-        } else {
-            false
+                .map(|(c, _)| c.media_type.as_str())
+                .collect();
+            let Some(media_type) = negotiate(accept, &available) else {
+                for media_type in available {
+                    if !unacceptable.iter().any(|m| m == media_type) {
+                        unacceptable.push(media_type.to_string());
+                    }
+                }
+                continue;
+            };
+            if let Some((content, example)) = declaring
+                .into_iter()
+                .find(|(c, _)| c.media_type == media_type)
+            {
+                return Ok(Selection {
+                    response,
+                    content: Some(content),
+                    example: Some(example),
+                    reason,
+                });
+            }
+        }
+        match unacceptable.is_empty() {
+            true => Err(not_found()),
+            false => Err(MockError::NotAcceptable(unacceptable)),
         }
     }
 }
 
-struct HeaderMatcher {
-    headers: HashMap<String, String>,
+/// Select the content of a response in the media type negotiated with the
+/// `Accept` header, along with its first named example if `first_example`
+/// is set. The content is None if the response declares no content.
+/// Returns `MockError::NotAcceptable` if the response declares content,
+/// but none of its media types is acceptable.
+fn select_content<'a>(
+    accept: &str,
+    response: &'a ResponseEntry,
+    reason: Option<String>,
+    first_example: bool,
+) -> Result<Selection<'a>, MockError> {
+    if response.content.is_empty() {
+        return Ok(Selection {
+            response,
+            content: None,
+            example: None,
+            reason,
+        });
+    }
+
+    let available = response.media_types();
+    let content = negotiate(accept, &available)
+        .and_then(|media_type| response.content(media_type))
+        .ok_or_else(|| {
+            MockError::NotAcceptable(available.iter().map(|m| m.to_string()).collect())
+        })?;
+    Ok(Selection {
+        response,
+        content: Some(content),
+        example: content.examples.first().filter(|_| first_example),
+        reason,
+    })
 }
 
-impl HeaderMatcher {
-    fn from_request(req: &HttpRequest) -> Self {
-        let headers = req
-            .headers()
-            .iter()
-            .map(|(key, value)| {
-                (
-                    key.as_str().to_string(),
-                    value.to_str().unwrap_or("").to_string(),
-                )
-            })
-            .collect();
-        Self { headers }
-    }
-
-    fn match_example(&self, example_name: &str) -> bool {
-        if example_name.starts_with("header:") {
-            let header = example_name.trim_start_matches("header:");
-            let mut header_params = HashMap::new();
-            for pair in header.split('&').map(|pair| {
-                let mut split = pair.split('=');
-                (split.next().unwrap(), split.next().unwrap_or(""))
-            }) {
-                header_params.insert(pair.0.to_string(), pair.1.to_string());
+/// List the responses of an operation along with the names of their
+/// examples, in all media types, or None for a response without named
+/// examples.
+fn declared_examples(operation: &OperationEntry) -> Vec<(String, Option<String>)> {
+    let mut declared = Vec::new();
+    for response in &operation.responses {
+        let mut names: Vec<&str> = Vec::new();
+        for example in response.content.iter().flat_map(|c| &c.examples) {
+            if !names.contains(&example.name.as_str()) {
+                names.push(&example.name);
             }
-            header_params
-                .iter()
-                .all(|(key, value)| self.headers.get(key).map_or(false, |v| v == value))
-        } else {
-            false
+        }
+        match names.is_empty() {
+            true => declared.push((response.key.clone(), None)),
+            false => declared.extend(
+                names
+                    .into_iter()
+                    .map(|name| (response.key.clone(), Some(name.to_string()))),
+            ),
         }
     }
+    declared
+}
+
+/// The response content selected for a request.
+struct Selection<'a> {
+    response: &'a ResponseEntry,
+    /// The content of the negotiated media type, or None if the response
+    /// has no content.
+    content: Option<&'a ContentEntry>,
+    /// The matched named example, or None if no named example matched.
+    example: Option<&'a ExampleEntry>,
+    /// Why the response was selected, or None if not explaining.
+    reason: Option<String>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use actix_web::test::TestRequest;
+    use actix_web::{http::StatusCode, test::TestRequest};
 
     #[test]
     fn test_load_spec() {
-        let spec = load_spec("tests/testdata/petstore.yaml");
-        assert_eq!(spec.unwrap().openapi, "3.0.0");
+        let (document, _) = bundle_files(Path::new("tests/testdata/petstore.yaml")).unwrap();
+        let (_, spec) = load_spec(document).unwrap();
+        assert_eq!(spec.openapi, "3.0.0");
     }
 
     #[test]
     fn test_load_path() {
-        let path = load_spec("tests/testdata/petstore.yaml")
-            .as_ref()
-            .and_then(load_path("/pets"));
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let path = spec.match_path("/pets");
         assert!(path.is_some());
     }
 
     #[test]
     fn test_load_path_not_found() {
-        let path = load_spec("tests/testdata/petstore.yaml")
-            .as_ref()
-            .and_then(load_path("/notfound"));
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let path = spec.match_path("/notfound");
         assert!(path.is_none());
     }
 
     #[test]
     fn test_load_path_with_params() {
-        let path = load_spec("tests/testdata/petstore.yaml")
-            .as_ref()
-            .and_then(load_path("/pets/{petId}"));
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let path = spec.match_path("/pets/{petId}");
         assert!(path.is_some());
     }
 
     #[test]
     fn test_load_path_with_dynamic_params() {
-        let path = load_spec("tests/testdata/petstore.yaml")
-            .as_ref()
-            .and_then(load_path("/pets/123"));
-        assert!(path.is_some());
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let path = spec.match_path("/pets/123").unwrap();
+        assert_eq!(path.key, "/pets/{petId}");
+        assert_eq!(path.param("petId"), Some("123"));
     }
 
     #[test]
     fn test_load_method() {
-        let method = load_spec("tests/testdata/petstore.yaml")
-            .as_ref()
-            .and_then(load_path("/pets"))
-            .and_then(load_method("get"));
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let path = spec.match_path("/pets").unwrap();
+        let method = spec.index.operation(path.key, &Method::GET);
         assert!(method.is_some());
     }
 
     #[test]
     fn test_load_method_not_found() {
-        let method = load_spec("tests/testdata/petstore.yaml")
-            .as_ref()
-            .and_then(load_path("/pets"))
-            .and_then(load_method("notfound"));
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let path = spec.match_path("/pets").unwrap();
+        let notfound = Method::from_bytes(b"NOTFOUND").unwrap();
+        let method = spec.index.operation(path.key, &notfound);
         assert!(method.is_none());
     }
 
+    #[test]
+    fn test_load_method_post() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let path = spec.match_path("/pets").unwrap();
+        let method = spec.index.operation(path.key, &Method::POST);
+        assert!(method.is_some());
+    }
+
+    #[test]
+    fn test_load_allowed_methods() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let path = spec.match_path("/pets/123").unwrap();
+        let methods = spec.index.methods(path.key).unwrap();
+        assert_eq!(methods, vec![Method::GET, Method::PUT, Method::DELETE]);
+    }
+
     #[test]
     fn test_load_examples() {
-        let spec = load_spec("tests/testdata/petstore.yaml").unwrap();
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let path = spec.match_path("/pets").unwrap();
+        let operation = spec.index.operation(path.key, &Method::GET).unwrap();
+        let examples = operation
+            .responses
+            .iter()
+            .find_map(|response| response.content("application/json"))
+            .map(|content| &content.examples);
+        assert!(examples.is_some_and(|examples| !examples.is_empty()));
+    }
+
+    #[test]
+    fn test_spec_openapi_31() {
+        let spec = Spec::from_path("tests/testdata/openapi31.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets?tag=cat").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.example.as_deref(), Some("query:tag=cat"));
+
+        // Generated from the `examples` and `const` of the schemas
+        let req = TestRequest::with_uri("/v1/pets/1").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(
+            example.value.unwrap(),
+            serde_json::json!({"id": 1, "name": "doggie", "kind": "pet"})
+        );
+        let req = TestRequest::with_uri("/pets").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(
+            example.headers,
+            vec![("x-rate-limit".to_string(), "100".to_string())]
+        );
 
-        let example = Some(&spec)
-            .and_then(load_path("/pets"))
-            .and_then(load_method("get"))
-            .and_then(load_responses())
-            .and_then(load_examples(&spec, "application/json"));
-        assert!(example.is_some());
+        let req = TestRequest::with_uri("/pets?limit=0").to_http_request();
+        let violations = spec.validate_request(&req, b"");
+        assert_eq!(violations[0].keyword, "exclusiveMinimum");
+
+        let error = Spec::from_path("tests/testdata/bundle/schemas/pet.yaml")
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("declares no openapi version"));
+    }
+
+    #[test]
+    fn test_spec_swagger_2() {
+        let spec = Spec::from_path("tests/testdata/swagger.yaml").unwrap();
+        assert_eq!(spec.base_paths(), ["/v2"]);
+        let req = TestRequest::with_uri("/v2/pets").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.value.unwrap()[0]["name"], "doggie");
+        assert_eq!(
+            example.headers,
+            vec![("x-rate-limit".to_string(), "100".to_string())]
+        );
+        let req = TestRequest::with_uri("/v2/pets?tag=cat").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.example.as_deref(), Some("query:tag=cat"));
+
+        // The global response referenced by the operation
+        let req = TestRequest::with_uri("/v2/pets/1").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.value.unwrap()["name"], "doggie");
+
+        let req = TestRequest::with_uri("/v2/pets?limit=500").to_http_request();
+        let violations = spec.validate_request(&req, b"");
+        assert_eq!(violations[0].keyword, "maximum");
+    }
+
+    #[test]
+    fn test_spec_match_body() {
+        let spec = Spec::from_path("tests/testdata/matching.yaml").unwrap();
+        let req = TestRequest::post().uri("/pets").to_http_request();
+        let example = |body: &str| spec.get_example_with_body(&req, body.as_bytes()).unwrap();
+
+        assert_eq!(
+            example(r#"{"kind": "cat"}"#).value.unwrap()["name"],
+            "kitty"
+        );
+        assert_eq!(example(r#"{"name": "batman"}"#).value.unwrap()["id"], 3);
+        let owned = example(r#"{"kind": "bird", "owner": {"id": 1, "name": "jon"}}"#);
+        assert_eq!(owned.example.as_deref(), Some("owned"));
+        let unknown = example(r#"{"kind": "unknown", "name": "x"}"#);
+        assert_eq!(unknown.status, StatusCode::BAD_REQUEST);
+
+        // No rule matches, or the body is not JSON
+        let example = example(r#"{"kind": "bird"}"#);
+        assert_eq!(example.example.as_deref(), Some("default"));
+        let example = spec.get_example_with_body(&req, b"kind=cat").unwrap();
+        assert_eq!(example.example.as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn test_spec_match_extension() {
+        let spec = Spec::from_path("tests/testdata/matching.yaml").unwrap();
+        let example = |req: TestRequest| {
+            let req = req.to_http_request();
+            spec.get_example(&req).unwrap().example.unwrap()
+        };
+
+        let first_page =
+            TestRequest::with_uri("/pets?page=1").insert_header(("x-api-key", "key-1"));
+        assert_eq!(example(first_page), "firstPage");
+        let debug =
+            TestRequest::with_uri("/pets?page=1&debug=true").insert_header(("x-api-key", "key-1"));
+        assert_eq!(example(debug), "default");
+
+        assert_eq!(example(TestRequest::with_uri("/pets?kind=kitten")), "cats");
+        let cookie = TestRequest::with_uri("/pets").insert_header(("cookie", "kind=cat"));
+        assert_eq!(example(cookie), "cats");
+        let keyed = TestRequest::with_uri("/pets?kind=cat").insert_header(("x-api-key", "123"));
+        assert_eq!(example(keyed), "default");
+    }
+
+    #[test]
+    fn test_spec_invalid_rules() {
+        let error = Spec::from_path("tests/testdata/invalid_rules.yaml")
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            error.starts_with("Invalid example rules in GET /pets, response 200, example broken:"),
+            "{}",
+            error
+        );
+
+        // Empty rules would match every request
+        let error = Spec::from_path("tests/testdata/empty_rules.yaml")
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            error.ends_with("example everything: x-mock-match must declare at least one condition"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_spec_match_params_and_cookies() {
+        let spec = Spec::from_path("tests/testdata/matching.yaml").unwrap();
+        let example = |req: TestRequest| {
+            let req = req.to_http_request();
+            spec.get_example(&req).unwrap().example.unwrap()
+        };
+
+        assert_eq!(example(TestRequest::with_uri("/pets/2")), "param:petId=2");
+        assert_eq!(example(TestRequest::with_uri("/pets/12")), "tens");
+        assert_eq!(example(TestRequest::with_uri("/pets/3")), "default");
+        let admin = TestRequest::with_uri("/pets/3").insert_header(("cookie", "session=admin"));
+        assert_eq!(example(admin), "cookie:session=admin");
+
+        // On a tie, the example of the response declared first wins
+        let req = TestRequest::with_uri("/pets/99").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.example.as_deref(), Some("gone"));
+        assert_eq!(example.status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_spec_last_default_example() {
+        let spec = Spec::from_path("tests/testdata/matching.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets/3").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.example.as_deref(), Some("default"));
+        assert_eq!(example.status, StatusCode::OK);
+        assert_eq!(example.value.unwrap()["name"], "doggie");
+    }
+
+    #[test]
+    fn test_spec_bundled() {
+        let spec = Spec::from_path("tests/testdata/bundle/openapi.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets/1").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.value.unwrap()["name"], "doggie");
+
+        // The response referenced from another file is found
+        let req = TestRequest::with_uri("/pets/2").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.status, StatusCode::OK);
+        let req = TestRequest::with_uri("/pets").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(
+            example.headers,
+            vec![("x-rate-limit".to_string(), "100".to_string())]
+        );
+
+        let error = Spec::from_path("tests/testdata/bundle/unresolved.yaml")
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("./schemas/pet.yaml#/Missing"));
     }
 
     #[test]
@@ -379,7 +1125,7 @@ mod tests {
         let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
         let req = TestRequest::with_uri("/pets").to_http_request();
         let example = spec.get_example(&req);
-        assert!(example.is_some());
+        assert!(example.is_ok());
     }
 
     #[test]
@@ -387,7 +1133,7 @@ mod tests {
         let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
         let req = TestRequest::with_uri("/pets/123").to_http_request();
         let example = spec.get_example(&req);
-        assert!(example.is_some());
+        assert!(example.is_ok());
     }
 
     #[test]
@@ -397,18 +1143,36 @@ mod tests {
         let example = spec.get_example(&req).unwrap();
 
         assert_eq!(
-            example["id"],
+            example.value.unwrap()["id"],
             serde_json::Value::Number(serde_json::Number::from(2))
         );
     }
 
+    #[test]
+    fn test_spec_with_put_method() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let req = TestRequest::put().uri("/pets/1").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(
+            example.value.unwrap()["name"],
+            serde_json::Value::String("updated doggie".to_string())
+        );
+    }
+
+    #[test]
+    fn test_spec_allowed_methods_not_found() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        assert!(spec.allowed_methods("/notfound").is_none());
+    }
+
     #[test]
     fn test_spec_match_query_params() {
         let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
         let req = TestRequest::with_uri("/pets?page=1").to_http_request();
         let res = spec.get_example(&req).unwrap();
 
-        let example = res.as_array().unwrap().get(0).unwrap();
+        let value = res.value.unwrap();
+        let example = value.as_array().unwrap().first().unwrap();
         assert_eq!(
             example["id"],
             serde_json::Value::Number(serde_json::Number::from(1))
@@ -421,23 +1185,90 @@ mod tests {
         let req = TestRequest::with_uri("/pets?page=1&limit=1").to_http_request();
         let res = spec.get_example(&req).unwrap();
 
-        let examples = res.as_array().unwrap();
+        let value = res.value.unwrap();
+        let examples = value.as_array().unwrap();
         assert_eq!(examples.len(), 1,);
 
-        let example = examples.get(0).unwrap();
+        let example = examples.first().unwrap();
         assert_eq!(
             example["id"],
             serde_json::Value::Number(serde_json::Number::from(1))
         );
     }
 
+    #[test]
+    fn test_spec_match_most_conditions() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets?page=1&limit=1").to_http_request();
+        let example = spec.explain_example(&req, &[]).unwrap();
+        assert_eq!(example.example.as_deref(), Some("query:limit=1&page=1"));
+        assert_eq!(
+            example.reason.unwrap(),
+            "example query:limit=1&page=1 of response 200 satisfied the most conditions (2), \
+             matched: query:page=1 (1), query:limit=1&page=1 (2)"
+        );
+
+        let req = TestRequest::with_uri("/pets").to_http_request();
+        let example = spec.explain_example(&req, &[]).unwrap();
+        assert_eq!(
+            example.reason.unwrap(),
+            "no example matched, default example of response 200"
+        );
+    }
+
+    #[test]
+    fn test_spec_directive() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let example = |req: TestRequest| spec.explain_example(&req.to_http_request(), &[]);
+
+        let req = TestRequest::with_uri("/pets/1").insert_header(("x-mock-status", "401"));
+        let example401 = example(req).unwrap();
+        assert_eq!(example401.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(example401.example.as_deref(), Some("/pets/5"));
+        assert_eq!(
+            example401.reason.unwrap(),
+            "selected by the request: code=401"
+        );
+
+        let req = TestRequest::with_uri("/pets/2?__example=header:x-api-key=123");
+        assert_eq!(example(req).unwrap().value.unwrap()["name"], "batty");
+
+        // The directive wins over the examples matching the request
+        let req = TestRequest::with_uri("/pets?page=1")
+            .insert_header(("prefer", "code=200, example=default"));
+        assert_eq!(example(req).unwrap().value.unwrap(), serde_json::json!([]));
+
+        let req =
+            TestRequest::with_uri("/pets/1").insert_header(("prefer", "code=401, example=default"));
+        let Err(MockError::ExampleNotFound(declared)) = example(req) else {
+            panic!("expected the declared examples");
+        };
+        assert_eq!(
+            declared[..2],
+            [
+                ("200".to_string(), Some("default".to_string())),
+                ("200".to_string(), Some("/pets/2".to_string())),
+            ]
+        );
+        assert!(declared.contains(&("401".to_string(), Some("/pets/5".to_string()))));
+
+        // The example exists, but in none of the acceptable media types
+        let req = TestRequest::with_uri("/pets")
+            .insert_header(("accept", "text/plain"))
+            .insert_header(("x-mock-example", "query:page=1"));
+        assert_eq!(
+            example(req).unwrap_err(),
+            MockError::NotAcceptable(vec!["application/json".to_string()])
+        );
+    }
+
     #[test]
     fn test_spec_prefer_path_over_query_params() {
         let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
         let req = TestRequest::with_uri("/pets/2?term=dog").to_http_request();
         let example = spec.get_example(&req).unwrap();
         assert_eq!(
-            example["id"],
+            example.value.unwrap()["id"],
             serde_json::Value::Number(serde_json::Number::from(2))
         );
     }
@@ -450,7 +1281,7 @@ mod tests {
             .to_http_request();
         let example = spec.get_example(&req).unwrap();
         assert_eq!(
-            example["id"],
+            example.value.unwrap()["id"],
             serde_json::Value::Number(serde_json::Number::from(4))
         );
     }
@@ -464,18 +1295,314 @@ mod tests {
             .to_http_request();
         let example = spec.get_example(&req).unwrap();
         assert_eq!(
-            example["id"],
+            example.value.unwrap()["id"],
             serde_json::Value::Number(serde_json::Number::from(4))
         );
     }
 
+    #[test]
+    fn test_spec_response_without_content() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets")
+            .method(Method::POST)
+            .to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.status, StatusCode::CREATED);
+        assert!(example.value.is_none());
+    }
+
+    #[test]
+    fn test_spec_generate_from_ref_schema() {
+        let spec = Spec::from_path("tests/testdata/generated.yaml").unwrap();
+        let req = TestRequest::with_uri("/users/1").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.status, StatusCode::OK);
+        assert_eq!(
+            example.value.unwrap(),
+            serde_json::json!({
+                "id": "00000000-0000-4000-8000-000000000000",
+                "email": "user@example.com",
+                "role": "admin",
+                "age": 18,
+                "code": "AA-000",
+                "tags": ["string", "string"],
+                "manager": null
+            })
+        );
+    }
+
+    #[test]
+    fn test_spec_generate_recursive_schema() {
+        let spec = Spec::from_path("tests/testdata/recursive.yaml").unwrap();
+        let req = TestRequest::with_uri("/nodes").to_http_request();
+        assert_eq!(
+            spec.get_example(&req).unwrap().value,
+            Some(serde_json::json!([null]))
+        );
+        let req = TestRequest::with_uri("/trees").to_http_request();
+        assert_eq!(
+            spec.get_example(&req).unwrap().value,
+            Some(serde_json::json!({"id": 0}))
+        );
+    }
+
+    #[test]
+    fn test_spec_media_type_example() {
+        let spec = Spec::from_path("tests/testdata/generated.yaml").unwrap();
+        let req = TestRequest::with_uri("/users/me").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.value.unwrap()["email"], "me@example.com");
+    }
+
+    #[test]
+    fn test_spec_schema_example() {
+        let spec = Spec::from_path("tests/testdata/generated.yaml").unwrap();
+        let req = TestRequest::with_uri("/status").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.value.unwrap(), serde_json::json!({"status": "ok"}));
+    }
+
+    #[test]
+    fn test_spec_property_examples() {
+        let spec = Spec::from_path("tests/testdata/generated.yaml").unwrap();
+        let req = TestRequest::with_uri("/users/1/profile").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(
+            example.value.unwrap(),
+            serde_json::json!({
+                "bio": "Hello there",
+                "links": ["https://example.com/jane"],
+                "settings": {"theme": "dark"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_spec_headers() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(
+            example.headers,
+            vec![
+                ("x-next".to_string(), "string".to_string()),
+                ("x-rate-limit".to_string(), "100".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spec_example_headers() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets?page=1").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(
+            example.headers,
+            vec![
+                ("x-rate-limit".to_string(), "100".to_string()),
+                ("x-next".to_string(), "/pets?page=2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spec_header_ref() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets/5").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(
+            example.headers,
+            vec![("retry-after".to_string(), "30".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_spec_method_not_allowed() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets")
+            .method(Method::PATCH)
+            .to_http_request();
+        let error = spec.get_example(&req).unwrap_err();
+        assert_eq!(
+            error,
+            MockError::MethodNotAllowed(vec![Method::GET, Method::POST])
+        );
+    }
+
+    #[test]
+    fn test_spec_not_found() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let req = TestRequest::with_uri("/notfound").to_http_request();
+        assert_eq!(spec.get_example(&req).unwrap_err(), MockError::NotFound);
+    }
+
+    #[test]
+    fn test_spec_negotiate_media_type() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets")
+            .insert_header(("accept", "text/*;q=0.9, application/json;q=0.5"))
+            .to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.content_type.unwrap(), "text/plain");
+        assert_eq!(example.value.unwrap(), serde_json::json!("string"));
+    }
+
+    #[test]
+    fn test_spec_negotiate_named_examples() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets?page=1")
+            .insert_header(("accept", "application/json"))
+            .to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.content_type.unwrap(), "application/json");
+        assert_eq!(example.example.unwrap(), "query:page=1");
+        assert_eq!(example.value.unwrap().as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_spec_not_acceptable() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets/1")
+            .insert_header(("accept", "application/xml"))
+            .to_http_request();
+        let error = spec.get_example(&req).unwrap_err();
+        assert_eq!(
+            error,
+            MockError::NotAcceptable(vec!["application/json".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_spec_validate_request_parameters() {
+        let spec = Spec::from_path("tests/testdata/validation.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets/abc?status=lost")
+            .insert_header(("x-request-id", "req-1"))
+            .to_http_request();
+        let violations: Vec<(String, String)> = spec
+            .validate_request(&req, &[])
+            .into_iter()
+            .map(|v| (v.pointer, v.keyword))
+            .collect();
+        assert_eq!(
+            violations,
+            vec![
+                ("/path/petId".to_string(), "type".to_string()),
+                ("/header/x-request-id".to_string(), "pattern".to_string()),
+                ("/query/status".to_string(), "enum".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spec_validate_request_body() {
+        let spec = Spec::from_path("tests/testdata/validation.yaml").unwrap();
+        let req = TestRequest::post()
+            .uri("/pets")
+            .insert_header(("content-type", "application/json"))
+            .to_http_request();
+        let violations = spec.validate_request(&req, br#"{"tag": "bird", "age": -1}"#);
+        let violations: Vec<&str> = violations.iter().map(|v| v.pointer.as_str()).collect();
+        assert_eq!(violations, vec!["/body/name", "/body/tag", "/body/age"]);
+
+        let violations = spec.validate_request(&req, br#"{"name": "doggie", "tag": "dog"}"#);
+        assert!(violations.is_empty());
+
+        let violations = spec.validate_request(&req, &[]);
+        assert_eq!(violations[0].keyword, "required");
+
+        let violations = spec.validate_request(&req, b"{");
+        assert_eq!(violations[0].keyword, "json");
+    }
+
+    #[test]
+    fn test_spec_validate_request_content_type() {
+        let spec = Spec::from_path("tests/testdata/validation.yaml").unwrap();
+        let req = TestRequest::post()
+            .uri("/pets")
+            .insert_header(("content-type", "text/plain"))
+            .to_http_request();
+        let violations = spec.validate_request(&req, b"doggie");
+        assert_eq!(violations[0].keyword, "contentType");
+    }
+
+    #[test]
+    fn test_spec_validate_response() {
+        let spec = Spec::from_path("tests/testdata/validation.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets?status=sold").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        let violations: Vec<(String, String)> = spec
+            .validate_response(&req, &example)
+            .into_iter()
+            .map(|v| (v.pointer, v.keyword))
+            .collect();
+        assert_eq!(
+            violations,
+            vec![
+                ("/body/0/name".to_string(), "required".to_string()),
+                ("/body/0/tag".to_string(), "enum".to_string()),
+            ]
+        );
+
+        let req = TestRequest::with_uri("/pets").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert!(spec.validate_response(&req, &example).is_empty());
+    }
+
+    #[test]
+    fn test_spec_literal_path_over_template() {
+        let spec = Spec::from_path("tests/testdata/routes.yaml").unwrap();
+        let req = TestRequest::with_uri("/pets/mine").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.value.unwrap()["name"], "my doggie");
+
+        let req = TestRequest::with_uri("/pets/1").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.value.unwrap()["name"], "doggie");
+    }
+
+    #[test]
+    fn test_spec_embedded_path_template() {
+        let spec = Spec::from_path("tests/testdata/routes.yaml").unwrap();
+        let route = spec.match_path("/files/report.tar.gz").unwrap();
+        assert_eq!(route.key, "/files/{name}.{ext}");
+        assert_eq!(route.param("name"), Some("report.tar"));
+        assert_eq!(route.param("ext"), Some("gz"));
+
+        let req = TestRequest::with_uri("/files/report.csv").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.value.unwrap()["size"], 128);
+    }
+
+    #[test]
+    fn test_spec_server_base_paths() {
+        let spec = Spec::from_path("tests/testdata/routes.yaml").unwrap();
+        assert_eq!(spec.base_paths(), ["/v1", "/v2"]);
+
+        let req = TestRequest::with_uri("/v2/pets/mine").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.value.unwrap()["name"], "my doggie");
+
+        let req = TestRequest::with_uri("/v3/pets/mine").to_http_request();
+        assert_eq!(spec.get_example(&req).unwrap_err(), MockError::NotFound);
+    }
+
+    #[test]
+    fn test_spec_base_path_example_match() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let req = TestRequest::with_uri("/v1/pets/2").to_http_request();
+        let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.value.unwrap()["id"], 2);
+    }
+
     #[test]
     fn test_match_401_response() {
         let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
         let req = TestRequest::with_uri("/pets/5").to_http_request();
         let example = spec.get_example(&req).unwrap();
+        assert_eq!(example.key, "401");
+        assert_eq!(example.status, StatusCode::UNAUTHORIZED);
         assert_eq!(
-            example["code"],
+            example.value.unwrap()["code"],
             serde_json::Value::Number(serde_json::Number::from(401))
         );
     }
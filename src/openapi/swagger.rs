@@ -0,0 +1,656 @@
+//! Conversion of Swagger 2.0 documents to OpenAPI 3.0.
+//!
+//! A Swagger 2.0 document is converted when it is loaded, so that the
+//! matching and serving logic is the same as for OpenAPI documents:
+//! - `host`, `basePath` and `schemes` become the `servers`.
+//! - `definitions` become the `components.schemas`, and the global
+//!   `responses` the `components.responses`.
+//! - `body` and `formData` parameters become the `requestBody`, declared
+//!   for the media types the operation `consumes`.
+//! - Response schemas and `examples`, keyed by media type, become the
+//!   response `content`, declared for the media types the operation
+//!   `produces`. A response `x-examples` extension, mapping example names
+//!   to values, becomes the named examples of each media type. The example
+//!   keyed by the media type is then the `default` named example.
+use serde_json::{json, Map, Value};
+
+/// The media type of operations declaring neither `produces` nor `consumes`.
+const DEFAULT_MEDIA_TYPE: &str = "application/json";
+
+/// The parameter fields copied to the schema of an OpenAPI parameter.
+const SCHEMA_FIELDS: [&str; 16] = [
+    "type",
+    "format",
+    "items",
+    "enum",
+    "default",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "minItems",
+    "maxItems",
+    "uniqueItems",
+    "multipleOf",
+];
+
+/// The methods of a path item.
+const METHODS: [&str; 7] = ["get", "put", "post", "delete", "options", "head", "patch"];
+
+/// Convert a Swagger 2.0 document to OpenAPI 3.0.
+/// # Arguments
+/// * `document` - The raw Swagger 2.0 document
+/// # Returns
+/// The equivalent OpenAPI 3.0 document
+/// # Example
+/// ```rust
+/// use serde_json::json;
+/// use openapi_mocker::openapi::swagger::convert;
+/// let document = convert(&json!({
+///     "swagger": "2.0",
+///     "info": {"title": "Pets", "version": "1.0.0"},
+///     "basePath": "/v1",
+///     "paths": {},
+///     "definitions": {"Pet": {"type": "object"}}
+/// }));
+/// assert_eq!(document["openapi"], "3.0.3");
+/// assert_eq!(document["servers"][0]["url"], "/v1");
+/// assert_eq!(document["components"]["schemas"]["Pet"]["type"], "object");
+/// ```
+pub fn convert(document: &Value) -> Value {
+    let mut converted = Map::new();
+    converted.insert("openapi".to_string(), json!("3.0.3"));
+    for (key, value) in fields(document) {
+        if ["info", "tags", "security", "externalDocs"].contains(&key.as_str())
+            || key.starts_with("x-")
+        {
+            converted.insert(key.clone(), value.clone());
+        }
+    }
+    converted.insert("servers".to_string(), convert_servers(document));
+
+    let produces = media_types(document, "produces");
+    let consumes = media_types(document, "consumes");
+    let paths: Map<String, Value> = fields(document.get("paths").unwrap_or(&Value::Null))
+        .map(|(key, path)| {
+            let path = convert_path(document, path, &produces, &consumes);
+            (key.clone(), path)
+        })
+        .collect();
+    converted.insert("paths".to_string(), Value::Object(paths));
+
+    let mut components = Map::new();
+    let schemas: Map<String, Value> = fields(document.get("definitions").unwrap_or(&Value::Null))
+        .map(|(name, schema)| (name.clone(), convert_schema(schema)))
+        .collect();
+    if !schemas.is_empty() {
+        components.insert("schemas".to_string(), Value::Object(schemas));
+    }
+    let responses: Map<String, Value> = fields(document.get("responses").unwrap_or(&Value::Null))
+        .map(|(name, response)| (name.clone(), convert_response(response, &produces)))
+        .collect();
+    if !responses.is_empty() {
+        components.insert("responses".to_string(), Value::Object(responses));
+    }
+    let schemes: Map<String, Value> =
+        fields(document.get("securityDefinitions").unwrap_or(&Value::Null))
+            .map(|(name, scheme)| (name.clone(), convert_security_scheme(scheme)))
+            .collect();
+    if !schemes.is_empty() {
+        components.insert("securitySchemes".to_string(), Value::Object(schemes));
+    }
+    if !components.is_empty() {
+        converted.insert("components".to_string(), Value::Object(components));
+    }
+
+    Value::Object(converted)
+}
+
+/// Iterate the fields of an object, none if the value is not an object.
+fn fields(value: &Value) -> impl Iterator<Item = (&String, &Value)> {
+    value.as_object().into_iter().flatten()
+}
+
+/// Get the media types of a `produces` or `consumes` field.
+fn media_types(value: &Value, field: &str) -> Option<Vec<String>> {
+    let media_types = value.get(field)?.as_array()?;
+    Some(
+        media_types
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Build the servers from the `host`, `basePath` and `schemes`.
+fn convert_servers(document: &Value) -> Value {
+    let base_path = document
+        .get("basePath")
+        .and_then(Value::as_str)
+        .unwrap_or("/");
+    let urls: Vec<String> = match document.get("host").and_then(Value::as_str) {
+        Some(host) => media_types(document, "schemes")
+            .filter(|schemes| !schemes.is_empty())
+            .unwrap_or_else(|| vec!["http".to_string()])
+            .iter()
+            .map(|scheme| format!("{}://{}{}", scheme, host, base_path))
+            .collect(),
+        None => vec![base_path.to_string()],
+    };
+    urls.into_iter().map(|url| json!({ "url": url })).collect()
+}
+
+fn convert_path(
+    document: &Value,
+    path: &Value,
+    produces: &Option<Vec<String>>,
+    consumes: &Option<Vec<String>>,
+) -> Value {
+    let shared = parameters(document, path);
+    let mut converted = Map::new();
+    for (key, value) in fields(path) {
+        if METHODS.contains(&key.as_str()) {
+            let operation = convert_operation(document, value, &shared, produces, consumes);
+            converted.insert(key.clone(), operation);
+        } else if ["summary", "description"].contains(&key.as_str()) || key.starts_with("x-") {
+            converted.insert(key.clone(), value.clone());
+        }
+    }
+    Value::Object(converted)
+}
+
+/// Get the parameters declared by a path or an operation, resolving the
+/// references to the global `parameters`.
+fn parameters(document: &Value, item: &Value) -> Vec<Value> {
+    let declared = item.get("parameters").and_then(Value::as_array);
+    declared
+        .into_iter()
+        .flatten()
+        .filter_map(
+            |parameter| match parameter.get("$ref").and_then(Value::as_str) {
+                Some(reference) => {
+                    let name = reference.strip_prefix("#/parameters/")?;
+                    document.get("parameters")?.get(name).cloned()
+                }
+                None => Some(parameter.clone()),
+            },
+        )
+        .collect()
+}
+
+fn convert_operation(
+    document: &Value,
+    operation: &Value,
+    shared: &[Value],
+    produces: &Option<Vec<String>>,
+    consumes: &Option<Vec<String>>,
+) -> Value {
+    let produces = media_types(operation, "produces")
+        .or(produces.clone())
+        .unwrap_or_else(|| vec![DEFAULT_MEDIA_TYPE.to_string()]);
+    let consumes = media_types(operation, "consumes")
+        .or(consumes.clone())
+        .unwrap_or_else(|| vec![DEFAULT_MEDIA_TYPE.to_string()]);
+
+    let mut converted = Map::new();
+    for (key, value) in fields(operation) {
+        let copied = [
+            "operationId",
+            "summary",
+            "description",
+            "tags",
+            "deprecated",
+            "security",
+            "externalDocs",
+        ];
+        if copied.contains(&key.as_str()) || key.starts_with("x-") {
+            converted.insert(key.clone(), value.clone());
+        }
+    }
+
+    // Operation parameters override the path parameters of the same name
+    let declared = parameters(document, operation);
+    let same = |a: &Value, b: &Value| a.get("name") == b.get("name") && a.get("in") == b.get("in");
+    let mut parameters: Vec<Value> = shared
+        .iter()
+        .filter(|parameter| !declared.iter().any(|d| same(d, parameter)))
+        .cloned()
+        .collect();
+    parameters.extend(declared);
+
+    let location = |parameter: &Value| {
+        parameter
+            .get("in")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    let converted_parameters: Vec<Value> = parameters
+        .iter()
+        .filter(|parameter| !["body", "formData"].contains(&location(parameter).as_str()))
+        .map(convert_parameter)
+        .collect();
+    if !converted_parameters.is_empty() {
+        converted.insert("parameters".to_string(), Value::Array(converted_parameters));
+    }
+
+    let body = parameters.iter().find(|p| location(p) == "body");
+    let form: Vec<&Value> = parameters
+        .iter()
+        .filter(|p| location(p) == "formData")
+        .collect();
+    let request_body = match body {
+        Some(body) => Some(convert_body(body, &consumes)),
+        None if !form.is_empty() => Some(convert_form(&form, &consumes)),
+        None => None,
+    };
+    if let Some(request_body) = request_body {
+        converted.insert("requestBody".to_string(), request_body);
+    }
+
+    let responses: Map<String, Value> = fields(operation.get("responses").unwrap_or(&Value::Null))
+        .map(|(key, response)| {
+            (
+                key.clone(),
+                convert_response(response, &Some(produces.clone())),
+            )
+        })
+        .collect();
+    converted.insert("responses".to_string(), Value::Object(responses));
+
+    Value::Object(converted)
+}
+
+/// Convert a parameter that is not in the body, moving its type to its
+/// schema.
+fn convert_parameter(parameter: &Value) -> Value {
+    let mut converted = Map::new();
+    let mut schema = Map::new();
+    for (key, value) in fields(parameter) {
+        match key.as_str() {
+            "name" | "in" | "description" | "required" | "allowEmptyValue" => {
+                converted.insert(key.clone(), value.clone());
+            }
+            "x-example" => {
+                converted.insert("example".to_string(), value.clone());
+            }
+            "collectionFormat" => {
+                let style = match value.as_str() {
+                    Some("ssv") => Some(("spaceDelimited", false)),
+                    Some("pipes") => Some(("pipeDelimited", false)),
+                    Some("multi") => Some(("form", true)),
+                    Some("csv") if parameter.get("in") == Some(&json!("query")) => {
+                        Some(("form", false))
+                    }
+                    _ => None,
+                };
+                if let Some((style, explode)) = style {
+                    converted.insert("style".to_string(), json!(style));
+                    converted.insert("explode".to_string(), json!(explode));
+                }
+            }
+            key if SCHEMA_FIELDS.contains(&key) => {
+                schema.insert(key.to_string(), value.clone());
+            }
+            key if key.starts_with("x-") => {
+                converted.insert(key.to_string(), value.clone());
+            }
+            _ => {}
+        }
+    }
+    converted.insert("schema".to_string(), convert_schema(&Value::Object(schema)));
+    Value::Object(converted)
+}
+
+/// Convert a `body` parameter to a request body.
+fn convert_body(body: &Value, consumes: &[String]) -> Value {
+    let schema = body.get("schema").map(convert_schema).unwrap_or(json!({}));
+    let examples = body.get("x-examples");
+    let content: Map<String, Value> = consumes
+        .iter()
+        .map(|media_type| {
+            let mut content = json!({ "schema": schema });
+            if let Some(example) = examples.and_then(|examples| examples.get(media_type)) {
+                content["example"] = example.clone();
+            }
+            (media_type.clone(), content)
+        })
+        .collect();
+
+    let mut converted = json!({ "content": content });
+    for field in ["description", "required"] {
+        if let Some(value) = body.get(field) {
+            converted[field] = value.clone();
+        }
+    }
+    converted
+}
+
+/// Convert `formData` parameters to a request body, with an object schema
+/// holding a property per parameter.
+fn convert_form(form: &[&Value], consumes: &[String]) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for parameter in form {
+        let Some(name) = parameter.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        let schema = convert_parameter(parameter)["schema"].clone();
+        properties.insert(name.to_string(), schema);
+        if parameter.get("required") == Some(&Value::Bool(true)) {
+            required.push(json!(name));
+        }
+    }
+    let mut schema = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        schema["required"] = Value::Array(required);
+    }
+
+    let mut media_types: Vec<&str> = consumes
+        .iter()
+        .map(String::as_str)
+        .filter(|media_type| {
+            ["application/x-www-form-urlencoded", "multipart/form-data"].contains(media_type)
+        })
+        .collect();
+    if media_types.is_empty() {
+        media_types.push("application/x-www-form-urlencoded");
+    }
+    let content: Map<String, Value> = media_types
+        .into_iter()
+        .map(|media_type| (media_type.to_string(), json!({ "schema": schema })))
+        .collect();
+    json!({ "content": content })
+}
+
+/// Convert a response, declaring its schema and examples for the media
+/// types it produces.
+fn convert_response(response: &Value, produces: &Option<Vec<String>>) -> Value {
+    if let Some(reference) = response.get("$ref").and_then(Value::as_str) {
+        return json!({ "$ref": convert_ref(reference) });
+    }
+
+    let mut converted = Map::new();
+    let description = response.get("description").cloned();
+    converted.insert(
+        "description".to_string(),
+        description.unwrap_or_else(|| json!("")),
+    );
+    for (key, value) in fields(response) {
+        if key.starts_with("x-") && key != "x-examples" {
+            converted.insert(key.clone(), value.clone());
+        }
+    }
+
+    let headers: Map<String, Value> = fields(response.get("headers").unwrap_or(&Value::Null))
+        .map(|(name, header)| (name.clone(), convert_header(header)))
+        .collect();
+    if !headers.is_empty() {
+        converted.insert("headers".to_string(), Value::Object(headers));
+    }
+
+    let schema = response.get("schema").map(convert_schema);
+    let examples = response.get("examples").and_then(Value::as_object);
+    let named = response.get("x-examples").and_then(Value::as_object);
+    let mut media_types: Vec<String> = produces
+        .clone()
+        .unwrap_or_else(|| vec![DEFAULT_MEDIA_TYPE.to_string()]);
+    for media_type in examples.into_iter().flatten().map(|(key, _)| key) {
+        if !media_types.contains(media_type) {
+            media_types.push(media_type.clone());
+        }
+    }
+
+    if schema.is_some() || examples.is_some() || named.is_some() {
+        let content: Map<String, Value> = media_types
+            .into_iter()
+            .map(|media_type| {
+                let mut content = Map::new();
+                if let Some(schema) = &schema {
+                    content.insert("schema".to_string(), schema.clone());
+                }
+                let example = examples.and_then(|examples| examples.get(&media_type));
+                match (example, named) {
+                    (example, Some(named)) => {
+                        // `example` and `examples` are exclusive: the example
+                        // of the media type is served as the default example
+                        let mut named: Map<String, Value> = named
+                            .iter()
+                            .map(|(name, value)| (name.clone(), json!({ "value": value })))
+                            .collect();
+                        if let Some(example) = example {
+                            named
+                                .entry("default")
+                                .or_insert_with(|| json!({ "value": example }));
+                        }
+                        content.insert("examples".to_string(), Value::Object(named));
+                    }
+                    (Some(example), None) => {
+                        content.insert("example".to_string(), example.clone());
+                    }
+                    (None, None) => {}
+                }
+                (media_type, Value::Object(content))
+            })
+            .collect();
+        converted.insert("content".to_string(), Value::Object(content));
+    }
+    Value::Object(converted)
+}
+
+/// Convert a response header, moving its type to its schema.
+fn convert_header(header: &Value) -> Value {
+    let mut converted = Map::new();
+    let mut schema = Map::new();
+    for (key, value) in fields(header) {
+        match key.as_str() {
+            "description" => {
+                converted.insert(key.clone(), value.clone());
+            }
+            "x-example" => {
+                converted.insert("example".to_string(), value.clone());
+            }
+            key if SCHEMA_FIELDS.contains(&key) => {
+                schema.insert(key.to_string(), value.clone());
+            }
+            _ => {}
+        }
+    }
+    converted.insert("schema".to_string(), convert_schema(&Value::Object(schema)));
+    Value::Object(converted)
+}
+
+/// The keywords holding values rather than schemas, left untouched.
+const VALUE_KEYWORDS: [&str; 3] = ["example", "default", "enum"];
+
+/// Convert a schema: references to `definitions` point to the components,
+/// `x-nullable` becomes `nullable`, the `file` type a binary string, and a
+/// `discriminator` property name a discriminator object.
+fn convert_schema(schema: &Value) -> Value {
+    convert_schema_value(schema, false)
+}
+
+/// Convert a schema, or a map of schemas by name if `named`.
+fn convert_schema_value(value: &Value, named: bool) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut converted = Map::new();
+            for (key, value) in object {
+                let value = match key.as_str() {
+                    _ if named => convert_schema_value(value, false),
+                    "$ref" => match value.as_str() {
+                        Some(reference) => json!(convert_ref(reference)),
+                        None => value.clone(),
+                    },
+                    "properties" => convert_schema_value(value, true),
+                    key if VALUE_KEYWORDS.contains(&key) || key.starts_with("x-") => value.clone(),
+                    _ => convert_schema_value(value, false),
+                };
+                converted.insert(key.clone(), value);
+            }
+            if named {
+                return Value::Object(converted);
+            }
+
+            if let Some(nullable) = converted.remove("x-nullable") {
+                converted.insert("nullable".to_string(), nullable);
+            }
+            if converted.get("type") == Some(&json!("file")) {
+                converted.insert("type".to_string(), json!("string"));
+                converted.insert("format".to_string(), json!("binary"));
+            }
+            if let Some(Value::String(property)) = converted.get("discriminator").cloned() {
+                converted.insert(
+                    "discriminator".to_string(),
+                    json!({ "propertyName": property }),
+                );
+            }
+            Value::Object(converted)
+        }
+        Value::Array(values) => values
+            .iter()
+            .map(|value| convert_schema_value(value, false))
+            .collect(),
+        value => value.clone(),
+    }
+}
+
+/// Point a reference to the global `definitions` or `responses` to the
+/// components.
+fn convert_ref(reference: &str) -> String {
+    let converted = [
+        ("#/definitions/", "#/components/schemas/"),
+        ("#/responses/", "#/components/responses/"),
+    ]
+    .iter()
+    .find_map(|(from, to)| Some(format!("{}{}", to, reference.strip_prefix(from)?)));
+    converted.unwrap_or_else(|| reference.to_string())
+}
+
+fn convert_security_scheme(scheme: &Value) -> Value {
+    match scheme.get("type").and_then(Value::as_str) {
+        Some("basic") => json!({ "type": "http", "scheme": "basic" }),
+        Some("oauth2") => {
+            let field = |name: &str| scheme.get(name).cloned().unwrap_or(json!(""));
+            let scopes = scheme.get("scopes").cloned().unwrap_or(json!({}));
+            let flow = match scheme.get("flow").and_then(Value::as_str) {
+                Some("implicit") => json!({ "implicit": {
+                    "authorizationUrl": field("authorizationUrl"), "scopes": scopes
+                }}),
+                Some("password") => json!({ "password": {
+                    "tokenUrl": field("tokenUrl"), "scopes": scopes
+                }}),
+                Some("application") => json!({ "clientCredentials": {
+                    "tokenUrl": field("tokenUrl"), "scopes": scopes
+                }}),
+                _ => json!({ "authorizationCode": {
+                    "authorizationUrl": field("authorizationUrl"),
+                    "tokenUrl": field("tokenUrl"),
+                    "scopes": scopes
+                }}),
+            };
+            json!({ "type": "oauth2", "flows": flow })
+        }
+        _ => scheme.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::document::load_document;
+
+    fn load_swagger() -> Value {
+        convert(&load_document("tests/testdata/swagger.yaml").unwrap())
+    }
+
+    #[test]
+    fn test_convert_servers() {
+        let document = load_swagger();
+        assert_eq!(
+            document["servers"],
+            json!([{"url": "https://petstore.swagger.io/v2"}])
+        );
+        assert_eq!(convert_servers(&json!({})), json!([{"url": "/"}]));
+    }
+
+    #[test]
+    fn test_convert_responses() {
+        let document = load_swagger();
+        let responses = &document["paths"]["/pets"]["get"]["responses"];
+        let content = &responses["200"]["content"];
+        assert_eq!(
+            content["application/json"]["schema"]["items"]["$ref"],
+            "#/components/schemas/Pet"
+        );
+        let examples = &content["application/json"]["examples"];
+        assert_eq!(examples["default"]["value"][0]["name"], "doggie");
+        assert_eq!(content["application/xml"]["schema"]["type"], "array");
+        assert_eq!(examples["query:tag=cat"]["value"][0]["name"], "kitty");
+        assert_eq!(
+            responses["200"]["headers"]["x-rate-limit"]["schema"]["type"],
+            "integer"
+        );
+        assert_eq!(responses["default"]["$ref"], "#/components/responses/Error");
+    }
+
+    #[test]
+    fn test_convert_parameters() {
+        let document = load_swagger();
+        let operation = &document["paths"]["/pets"]["get"];
+        assert_eq!(
+            operation["parameters"][0],
+            json!({
+                "name": "tags",
+                "in": "query",
+                "required": false,
+                "style": "form",
+                "explode": true,
+                "schema": {"type": "array", "items": {"type": "string"}}
+            })
+        );
+        let body = &document["paths"]["/pets"]["post"]["requestBody"];
+        assert_eq!(body["required"], true);
+        assert_eq!(
+            body["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/NewPet"
+        );
+
+        let form = &document["paths"]["/pets/{petId}"]["post"]["requestBody"]["content"];
+        let schema = &form["multipart/form-data"]["schema"];
+        assert_eq!(schema["required"], json!(["name"]));
+        assert_eq!(schema["properties"]["photo"]["format"], "binary");
+        // The path parameter is declared for each operation
+        let parameters = &document["paths"]["/pets/{petId}"]["post"]["parameters"];
+        assert_eq!(parameters[0]["name"], "petId");
+    }
+
+    #[test]
+    fn test_convert_schema() {
+        let schema = json!({
+            "type": "object",
+            "discriminator": "kind",
+            "properties": {
+                "owner": {"$ref": "#/definitions/Owner", "x-nullable": true},
+                "discriminator": {"type": "string"}
+            },
+            "example": {"owner": {"$ref": "#/definitions/Owner"}}
+        });
+        assert_eq!(
+            convert_schema(&schema),
+            json!({
+                "type": "object",
+                "discriminator": {"propertyName": "kind"},
+                "properties": {
+                    "owner": {"$ref": "#/components/schemas/Owner", "nullable": true},
+                    "discriminator": {"type": "string"}
+                },
+                "example": {"owner": {"$ref": "#/definitions/Owner"}}
+            })
+        );
+    }
+}
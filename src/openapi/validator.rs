@@ -0,0 +1,546 @@
+//! Validation of values against the schemas of the raw OpenAPI document.
+//!
+//! Schemas are validated from the raw document rather than the `oas3`
+//! structures, which cannot hold numeric `enum` values nor the `$ref`
+//! of a parameter schema.
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::document::resolve_ref;
+
+/// Maximum nesting depth of the `allOf`, `anyOf` and `oneOf` schemas of a
+/// value. A schema composed of itself stops being validated past this
+/// depth, rather than recursing without end.
+const MAX_DEPTH: usize = 32;
+
+/// A value that does not conform to the schema it was validated against.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Violation {
+    /// JSON pointer to the invalid value, e.g. `/body/tags/0`.
+    pub pointer: String,
+    /// The schema keyword the value violates, e.g. `type` or `required`.
+    pub keyword: String,
+    /// A human readable description of the violation.
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(pointer: &str, keyword: &str, message: impl Into<String>) -> Self {
+        Self {
+            pointer: pointer.to_string(),
+            keyword: keyword.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Validate a value against a schema.
+/// # Arguments
+/// * `document` - The raw OpenAPI document, used to resolve `$ref`s
+/// * `schema` - The schema to validate the value against
+/// * `value` - The value to validate
+/// * `pointer` - The JSON pointer of the value, prefixed to the violation pointers
+/// # Returns
+/// The violations found, empty if the value is valid
+/// # Example
+/// ```rust
+/// use openapi_mocker::openapi::validator::validate;
+/// use serde_json::json;
+/// let document = json!({});
+/// let schema = json!({"type": "object", "required": ["name"]});
+/// let violations = validate(&document, &schema, &json!({}), "/body");
+/// assert_eq!(violations[0].pointer, "/body/name");
+/// assert_eq!(violations[0].keyword, "required");
+/// ```
+pub fn validate(document: &Value, schema: &Value, value: &Value, pointer: &str) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    Validator { document }.validate(schema, value, pointer, 0, &mut violations);
+    violations
+}
+
+/// Escape a token of a JSON pointer.
+pub fn escape_pointer(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+struct Validator<'a> {
+    document: &'a Value,
+}
+
+impl Validator<'_> {
+    /// Validate a value against a schema.
+    /// # Arguments
+    /// * `depth` - The number of composed schemas the value is validated
+    ///   against, reset for the items and properties of the value
+    fn validate(
+        &self,
+        schema: &Value,
+        value: &Value,
+        pointer: &str,
+        depth: usize,
+        out: &mut Vec<Violation>,
+    ) {
+        if depth >= MAX_DEPTH {
+            return;
+        }
+        let Some(schema) = resolve_ref(self.document, schema) else {
+            return;
+        };
+
+        if value.is_null() && schema.get("nullable") == Some(&Value::Bool(true)) {
+            return;
+        }
+
+        for schema in array(schema, "allOf") {
+            self.validate(schema, value, pointer, depth + 1, out);
+        }
+        self.validate_any_of(schema, value, pointer, depth + 1, out);
+        self.validate_one_of(schema, value, pointer, depth + 1, out);
+
+        // OpenAPI 3.1 declares a type or an array of types
+        let kinds: Vec<&str> = match schema.get("type") {
+            Some(Value::String(kind)) => vec![kind.as_str()],
+            Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !kinds.is_empty() && !kinds.iter().any(|kind| is_type(value, kind)) {
+            let message = format!(
+                "expected {}, found {}",
+                kinds.join(" or "),
+                type_name(value)
+            );
+            out.push(Violation::new(pointer, "type", message));
+            return;
+        }
+
+        if let Some(constant) = schema.get("const") {
+            if constant != value {
+                let message = format!("{} is not {}", value, constant);
+                out.push(Violation::new(pointer, "const", message));
+            }
+        }
+
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            if !values.contains(value) {
+                let message = format!("{} is not one of {}", value, Value::from(values.clone()));
+                out.push(Violation::new(pointer, "enum", message));
+            }
+        }
+
+        match value {
+            Value::String(value) => self.validate_string(schema, value, pointer, out),
+            Value::Number(_) => self.validate_number(schema, value, pointer, out),
+            Value::Array(items) => self.validate_array(schema, items, pointer, out),
+            Value::Object(object) => self.validate_object(schema, object, pointer, out),
+            _ => {}
+        }
+    }
+
+    fn is_valid(&self, schema: &Value, value: &Value, pointer: &str, depth: usize) -> bool {
+        let mut violations = Vec::new();
+        self.validate(schema, value, pointer, depth, &mut violations);
+        violations.is_empty()
+    }
+
+    fn validate_any_of(
+        &self,
+        schema: &Value,
+        value: &Value,
+        pointer: &str,
+        depth: usize,
+        out: &mut Vec<Violation>,
+    ) {
+        let schemas = array(schema, "anyOf");
+        let valid = |s: &Value| self.is_valid(s, value, pointer, depth);
+        if !schemas.is_empty() && !schemas.iter().any(valid) {
+            let message = "value does not match any of the anyOf schemas";
+            out.push(Violation::new(pointer, "anyOf", message));
+        }
+    }
+
+    fn validate_one_of(
+        &self,
+        schema: &Value,
+        value: &Value,
+        pointer: &str,
+        depth: usize,
+        out: &mut Vec<Violation>,
+    ) {
+        let schemas = array(schema, "oneOf");
+        if schemas.is_empty() {
+            return;
+        }
+        let matches = schemas
+            .iter()
+            .filter(|s| self.is_valid(s, value, pointer, depth))
+            .count();
+        if matches != 1 {
+            let message = format!("value matches {} of the oneOf schemas, expected 1", matches);
+            out.push(Violation::new(pointer, "oneOf", message));
+        }
+    }
+
+    fn validate_string(
+        &self,
+        schema: &Value,
+        value: &str,
+        pointer: &str,
+        out: &mut Vec<Violation>,
+    ) {
+        let length = value.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+            if length < min {
+                let message = format!("length {} is shorter than {}", length, min);
+                out.push(Violation::new(pointer, "minLength", message));
+            }
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+            if length > max {
+                let message = format!("length {} is longer than {}", length, max);
+                out.push(Violation::new(pointer, "maxLength", message));
+            }
+        }
+        if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+            // Invalid patterns are ignored rather than rejecting every value
+            if compile(pattern).is_some_and(|regex| !regex.is_match(value)) {
+                let message = format!("{:?} does not match pattern {:?}", value, pattern);
+                out.push(Violation::new(pointer, "pattern", message));
+            }
+        }
+    }
+
+    fn validate_number(
+        &self,
+        schema: &Value,
+        value: &Value,
+        pointer: &str,
+        out: &mut Vec<Violation>,
+    ) {
+        let Some(number) = value.as_f64() else {
+            return;
+        };
+        let exclusive = |keyword: &str| schema.get(keyword) == Some(&Value::Bool(true));
+
+        if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+            if number < min || exclusive("exclusiveMinimum") && number == min {
+                let message = format!("{} is less than the minimum of {}", value, min);
+                out.push(Violation::new(pointer, "minimum", message));
+            }
+        }
+        if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+            if number > max || exclusive("exclusiveMaximum") && number == max {
+                let message = format!("{} is greater than the maximum of {}", value, max);
+                out.push(Violation::new(pointer, "maximum", message));
+            }
+        }
+        // OpenAPI 3.1 declares the exclusive bounds as numbers
+        if let Some(min) = schema.get("exclusiveMinimum").and_then(Value::as_f64) {
+            if number <= min {
+                let message = format!("{} is not greater than {}", value, min);
+                out.push(Violation::new(pointer, "exclusiveMinimum", message));
+            }
+        }
+        if let Some(max) = schema.get("exclusiveMaximum").and_then(Value::as_f64) {
+            if number >= max {
+                let message = format!("{} is not less than {}", value, max);
+                out.push(Violation::new(pointer, "exclusiveMaximum", message));
+            }
+        }
+        if let Some(multiple) = schema.get("multipleOf").and_then(Value::as_f64) {
+            let quotient = number / multiple;
+            if multiple > 0.0 && (quotient - quotient.round()).abs() > f64::EPSILON {
+                let message = format!("{} is not a multiple of {}", value, multiple);
+                out.push(Violation::new(pointer, "multipleOf", message));
+            }
+        }
+    }
+
+    fn validate_array(
+        &self,
+        schema: &Value,
+        items: &[Value],
+        pointer: &str,
+        out: &mut Vec<Violation>,
+    ) {
+        let length = items.len() as u64;
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+            if length < min {
+                let message = format!("{} items are fewer than {}", length, min);
+                out.push(Violation::new(pointer, "minItems", message));
+            }
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+            if length > max {
+                let message = format!("{} items are more than {}", length, max);
+                out.push(Violation::new(pointer, "maxItems", message));
+            }
+        }
+        if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+            let duplicate = items
+                .iter()
+                .enumerate()
+                .any(|(i, item)| items[..i].contains(item));
+            if duplicate {
+                out.push(Violation::new(
+                    pointer,
+                    "uniqueItems",
+                    "items are not unique",
+                ));
+            }
+        }
+        if let Some(items_schema) = schema.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                self.validate(items_schema, item, &format!("{}/{}", pointer, i), 0, out);
+            }
+        }
+    }
+
+    fn validate_object(
+        &self,
+        schema: &Value,
+        object: &serde_json::Map<String, Value>,
+        pointer: &str,
+        out: &mut Vec<Violation>,
+    ) {
+        let property_pointer = |name: &str| format!("{}/{}", pointer, escape_pointer(name));
+
+        for name in array(schema, "required")
+            .iter()
+            .filter_map(|name| name.as_str())
+        {
+            if !object.contains_key(name) {
+                let message = format!("missing required property {:?}", name);
+                out.push(Violation::new(&property_pointer(name), "required", message));
+            }
+        }
+
+        let length = object.len() as u64;
+        if let Some(min) = schema.get("minProperties").and_then(Value::as_u64) {
+            if length < min {
+                let message = format!("{} properties are fewer than {}", length, min);
+                out.push(Violation::new(pointer, "minProperties", message));
+            }
+        }
+        if let Some(max) = schema.get("maxProperties").and_then(Value::as_u64) {
+            if length > max {
+                let message = format!("{} properties are more than {}", length, max);
+                out.push(Violation::new(pointer, "maxProperties", message));
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, value) in object {
+            match (
+                properties.and_then(|p| p.get(name)),
+                schema.get("additionalProperties"),
+            ) {
+                (Some(property), _) => {
+                    self.validate(property, value, &property_pointer(name), 0, out)
+                }
+                (None, Some(Value::Bool(false))) => {
+                    let message = format!("property {:?} is not allowed", name);
+                    out.push(Violation::new(
+                        &property_pointer(name),
+                        "additionalProperties",
+                        message,
+                    ));
+                }
+                (None, Some(additional)) if additional.is_object() => {
+                    self.validate(additional, value, &property_pointer(name), 0, out)
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Compile a `pattern` once, caching it for the values validated later.
+/// # Returns
+/// The regular expression, or None if the pattern is invalid
+fn compile(pattern: &str) -> Option<Regex> {
+    static PATTERNS: OnceLock<Mutex<HashMap<String, Option<Regex>>>> = OnceLock::new();
+    let mut patterns = PATTERNS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(regex) = patterns.get(pattern) {
+        return regex.clone();
+    }
+    let regex = Regex::new(pattern).ok();
+    patterns.insert(pattern.to_string(), regex.clone());
+    regex
+}
+
+/// Get an array keyword of a schema, empty if it is not declared.
+fn array<'a>(schema: &'a Value, keyword: &str) -> &'a [Value] {
+    schema
+        .get(keyword)
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn is_type(value: &Value, kind: &str) -> bool {
+    match kind {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn keywords(schema: Value, value: Value) -> Vec<(String, String)> {
+        validate(&json!({}), &schema, &value, "")
+            .into_iter()
+            .map(|v| (v.pointer, v.keyword))
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_type() {
+        let violations = validate(&json!({}), &json!({"type": "integer"}), &json!("1"), "/id");
+        assert_eq!(
+            violations,
+            vec![Violation::new(
+                "/id",
+                "type",
+                "expected integer, found string"
+            )]
+        );
+        assert!(keywords(json!({"type": "integer"}), json!(1.0)).is_empty());
+        assert!(keywords(json!({"type": "string", "nullable": true}), json!(null)).is_empty());
+    }
+
+    #[test]
+    fn test_validate_recursive_composition() {
+        let document = json!({"components": {"schemas": {
+            "Loop": {"allOf": [{"$ref": "#/components/schemas/Loop"}], "type": "string"},
+            "Either": {"anyOf": [{"$ref": "#/components/schemas/Either"}, {"type": "string"}]}
+        }}});
+        let schema = |name: &str| json!({"$ref": format!("#/components/schemas/{}", name)});
+        let violations = validate(&document, &schema("Loop"), &json!(1), "");
+        assert!(violations.iter().all(|v| v.keyword == "type"));
+        assert!(!violations.is_empty());
+        assert!(validate(&document, &schema("Either"), &json!("a"), "").is_empty());
+    }
+
+    #[test]
+    fn test_validate_openapi_31() {
+        let schema = json!({"type": ["string", "null"]});
+        assert!(keywords(schema.clone(), json!(null)).is_empty());
+        assert_eq!(keywords(schema, json!(1)), vec![("".into(), "type".into())]);
+        assert_eq!(
+            keywords(json!({"const": "dog"}), json!("cat")),
+            vec![("".into(), "const".into())]
+        );
+        let schema = json!({"type": "integer", "exclusiveMinimum": 0});
+        assert!(keywords(schema.clone(), json!(1)).is_empty());
+        assert_eq!(
+            keywords(schema, json!(0)),
+            vec![("".into(), "exclusiveMinimum".into())]
+        );
+    }
+
+    #[test]
+    fn test_validate_enum_and_pattern() {
+        let schema = json!({"type": "integer", "enum": [1, 2]});
+        assert_eq!(keywords(schema, json!(3)), vec![("".into(), "enum".into())]);
+        let schema = json!({"type": "string", "pattern": "^[A-Z]{2}-[0-9]{3}$"});
+        assert!(keywords(schema.clone(), json!("AB-123")).is_empty());
+        assert_eq!(
+            keywords(schema, json!("ab")),
+            vec![("".into(), "pattern".into())]
+        );
+    }
+
+    #[test]
+    fn test_validate_bounds() {
+        let schema =
+            json!({"minimum": 0, "maximum": 10, "exclusiveMaximum": true, "multipleOf": 2});
+        assert!(keywords(schema.clone(), json!(4)).is_empty());
+        assert_eq!(
+            keywords(schema.clone(), json!(10)),
+            vec![("".into(), "maximum".into())]
+        );
+        assert_eq!(
+            keywords(schema, json!(3)),
+            vec![("".into(), "multipleOf".into())]
+        );
+        let schema = json!({"minLength": 2, "maxItems": 1});
+        assert_eq!(
+            keywords(schema.clone(), json!("a")),
+            vec![("".into(), "minLength".into())]
+        );
+        assert_eq!(
+            keywords(schema, json!([1, 2])),
+            vec![("".into(), "maxItems".into())]
+        );
+    }
+
+    #[test]
+    fn test_validate_object() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {"tags": {"type": "array", "uniqueItems": true, "items": {"type": "string"}}},
+            "additionalProperties": false
+        });
+        let value = json!({"tags": ["a", 1, "a"], "age": 2});
+        assert_eq!(
+            keywords(schema, value),
+            vec![
+                ("/name".into(), "required".into()),
+                ("/tags".into(), "uniqueItems".into()),
+                ("/tags/1".into(), "type".into()),
+                ("/age".into(), "additionalProperties".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_refs_and_composition() {
+        let document = json!({"components": {"schemas": {"Id": {"type": "integer"}}}});
+        let schema = json!({"oneOf": [{"$ref": "#/components/schemas/Id"}, {"type": "number"}]});
+        let violations = validate(&document, &schema, &json!(1), "");
+        assert_eq!(violations[0].keyword, "oneOf");
+        assert!(validate(&document, &schema, &json!(1.5), "").is_empty());
+
+        let schema = json!({"anyOf": [{"type": "string"}, {"type": "boolean"}]});
+        assert_eq!(
+            keywords(schema, json!(1)),
+            vec![("".into(), "anyOf".into())]
+        );
+    }
+
+    #[test]
+    fn test_escape_pointer() {
+        assert_eq!(escape_pointer("a/b~c"), "a~1b~0c");
+    }
+}
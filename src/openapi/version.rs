@@ -0,0 +1,235 @@
+//! Detection of the OpenAPI version of a document.
+//!
+//! The `oas3` structures target OpenAPI 3.0. An OpenAPI 3.1 document is
+//! normalized to the shape of 3.0 before it is parsed by `oas3`, while the
+//! raw document is kept as it is: the validator understands the JSON Schema
+//! keywords of 3.1, and the webhooks are read from the raw document.
+//! A Swagger 2.0 document is converted to OpenAPI 3.0 when it is loaded,
+//! see the `swagger` module.
+use serde_json::{json, Map, Value};
+
+/// The OpenAPI versions of the documents the mocker serves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Version {
+    Swagger2,
+    V30,
+    V31,
+}
+
+/// Detect the OpenAPI version of a document from its `openapi` field, or
+/// its `swagger` field for Swagger 2.0.
+/// # Errors
+/// Returns an error if the document declares no version, or a version
+/// the mocker does not serve.
+/// # Example
+/// ```rust
+/// use serde_json::json;
+/// use openapi_mocker::openapi::version::{detect, Version};
+/// assert_eq!(detect(&json!({"openapi": "3.1.0"})), Ok(Version::V31));
+/// assert_eq!(detect(&json!({"swagger": "2.0"})), Ok(Version::Swagger2));
+/// assert!(detect(&json!({"openapi": "4.0.0"})).is_err());
+/// ```
+pub fn detect(document: &Value) -> Result<Version, String> {
+    if let Some(version) = document.get("swagger").and_then(Value::as_str) {
+        return match version {
+            "2.0" => Ok(Version::Swagger2),
+            _ => Err(format!("Unsupported Swagger version {}", version)),
+        };
+    }
+    let version = document
+        .get("openapi")
+        .and_then(Value::as_str)
+        .ok_or("The document declares no openapi version")?;
+    match version.split('.').take(2).collect::<Vec<&str>>()[..] {
+        ["3", "0"] => Ok(Version::V30),
+        ["3", "1"] => Ok(Version::V31),
+        _ => Err(format!("Unsupported OpenAPI version {}", version)),
+    }
+}
+
+/// Normalize an OpenAPI 3.1 document to the shape of OpenAPI 3.0, to be
+/// parsed by `oas3`. The JSON Schema keywords of 3.1 are converted to
+/// their closest 3.0 equivalent:
+/// - `type: [string, "null"]` becomes `type: string` and `nullable: true`,
+///   keeping the first type of the types that are not `null`.
+/// - `const` becomes the `example` of the schema, and its only `enum`
+///   value if it is a string.
+/// - An `examples` array becomes the `example` of the schema.
+/// - A numeric `exclusiveMinimum` or `exclusiveMaximum` becomes the
+///   `minimum` or `maximum`, with the boolean exclusive flag.
+///
+/// A document with no `paths`, declaring only webhooks, gets empty paths.
+pub fn normalize(document: &Value) -> Value {
+    let mut document = normalize_value(document);
+    if let Some(document) = document.as_object_mut() {
+        document.entry("paths").or_insert_with(|| json!({}));
+    }
+    document
+}
+
+/// Normalize a JSON Schema of OpenAPI 3.1 to the shape of OpenAPI 3.0,
+/// see `normalize`.
+/// # Example
+/// ```rust
+/// use serde_json::json;
+/// use openapi_mocker::openapi::version::normalize_schema;
+/// let schema = normalize_schema(&json!({"type": ["integer", "null"]}));
+/// assert_eq!(schema, json!({"type": "integer", "nullable": true}));
+/// ```
+pub fn normalize_schema(schema: &Value) -> Value {
+    normalize_value(schema)
+}
+
+/// The keywords holding values rather than schemas, left untouched.
+const VALUE_KEYWORDS: [&str; 6] = ["example", "examples", "default", "enum", "const", "value"];
+
+/// The keywords holding maps by user-defined names, which may collide with
+/// the keywords or look like extensions, e.g. a `x-rate-limit` header.
+const NAMED_KEYWORDS: [&str; 12] = [
+    "paths",
+    "webhooks",
+    "callbacks",
+    "pathItems",
+    "responses",
+    "parameters",
+    "requestBodies",
+    "headers",
+    "schemas",
+    "properties",
+    "patternProperties",
+    "$defs",
+];
+
+fn normalize_value(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut normalized = normalize_entries(object, false);
+            normalize_keywords(&mut normalized);
+            Value::Object(normalized)
+        }
+        Value::Array(values) => Value::Array(values.iter().map(normalize_value).collect()),
+        value => value.clone(),
+    }
+}
+
+/// Normalize the entries of an object.
+/// # Arguments
+/// * `object` - The object
+/// * `named` - Whether the object maps names to schemas, rather than
+///   keywords to values
+fn normalize_entries(object: &Map<String, Value>, named: bool) -> Map<String, Value> {
+    object
+        .iter()
+        .map(|(key, value)| {
+            let value = match (value, named) {
+                (value, true) => normalize_value(value),
+                (_, false) if VALUE_KEYWORDS.contains(&key.as_str()) || key.starts_with("x-") => {
+                    value.clone()
+                }
+                (Value::Object(object), false) if NAMED_KEYWORDS.contains(&key.as_str()) => {
+                    Value::Object(normalize_entries(object, true))
+                }
+                (value, false) => normalize_value(value),
+            };
+            (key.clone(), value)
+        })
+        .collect()
+}
+
+/// Convert the JSON Schema keywords of an object, if it is a schema.
+fn normalize_keywords(schema: &mut Map<String, Value>) {
+    if let Some(Value::Array(types)) = schema.get("type").cloned() {
+        let nullable = types.iter().any(|kind| kind == "null");
+        match types.into_iter().find(|kind| kind != "null") {
+            Some(kind) => schema.insert("type".to_string(), kind),
+            None => schema.remove("type"),
+        };
+        if nullable {
+            schema.insert("nullable".to_string(), Value::Bool(true));
+        }
+    }
+    if schema.get("type") == Some(&json!("null")) {
+        schema.remove("type");
+        schema.insert("nullable".to_string(), Value::Bool(true));
+    }
+
+    if let Some(value) = schema.get("const").cloned() {
+        if value.is_string() && !schema.contains_key("enum") {
+            schema.insert("enum".to_string(), json!([value]));
+        }
+        schema.entry("example").or_insert(value);
+    }
+    if let Some(Value::Array(examples)) = schema.get("examples").cloned() {
+        if let Some(example) = examples.into_iter().next() {
+            schema.entry("example").or_insert(example);
+        }
+        schema.remove("examples");
+    }
+
+    for (exclusive, bound) in [
+        ("exclusiveMinimum", "minimum"),
+        ("exclusiveMaximum", "maximum"),
+    ] {
+        if let Some(value) = schema.get(exclusive).filter(|v| v.is_number()).cloned() {
+            schema.insert(bound.to_string(), value);
+            schema.insert(exclusive.to_string(), Value::Bool(true));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(&json!({"openapi": "3.0.3"})), Ok(Version::V30));
+        assert_eq!(detect(&json!({"openapi": "3.1.1"})), Ok(Version::V31));
+        assert_eq!(detect(&json!({"swagger": "2.0"})), Ok(Version::Swagger2));
+        assert!(detect(&json!({"swagger": "1.2"})).is_err());
+    }
+
+    #[test]
+    fn test_normalize_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "kind": {"const": "dog"},
+                "age": {"type": "integer", "exclusiveMinimum": 0, "examples": [3, 4]},
+                "tag": {"type": ["null"]},
+                "type": {"type": ["string", "integer"]},
+                "const": {"type": "string"}
+            },
+            "examples": [{"kind": "dog", "type": ["string"]}]
+        });
+        assert_eq!(
+            normalize_schema(&schema),
+            json!({
+                "type": "object",
+                "properties": {
+                    "kind": {"const": "dog", "enum": ["dog"], "example": "dog"},
+                    "age": {"type": "integer", "minimum": 0, "exclusiveMinimum": true, "example": 3},
+                    "tag": {"nullable": true},
+                    "type": {"type": "string"},
+                    "const": {"type": "string"}
+                },
+                "example": {"kind": "dog", "type": ["string"]}
+            })
+        );
+    }
+
+    #[test]
+    fn test_normalize_keeps_media_type_examples() {
+        let document = json!({
+            "openapi": "3.1.0",
+            "webhooks": {},
+            "headers": {"x-rate-limit": {"schema": {"type": ["integer"]}}},
+            "components": {"examples": {"Dog": {"value": {"type": ["a"]}}}}
+        });
+        let normalized = normalize(&document);
+        assert_eq!(normalized["paths"], json!({}));
+        let header = &normalized["headers"]["x-rate-limit"];
+        assert_eq!(header["schema"]["type"], "integer");
+        assert_eq!(normalized["components"], document["components"]);
+    }
+}
@@ -0,0 +1,245 @@
+//! The webhooks of an OpenAPI 3.1 spec.
+//!
+//! Webhooks describe the requests the API sends to its consumers. The
+//! mocker resolves the payload of each webhook from its request body, so
+//! that the admin API can send it to a consumer under test.
+use actix_web::http::Method;
+use oas3::spec::ObjectOrReference;
+use serde_json::Value;
+
+use super::document::{get, resolve_ref};
+use super::generator::{generate, resolve_schema};
+use super::version::normalize_schema;
+
+/// The methods of a path item, in the order of `PathItem::methods`.
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// A webhook operation, with its payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Webhook {
+    /// The name of the webhook, as declared under `webhooks`.
+    pub name: String,
+    pub method: Method,
+    /// The `operationId` of the operation, if declared.
+    pub operation_id: Option<String>,
+    /// The media type of the payload, `application/json` if declared, or
+    /// else the first declared. None if the webhook declares no body.
+    pub media_type: Option<String>,
+    /// The named examples of the payload.
+    pub examples: Vec<(String, Value)>,
+    /// The payload sent when no named example is requested: the media
+    /// type `example`, or a value generated from the schema.
+    pub payload: Value,
+}
+
+impl Webhook {
+    /// Get the payload of the webhook.
+    /// # Arguments
+    /// * `example` - The name of a named example, or None for the default
+    ///   payload
+    /// # Returns
+    /// The payload, or None if the webhook has no such named example
+    pub fn payload(&self, example: Option<&str>) -> Option<&Value> {
+        match example {
+            Some(name) => self
+                .examples
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| value),
+            None => Some(&self.payload),
+        }
+    }
+}
+
+/// Load the webhooks declared in the raw document, sorted by name.
+/// # Arguments
+/// * `spec` - The OpenAPI spec, used to generate payloads from schemas
+/// * `document` - The raw OpenAPI document
+pub fn load_webhooks(spec: &oas3::OpenApiV3Spec, document: &Value) -> Vec<Webhook> {
+    let mut names = document
+        .get("webhooks")
+        .and_then(Value::as_object)
+        .map(|webhooks| webhooks.keys().cloned().collect::<Vec<String>>())
+        .unwrap_or_default();
+    // The document keeps the keys in the order they are declared in
+    names.sort();
+
+    let mut webhooks = Vec::new();
+    for name in names {
+        for method in METHODS {
+            let Some(operation) = get(document, &["webhooks", &name, method]) else {
+                continue;
+            };
+            let body = get(document, &["webhooks", &name, method, "requestBody"]);
+            let (media_type, examples, payload) = match body.and_then(|b| load_content(b)) {
+                Some((media_type, content)) => (
+                    Some(media_type.to_string()),
+                    load_examples(document, content),
+                    load_payload(spec, content),
+                ),
+                None => (None, Vec::new(), Value::Null),
+            };
+            webhooks.push(Webhook {
+                name: name.clone(),
+                method: Method::from_bytes(method.to_uppercase().as_bytes())
+                    .unwrap_or(Method::POST),
+                operation_id: operation
+                    .get("operationId")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                media_type,
+                examples,
+                payload,
+            });
+        }
+    }
+    webhooks
+}
+
+/// Get the content of a request body: `application/json` if declared,
+/// or else the first media type.
+fn load_content(body: &Value) -> Option<(&str, &Value)> {
+    let content = body.get("content")?.as_object()?;
+    content
+        .get_key_value("application/json")
+        .or_else(|| content.iter().next())
+        .map(|(media_type, content)| (media_type.as_str(), content))
+}
+
+fn load_examples(document: &Value, content: &Value) -> Vec<(String, Value)> {
+    let examples = content.get("examples").and_then(Value::as_object);
+    examples
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, example)| {
+            let value = resolve_ref(document, example)?.get("value")?;
+            Some((name.clone(), value.clone()))
+        })
+        .collect()
+}
+
+fn load_payload(spec: &oas3::OpenApiV3Spec, content: &Value) -> Value {
+    if let Some(example) = content.get("example") {
+        return example.clone();
+    }
+    content
+        .get("schema")
+        .and_then(|schema| {
+            serde_json::from_value::<ObjectOrReference<oas3::Schema>>(normalize_schema(schema)).ok()
+        })
+        .and_then(|schema| resolve_schema(&schema, spec))
+        .map(|schema| generate(&schema, spec))
+        .unwrap_or(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::version::normalize;
+    use serde_json::json;
+
+    fn load(document: Value) -> Vec<Webhook> {
+        let spec = serde_json::from_value(normalize(&document)).unwrap();
+        load_webhooks(&spec, &document)
+    }
+
+    #[test]
+    fn test_load_webhooks() {
+        let webhooks = load(json!({
+            "openapi": "3.1.0",
+            "info": {"title": "Webhooks", "version": "1.0.0"},
+            "webhooks": {
+                "newPet": {"post": {
+                    "operationId": "newPetHook",
+                    "requestBody": {"content": {
+                        "text/plain": {"example": "new pet"},
+                        "application/json": {
+                            "example": {"id": 1},
+                            "examples": {"cat": {"value": {"id": 2}}}
+                        }
+                    }},
+                    "responses": {}
+                }},
+                "petSold": {"post": {
+                    "requestBody": {"content": {"text/plain": {"example": "sold"}}},
+                    "responses": {}
+                }},
+                "ping": {"post": {"responses": {}}}
+            }
+        }));
+        assert_eq!(webhooks.len(), 3);
+
+        let new_pet = &webhooks[0];
+        assert_eq!(new_pet.operation_id.as_deref(), Some("newPetHook"));
+        assert_eq!(new_pet.media_type.as_deref(), Some("application/json"));
+        assert_eq!(new_pet.payload(None), Some(&json!({"id": 1})));
+        assert_eq!(new_pet.payload(Some("cat")), Some(&json!({"id": 2})));
+        assert_eq!(new_pet.payload(Some("dog")), None);
+
+        let pet_sold = &webhooks[1];
+        assert_eq!(pet_sold.operation_id, None);
+        assert_eq!(pet_sold.media_type.as_deref(), Some("text/plain"));
+        assert_eq!(pet_sold.payload(None), Some(&json!("sold")));
+
+        let ping = &webhooks[2];
+        assert_eq!(ping.media_type, None);
+        assert!(ping.examples.is_empty());
+        assert_eq!(ping.payload, Value::Null);
+    }
+
+    #[test]
+    fn test_load_webhooks_ref() {
+        let webhooks = load(json!({
+            "openapi": "3.1.0",
+            "info": {"title": "Webhooks", "version": "1.0.0"},
+            "webhooks": {
+                "newPet": {"post": {
+                    "requestBody": {"content": {"application/json": {
+                        "schema": {"$ref": "#/components/schemas/Pet"},
+                        "examples": {"cat": {"$ref": "#/components/examples/Cat"}}
+                    }}},
+                    "responses": {}
+                }}
+            },
+            "components": {
+                "schemas": {"Pet": {
+                    "type": "object",
+                    "properties": {"name": {"type": "string", "const": "rex"}}
+                }},
+                "examples": {"Cat": {"value": {"name": "tom"}}}
+            }
+        }));
+        assert_eq!(webhooks.len(), 1);
+        assert_eq!(webhooks[0].payload(None), Some(&json!({"name": "rex"})));
+        assert_eq!(
+            webhooks[0].payload(Some("cat")),
+            Some(&json!({"name": "tom"}))
+        );
+    }
+
+    #[test]
+    fn test_load_webhooks_sorted() {
+        let webhooks = load(json!({
+            "openapi": "3.1.0",
+            "info": {"title": "Webhooks", "version": "1.0.0"},
+            "webhooks": {
+                "petDeleted": {"delete": {"responses": {}}},
+                "newPet": {"put": {"responses": {}}, "post": {"responses": {}}}
+            }
+        }));
+        let names: Vec<(&str, &Method)> = webhooks
+            .iter()
+            .map(|webhook| (webhook.name.as_str(), &webhook.method))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("newPet", &Method::PUT),
+                ("newPet", &Method::POST),
+                ("petDeleted", &Method::DELETE),
+            ]
+        );
+    }
+}
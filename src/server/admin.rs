@@ -0,0 +1,772 @@
+//! The admin API of the mock server, mounted under `/__mocker`.
+//!
+//! Overrides replace the response of the requests they match, without
+//! editing the spec: tests can register them mid-run to make an endpoint
+//! fail, then reset the server to its initial state.
+//!
+//! - `GET /__mocker/overrides` lists the active overrides.
+//! - `POST /__mocker/overrides` registers an override.
+//! - `DELETE /__mocker/overrides/{id}` removes an override.
+//! - `DELETE /__mocker/overrides` removes every override.
+//! - `GET /__mocker/requests` lists the recorded requests, filtered by the
+//!   query parameters.
+//! - `DELETE /__mocker/requests` clears the journal of recorded requests.
+//! - `POST /__mocker/requests/verify` checks the number of recorded requests
+//!   matching a filter.
+//! - `POST /__mocker/reset` removes every override, clears the journal and
+//!   resets the store of the stateful mode.
+//! - `GET /__mocker/spec` summarizes the loaded spec, the first one when
+//!   serving several specs.
+//! - `GET /__mocker/specs` summarizes every loaded spec.
+//! - `POST /__mocker/reload` reloads the specs from their file.
+//! - `GET /__mocker/webhooks` lists the webhooks of the specs, with their
+//!   payload.
+//! - `POST /__mocker/webhooks/{name}` sends the payload of a webhook to a
+//!   consumer.
+use std::collections::BTreeMap;
+
+use actix_web::{
+    http::{header, StatusCode},
+    web, HttpRequest, HttpResponse, Scope,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{journal::RequestFilter, mount::MountedSpec, AppState};
+use crate::openapi::{content::serialize, router::RouteMatch, spec::match_example_name};
+
+/// The prefix the admin API is mounted under.
+pub const ADMIN_PREFIX: &str = "/__mocker";
+
+/// A response registered at runtime, replacing the response of the
+/// requests it matches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Override {
+    /// The identifier of the override, assigned when it is registered.
+    #[serde(default, skip_deserializing)]
+    pub id: u64,
+    /// The method to match, or None to match any method.
+    #[serde(default)]
+    pub method: Option<String>,
+    /// The request path to match, e.g. `/pets/1`, or a path of the spec
+    /// to match all of its requests, e.g. `/pets/{petId}`.
+    pub path: String,
+    /// An example name to match, e.g. `query:page=1` or `header:x-api-key=123`.
+    #[serde(default, rename = "match", skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<String>,
+    /// The status of the response.
+    #[serde(default = "default_status")]
+    pub status: u16,
+    /// The headers of the response.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// The body of the response, serialized as JSON unless it is a string
+    /// and the headers set a `Content-Type`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Value>,
+    /// The number of requests the override still applies to, or None if
+    /// it applies until it is removed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub times: Option<u32>,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+impl Override {
+    /// Check whether the override matches a request.
+    /// # Arguments
+    /// * `req` - The request
+    /// * `route` - The path of the spec matching the request, if any
+    /// * `body` - The request body
+    pub fn matches(&self, req: &HttpRequest, route: Option<&RouteMatch>, body: &[u8]) -> bool {
+        let method = self
+            .method
+            .as_ref()
+            .is_none_or(|method| method.eq_ignore_ascii_case(req.method().as_str()));
+        let path = self.path == req.path()
+            || route.is_some_and(|route| self.path == route.path || self.path == route.key);
+        let matcher = self
+            .matcher
+            .as_ref()
+            .is_none_or(|matcher| match_example_name(req, route, body, matcher));
+        method && path && matcher
+    }
+
+    /// Build the response of the override.
+    pub fn respond(&self) -> HttpResponse {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        let mut response = HttpResponse::build(status);
+        for (name, value) in &self.headers {
+            response.append_header((name.as_str(), value.as_str()));
+        }
+
+        let content_type = self
+            .headers
+            .keys()
+            .any(|name| name.eq_ignore_ascii_case(header::CONTENT_TYPE.as_str()));
+        match &self.body {
+            Some(Value::String(body)) if content_type => response.body(body.clone()),
+            Some(body) => response.json(body),
+            None => response.finish(),
+        }
+    }
+}
+
+/// The overrides registered at runtime.
+#[derive(Debug, Default)]
+pub struct Overrides {
+    next_id: u64,
+    items: Vec<Override>,
+}
+
+impl Overrides {
+    /// Register an override, assigning its identifier.
+    pub fn add(&mut self, mut item: Override) -> Override {
+        self.next_id += 1;
+        item.id = self.next_id;
+        self.items.push(item.clone());
+        item
+    }
+
+    /// Remove an override. Returns false if no override has the identifier.
+    pub fn remove(&mut self, id: u64) -> bool {
+        let len = self.items.len();
+        self.items.retain(|item| item.id != id);
+        self.items.len() != len
+    }
+
+    /// Remove every override.
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// The active overrides, in the order they were registered.
+    pub fn list(&self) -> &[Override] {
+        &self.items
+    }
+
+    /// Take the override matching a request, the last registered first.
+    /// Overrides limited to a number of requests are removed once used up.
+    pub fn take(
+        &mut self,
+        req: &HttpRequest,
+        route: Option<&RouteMatch>,
+        body: &[u8],
+    ) -> Option<Override> {
+        let position = self
+            .items
+            .iter()
+            .rposition(|item| item.matches(req, route, body))?;
+        let item = &mut self.items[position];
+        match item.times {
+            Some(times) if times <= 1 => Some(self.items.remove(position)),
+            Some(times) => {
+                item.times = Some(times - 1);
+                Some(item.clone())
+            }
+            None => Some(item.clone()),
+        }
+    }
+}
+
+/// The expected number of requests matching a filter.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Verification {
+    #[serde(flatten)]
+    pub filter: RequestFilter,
+    /// The exact number of matching requests.
+    pub count: Option<usize>,
+    /// The minimum number of matching requests.
+    pub at_least: Option<usize>,
+    /// The maximum number of matching requests.
+    pub at_most: Option<usize>,
+}
+
+impl Verification {
+    /// Check whether a number of matching requests is expected. A
+    /// verification without count expects at least one request.
+    pub fn check(&self, count: usize) -> bool {
+        match (self.count, self.at_least, self.at_most) {
+            (None, None, None) => count >= 1,
+            (exact, at_least, at_most) => {
+                exact.is_none_or(|exact| count == exact)
+                    && at_least.is_none_or(|at_least| count >= at_least)
+                    && at_most.is_none_or(|at_most| count <= at_most)
+            }
+        }
+    }
+}
+
+/// A summary of a loaded spec.
+#[derive(Debug, Serialize)]
+pub struct SpecSummary {
+    pub title: Option<String>,
+    pub version: Option<String>,
+    /// The prefix the spec is mounted under, or an empty string.
+    pub prefix: String,
+    /// The port the spec is served on, or None for the port of the server.
+    pub port: Option<u16>,
+    pub base_paths: Vec<String>,
+    /// The declared paths with their methods.
+    pub paths: BTreeMap<String, Vec<String>>,
+    /// The collections served by the stateful mode, empty if it is disabled.
+    pub collections: Vec<String>,
+}
+
+/// A webhook of a loaded spec, as listed by the admin API.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookSummary {
+    pub name: String,
+    pub method: String,
+    pub operation_id: Option<String>,
+    pub media_type: Option<String>,
+    /// The names of the named examples of the payload.
+    pub examples: Vec<String>,
+    /// The payload sent when no named example is requested.
+    pub payload: Value,
+}
+
+/// A request to send the payload of a webhook to a consumer.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WebhookTrigger {
+    /// The URL of the consumer, e.g. `http://localhost:3000/hooks`.
+    pub url: String,
+    /// A named example of the payload, or None for the default payload.
+    #[serde(default)]
+    pub example: Option<String>,
+    /// The headers sent along with the payload.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Returns the Actix Web scope of the admin API.
+/// It must be registered before the scope returned by `get_scope`.
+pub fn get_admin_scope() -> Scope {
+    web::scope(ADMIN_PREFIX)
+        .route("/overrides", web::get().to(list_overrides))
+        .route("/overrides", web::post().to(add_override))
+        .route("/overrides", web::delete().to(clear_overrides))
+        .route("/overrides/{id}", web::delete().to(remove_override))
+        .route("/requests", web::get().to(list_requests))
+        .route("/requests", web::delete().to(clear_requests))
+        .route("/requests/verify", web::post().to(verify_requests))
+        .route("/reset", web::post().to(reset))
+        .route("/spec", web::get().to(spec_summary))
+        .route("/specs", web::get().to(specs_summary))
+        .route("/reload", web::post().to(reload))
+        .route("/webhooks", web::get().to(list_webhooks))
+        .route("/webhooks/{name}", web::post().to(trigger_webhook))
+}
+
+async fn list_overrides(data: web::Data<AppState>) -> HttpResponse {
+    let overrides = data.overrides.lock().unwrap_or_else(|e| e.into_inner());
+    HttpResponse::Ok().json(overrides.list())
+}
+
+async fn add_override(item: web::Json<Override>, data: web::Data<AppState>) -> HttpResponse {
+    let item = item.into_inner();
+    if StatusCode::from_u16(item.status).is_err() {
+        return HttpResponse::BadRequest().json(json!({ "message": "Invalid status code" }));
+    }
+    let mut overrides = data.overrides.lock().unwrap_or_else(|e| e.into_inner());
+    HttpResponse::Created().json(overrides.add(item))
+}
+
+async fn clear_overrides(data: web::Data<AppState>) -> HttpResponse {
+    let mut overrides = data.overrides.lock().unwrap_or_else(|e| e.into_inner());
+    overrides.clear();
+    HttpResponse::NoContent().finish()
+}
+
+async fn remove_override(id: web::Path<u64>, data: web::Data<AppState>) -> HttpResponse {
+    let mut overrides = data.overrides.lock().unwrap_or_else(|e| e.into_inner());
+    match overrides.remove(id.into_inner()) {
+        true => HttpResponse::NoContent().finish(),
+        false => HttpResponse::NotFound().finish(),
+    }
+}
+
+async fn list_requests(
+    filter: web::Query<RequestFilter>,
+    data: web::Data<AppState>,
+) -> HttpResponse {
+    let journal = data.journal.lock().unwrap_or_else(|e| e.into_inner());
+    HttpResponse::Ok().json(journal.find(&filter))
+}
+
+async fn clear_requests(data: web::Data<AppState>) -> HttpResponse {
+    data.journal
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clear();
+    HttpResponse::NoContent().finish()
+}
+
+/// Respond `200 OK` with the number of matching requests if it is the
+/// expected one, or `417 Expectation Failed` along with the matching
+/// requests otherwise.
+async fn verify_requests(
+    verification: web::Json<Verification>,
+    data: web::Data<AppState>,
+) -> HttpResponse {
+    let journal = data.journal.lock().unwrap_or_else(|e| e.into_inner());
+    let requests = journal.find(&verification.filter);
+    match verification.check(requests.len()) {
+        true => HttpResponse::Ok().json(json!({ "count": requests.len() })),
+        false => HttpResponse::ExpectationFailed().json(json!({
+            "count": requests.len(),
+            "requests": requests,
+        })),
+    }
+}
+
+async fn reset(data: web::Data<AppState>) -> HttpResponse {
+    data.overrides
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clear();
+    data.journal
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clear();
+    for store in data
+        .specs
+        .iter()
+        .filter_map(|mounted| mounted.store.as_ref())
+    {
+        store.lock().unwrap_or_else(|e| e.into_inner()).reset();
+    }
+    HttpResponse::NoContent().finish()
+}
+
+/// Summarize the first spec served.
+async fn spec_summary(data: web::Data<AppState>) -> HttpResponse {
+    match data.specs.first() {
+        Some(mounted) => HttpResponse::Ok().json(summarize(mounted)),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+/// Summarize every spec served, in the order they were given.
+async fn specs_summary(data: web::Data<AppState>) -> HttpResponse {
+    let summaries: Vec<SpecSummary> = data.specs.iter().map(summarize).collect();
+    HttpResponse::Ok().json(summaries)
+}
+
+fn summarize(mounted: &MountedSpec) -> SpecSummary {
+    let spec = mounted.spec();
+    let info = |field: &str| {
+        spec.document()
+            .pointer(&format!("/info/{}", field))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    let paths = spec
+        .paths()
+        .into_iter()
+        .map(|(key, methods)| {
+            let methods = methods.iter().map(|m| m.to_string()).collect();
+            (key.to_string(), methods)
+        })
+        .collect();
+    let collections = match &mounted.store {
+        Some(_) => spec.collections().into_iter().map(|c| c.path).collect(),
+        None => Vec::new(),
+    };
+
+    SpecSummary {
+        title: info("title"),
+        version: info("version"),
+        prefix: spec.prefix().to_string(),
+        port: mounted.port,
+        base_paths: spec.base_paths().to_vec(),
+        paths,
+        collections,
+    }
+}
+
+/// Respond `200 OK` once the spec is reloaded, or `422 Unprocessable
+/// Entity` with the error if it fails to load, keeping the current spec.
+async fn reload(data: web::Data<AppState>) -> HttpResponse {
+    match data.reload() {
+        Ok(()) => HttpResponse::Ok().json(json!({ "message": "Spec reloaded" })),
+        Err(error) => HttpResponse::UnprocessableEntity().json(json!({ "message": error })),
+    }
+}
+
+async fn list_webhooks(data: web::Data<AppState>) -> HttpResponse {
+    let mut webhooks = Vec::new();
+    for mounted in &data.specs {
+        let spec = mounted.spec();
+        webhooks.extend(spec.webhooks().iter().map(|webhook| {
+            WebhookSummary {
+                name: webhook.name.clone(),
+                method: webhook.method.to_string(),
+                operation_id: webhook.operation_id.clone(),
+                media_type: webhook.media_type.clone(),
+                examples: webhook
+                    .examples
+                    .iter()
+                    .map(|(name, _)| name.clone())
+                    .collect(),
+                payload: webhook.payload.clone(),
+            }
+        }));
+    }
+    HttpResponse::Ok().json(webhooks)
+}
+
+/// Send the payload of a webhook to a consumer, and respond `200 OK` with
+/// the status the consumer responded with. Responds `404 Not Found` if the
+/// webhook or its named example is not declared, and `502 Bad Gateway` if
+/// the consumer cannot be reached.
+async fn trigger_webhook(
+    name: web::Path<String>,
+    trigger: web::Json<WebhookTrigger>,
+    data: web::Data<AppState>,
+) -> HttpResponse {
+    let specs: Vec<_> = data.specs.iter().map(|mounted| mounted.spec()).collect();
+    let Some(webhook) = specs
+        .iter()
+        .flat_map(|spec| spec.webhooks())
+        .find(|webhook| webhook.name == *name)
+    else {
+        let message = format!("Webhook {} not found", name);
+        return HttpResponse::NotFound().json(json!({ "message": message }));
+    };
+    let Some(payload) = webhook.payload(trigger.example.as_deref()) else {
+        let message = format!(
+            "Example {} not found",
+            trigger.example.as_deref().unwrap_or_default()
+        );
+        return HttpResponse::NotFound().json(json!({ "message": message }));
+    };
+
+    let method = reqwest::Method::from_bytes(webhook.method.as_str().as_bytes())
+        .unwrap_or(reqwest::Method::POST);
+    let mut request = reqwest::Client::new().request(method, &trigger.url);
+    if let Some(media_type) = &webhook.media_type {
+        request = request
+            .header(header::CONTENT_TYPE.as_str(), media_type.as_str())
+            .body(serialize(payload, media_type));
+    }
+    for (name, value) in &trigger.headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+
+    match request.send().await {
+        Ok(response) => HttpResponse::Ok().json(json!({ "status": response.status().as_u16() })),
+        Err(error) => {
+            println!(
+                "Failed to send the webhook {} to {}: {}",
+                name, trigger.url, error
+            );
+            let message = format!("Failed to reach {}", trigger.url);
+            HttpResponse::BadGateway().json(json!({ "message": message }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        openapi::spec::Spec,
+        server::{get_scope, reload::SpecSource},
+    };
+    use actix_web::{
+        test::{call_and_read_body_json, call_service, init_service, read_body_json, TestRequest},
+        App,
+    };
+
+    fn load_override(value: Value) -> Override {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_override_matches() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let item = load_override(json!({"method": "get", "path": "/pets/{petId}"}));
+        let req = TestRequest::with_uri("/v1/pets/1").to_http_request();
+        let route = spec.match_path(req.path());
+        assert!(item.matches(&req, route.as_ref(), &[]));
+
+        let req = TestRequest::post().uri("/pets/1").to_http_request();
+        let route = spec.match_path(req.path());
+        assert!(!item.matches(&req, route.as_ref(), &[]));
+
+        let item = load_override(json!({"path": "/pets", "match": "query:page=2"}));
+        let req = TestRequest::with_uri("/pets?page=2").to_http_request();
+        assert!(item.matches(&req, None, &[]));
+        let req = TestRequest::with_uri("/pets?page=1").to_http_request();
+        assert!(!item.matches(&req, None, &[]));
+
+        let item = load_override(json!({"path": "/pets/{petId}", "match": "param:petId=2"}));
+        let req = TestRequest::with_uri("/v1/pets/2").to_http_request();
+        assert!(item.matches(&req, spec.match_path(req.path()).as_ref(), &[]));
+        let req = TestRequest::with_uri("/v1/pets/1").to_http_request();
+        assert!(!item.matches(&req, spec.match_path(req.path()).as_ref(), &[]));
+
+        let item = load_override(json!({"path": "/pets", "match": "body:/kind=cat"}));
+        let req = TestRequest::post().uri("/pets").to_http_request();
+        assert!(item.matches(&req, None, br#"{"kind": "cat"}"#));
+        assert!(!item.matches(&req, None, br#"{"kind": "dog"}"#));
+    }
+
+    #[test]
+    fn test_overrides_take() {
+        let mut overrides = Overrides::default();
+        overrides.add(load_override(json!({"path": "/pets", "status": 500})));
+        overrides.add(load_override(
+            json!({"path": "/pets", "status": 503, "times": 2}),
+        ));
+
+        let req = TestRequest::with_uri("/pets").to_http_request();
+        let statuses: Vec<u16> = (0..3)
+            .filter_map(|_| overrides.take(&req, None, &[]))
+            .map(|item| item.status)
+            .collect();
+        assert_eq!(statuses, vec![503, 503, 500]);
+        assert_eq!(overrides.list().len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_admin_overrides() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new()
+            .app_data(data.clone())
+            .service(get_admin_scope())
+            .service(get_scope());
+        let app = init_service(app).await;
+
+        let req = TestRequest::post()
+            .uri("/__mocker/overrides")
+            .set_json(
+                json!({"path": "/pets", "status": 503, "body": {"message": "down"}, "times": 1}),
+            )
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+        let item: Value = read_body_json(resp).await;
+        assert_eq!(item["id"], 1);
+
+        let req = TestRequest::get().uri("/pets").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 503);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["message"], "down");
+
+        let req = TestRequest::get().uri("/pets").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = TestRequest::get().uri("/__mocker/overrides").to_request();
+        let overrides: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(overrides, json!([]));
+    }
+
+    #[test]
+    fn test_verification_check() {
+        let verification =
+            |value: Value| -> Verification { serde_json::from_value(value).unwrap() };
+        assert!(verification(json!({"path": "/pets"})).check(1));
+        assert!(!verification(json!({"path": "/pets"})).check(0));
+        assert!(verification(json!({"count": 0})).check(0));
+        assert!(verification(json!({"atLeast": 1, "atMost": 2})).check(2));
+        assert!(!verification(json!({"atLeast": 1, "atMost": 2})).check(3));
+    }
+
+    #[actix_rt::test]
+    async fn test_admin_requests() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new()
+            .app_data(data.clone())
+            .service(get_admin_scope())
+            .service(get_scope());
+        let app = init_service(app).await;
+
+        for uri in ["/pets?page=1", "/pets/2", "/pets/3"] {
+            call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+        }
+
+        let req = TestRequest::get()
+            .uri("/__mocker/requests?operationId=showPetById")
+            .to_request();
+        let requests: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(requests.as_array().unwrap().len(), 2);
+        assert_eq!(requests[0]["path"], "/pets/2");
+        assert_eq!(requests[0]["route"], "/pets/{petId}");
+        assert_eq!(requests[0]["example"], "/pets/2");
+        assert_eq!(requests[0]["status"], 200);
+
+        let req = TestRequest::post()
+            .uri("/__mocker/requests/verify")
+            .set_json(json!({"method": "GET", "path": "/pets", "query": {"page": "1"}, "count": 1}))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+
+        let req = TestRequest::post()
+            .uri("/__mocker/requests/verify")
+            .set_json(json!({"method": "POST", "path": "/pets"}))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 417);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["count"], 0);
+    }
+
+    #[actix_rt::test]
+    async fn test_admin_reset() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec).stateful());
+        let app = App::new()
+            .app_data(data.clone())
+            .service(get_admin_scope())
+            .service(get_scope());
+        let app = init_service(app).await;
+
+        let req = TestRequest::post()
+            .uri("/__mocker/overrides")
+            .set_json(json!({"method": "GET", "path": "/pets/1", "status": 404}))
+            .to_request();
+        call_service(&app, req).await;
+        let req = TestRequest::delete().uri("/pets/2").to_request();
+        call_service(&app, req).await;
+
+        let req = TestRequest::post().uri("/__mocker/reset").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 204);
+
+        for path in ["/pets/1", "/pets/2"] {
+            let req = TestRequest::get().uri(path).to_request();
+            let resp = call_service(&app, req).await;
+            assert_eq!(resp.status(), 200);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_admin_reload() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_admin_scope());
+        let app = init_service(app).await;
+
+        let req = TestRequest::post().uri("/__mocker/reload").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+    }
+
+    #[actix_rt::test]
+    async fn test_admin_reload_invalid_rules() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let source = SpecSource::new("tests/testdata/invalid_rules.yaml");
+        let data = web::Data::new(AppState::with_specs(vec![MountedSpec {
+            source: Some(source),
+            ..MountedSpec::new(spec)
+        }]));
+        let app = App::new().app_data(data.clone()).service(get_admin_scope());
+        let app = init_service(app).await;
+
+        let req = TestRequest::post().uri("/__mocker/reload").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 422);
+        let body: Value = read_body_json(resp).await;
+        let message = body["message"].as_str().unwrap();
+        assert!(message.contains("example broken"), "{}", message);
+        assert!(data.specs[0].spec().document()["paths"]["/pets/{petId}"].is_object());
+    }
+
+    #[actix_rt::test]
+    async fn test_admin_spec_summary() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_admin_scope());
+        let app = init_service(app).await;
+
+        let req = TestRequest::get().uri("/__mocker/spec").to_request();
+        let summary: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(summary["title"], "Swagger Petstore");
+        assert_eq!(summary["base_paths"], json!(["/v1"]));
+        assert_eq!(summary["paths"]["/pets"], json!(["GET", "POST"]));
+        assert_eq!(summary["collections"], json!([]));
+    }
+
+    #[actix_rt::test]
+    async fn test_admin_specs_summary() {
+        let petstore =
+            Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let users = Spec::from_path("tests/testdata/generated.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::with_specs(vec![
+            MountedSpec::new(petstore),
+            MountedSpec {
+                port: Some(8081),
+                ..MountedSpec::new(users.with_prefix("/users"))
+            },
+        ]));
+        let app = App::new().app_data(data.clone()).service(get_admin_scope());
+        let app = init_service(app).await;
+
+        let req = TestRequest::get().uri("/__mocker/specs").to_request();
+        let summaries: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(summaries[0]["title"], "Swagger Petstore");
+        assert_eq!(summaries[0]["prefix"], "");
+        assert_eq!(summaries[1]["prefix"], "/users");
+        assert_eq!(summaries[1]["port"], 8081);
+    }
+
+    #[actix_rt::test]
+    async fn test_admin_webhooks() {
+        // A consumer answering with the name of the received pet
+        let server = actix_web::HttpServer::new(|| {
+            App::new().default_service(web::to(|body: web::Json<Value>| async move {
+                match body["name"].as_str() {
+                    Some("kitty") => HttpResponse::Accepted().finish(),
+                    _ => HttpResponse::Ok().finish(),
+                }
+            }))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .expect("failed to bind the consumer");
+        let consumer = format!("http://{}/hooks", server.addrs()[0]);
+        actix_rt::spawn(server.run());
+
+        let spec = Spec::from_path("tests/testdata/openapi31.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_admin_scope());
+        let app = init_service(app).await;
+
+        let req = TestRequest::get().uri("/__mocker/webhooks").to_request();
+        let webhooks: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(webhooks[0]["name"], "newPet");
+        assert_eq!(webhooks[0]["examples"], json!(["kitty"]));
+
+        let req = TestRequest::post()
+            .uri("/__mocker/webhooks/newPet")
+            .set_json(json!({"url": consumer}))
+            .to_request();
+        let body: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(body["status"], 200);
+
+        let req = TestRequest::post()
+            .uri("/__mocker/webhooks/newPet")
+            .set_json(json!({"url": consumer, "example": "kitty"}))
+            .to_request();
+        let body: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(body["status"], 202);
+
+        let req = TestRequest::post()
+            .uri("/__mocker/webhooks/oldPet")
+            .set_json(json!({"url": consumer}))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+    }
+}
@@ -0,0 +1,216 @@
+//! The journal of the requests handled by the mock server.
+//!
+//! Every request dispatched to the spec is recorded, so that tests can
+//! verify their service called its dependencies as expected. The journal
+//! is bounded: once full, the oldest requests are dropped.
+use std::collections::{BTreeMap, VecDeque};
+
+use actix_web::{web, HttpRequest};
+use serde::{Deserialize, Serialize};
+
+/// The default number of requests kept in the journal.
+pub const DEFAULT_JOURNAL_SIZE: usize = 1000;
+
+/// A request recorded in the journal.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    /// The sequence number of the request, starting at 1.
+    pub id: u64,
+    pub method: String,
+    pub path: String,
+    /// The raw query string, without the leading `?`.
+    pub query: String,
+    /// The request headers, with lowercase names. Repeated headers are
+    /// joined with commas.
+    pub headers: BTreeMap<String, String>,
+    /// The request body, decoded as UTF-8.
+    pub body: String,
+    /// The path of the spec matching the request, e.g. `/pets/{petId}`.
+    pub route: Option<String>,
+    /// The `operationId` of the operation matching the request.
+    pub operation_id: Option<String>,
+    /// The name of the named example returned, if any.
+    pub example: Option<String>,
+    /// The status of the response.
+    pub status: u16,
+}
+
+impl JournalEntry {
+    /// Create an entry from a request, before it is handled.
+    pub fn from_request(req: &HttpRequest, body: &[u8]) -> Self {
+        let mut headers: BTreeMap<String, String> = BTreeMap::new();
+        for (name, value) in req.headers() {
+            let value = value.to_str().unwrap_or_default();
+            headers
+                .entry(name.as_str().to_string())
+                .and_modify(|values| {
+                    values.push_str(", ");
+                    values.push_str(value);
+                })
+                .or_insert_with(|| value.to_string());
+        }
+
+        Self {
+            id: 0,
+            method: req.method().to_string(),
+            path: req.path().to_string(),
+            query: req.query_string().to_string(),
+            headers,
+            body: String::from_utf8_lossy(body).to_string(),
+            route: None,
+            operation_id: None,
+            example: None,
+            status: 0,
+        }
+    }
+}
+
+/// A filter of the requests recorded in the journal. Every field set
+/// must match.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RequestFilter {
+    pub method: Option<String>,
+    /// The request path, e.g. `/pets/1`, or the path of the spec, e.g.
+    /// `/pets/{petId}`.
+    pub path: Option<String>,
+    pub operation_id: Option<String>,
+    pub example: Option<String>,
+    pub status: Option<u16>,
+    /// Query parameters the request must have.
+    pub query: BTreeMap<String, String>,
+    /// Headers the request must have, matched case-insensitively by name.
+    pub headers: BTreeMap<String, String>,
+    /// Text the request body must contain.
+    pub body: Option<String>,
+}
+
+impl RequestFilter {
+    /// Check whether a recorded request matches the filter.
+    pub fn matches(&self, entry: &JournalEntry) -> bool {
+        let method = self
+            .method
+            .as_ref()
+            .is_none_or(|method| method.eq_ignore_ascii_case(&entry.method));
+        let path = self
+            .path
+            .as_ref()
+            .is_none_or(|path| path == &entry.path || entry.route.as_ref() == Some(path));
+        let operation_id = self.operation_id.is_none() || self.operation_id == entry.operation_id;
+        let example = self.example.is_none() || self.example == entry.example;
+        let status = self.status.is_none_or(|status| status == entry.status);
+
+        let params = web::Query::<Vec<(String, String)>>::from_query(&entry.query)
+            .map(|query| query.into_inner())
+            .unwrap_or_default();
+        let query = self
+            .query
+            .iter()
+            .all(|(name, value)| params.iter().any(|(n, v)| n == name && v == value));
+        let headers = self
+            .headers
+            .iter()
+            .all(|(name, value)| entry.headers.get(&name.to_lowercase()) == Some(value));
+        let body = self
+            .body
+            .as_ref()
+            .is_none_or(|body| entry.body.contains(body.as_str()));
+
+        method && path && operation_id && example && status && query && headers && body
+    }
+}
+
+/// The bounded journal of the requests handled by the mock server.
+#[derive(Debug)]
+pub struct Journal {
+    capacity: usize,
+    next_id: u64,
+    entries: VecDeque<JournalEntry>,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self::new(DEFAULT_JOURNAL_SIZE)
+    }
+}
+
+impl Journal {
+    /// Create a journal keeping up to `capacity` requests.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            next_id: 0,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Record a request, dropping the oldest one if the journal is full.
+    pub fn record(&mut self, mut entry: JournalEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.next_id += 1;
+        entry.id = self.next_id;
+        self.entries.push_back(entry);
+    }
+
+    /// Get the recorded requests matching a filter, oldest first.
+    pub fn find(&self, filter: &RequestFilter) -> Vec<&JournalEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| filter.matches(entry))
+            .collect()
+    }
+
+    /// Remove every recorded request.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn entry(uri: &str) -> JournalEntry {
+        let req = TestRequest::with_uri(uri)
+            .insert_header(("x-api-key", "123"))
+            .to_http_request();
+        JournalEntry::from_request(&req, b"{\"name\": \"rex\"}")
+    }
+
+    #[test]
+    fn test_journal_bounded() {
+        let mut journal = Journal::new(2);
+        for uri in ["/pets/1", "/pets/2", "/pets/3"] {
+            journal.record(entry(uri));
+        }
+        let entries = journal.find(&RequestFilter::default());
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["/pets/2", "/pets/3"]);
+        assert_eq!(entries[1].id, 3);
+    }
+
+    #[test]
+    fn test_request_filter() {
+        let mut recorded = entry("/pets/1?page=1&tag=dog");
+        recorded.route = Some("/pets/{petId}".to_string());
+
+        let filter =
+            |value: serde_json::Value| -> RequestFilter { serde_json::from_value(value).unwrap() };
+        assert!(
+            filter(serde_json::json!({"path": "/pets/{petId}", "method": "get"}))
+                .matches(&recorded)
+        );
+        assert!(filter(serde_json::json!({"query": {"tag": "dog"}})).matches(&recorded));
+        assert!(filter(serde_json::json!({"headers": {"X-Api-Key": "123"}})).matches(&recorded));
+        assert!(filter(serde_json::json!({"body": "rex"})).matches(&recorded));
+        assert!(!filter(serde_json::json!({"query": {"tag": "cat"}})).matches(&recorded));
+        assert!(!filter(serde_json::json!({"path": "/pets"})).matches(&recorded));
+    }
+}
@@ -0,0 +1,763 @@
+pub mod admin;
+pub mod journal;
+pub mod mount;
+pub mod proxy;
+pub mod reload;
+pub mod store;
+
+use crate::openapi::{
+    content::serialize, directive::Directive, response::MockError, spec::Spec, validator::Violation,
+};
+use actix_web::{
+    http::{header, Method},
+    web, HttpRequest, HttpResponse, Scope,
+};
+use admin::Overrides;
+use journal::{Journal, JournalEntry};
+use mount::{dispatch_order, MountedSpec};
+use proxy::Proxy;
+use std::sync::{Arc, Mutex};
+use store::Store;
+
+/// Application state for the Actix Web server.
+pub struct AppState {
+    /// The specs being served, in the order they were given.
+    pub specs: Vec<MountedSpec>,
+    /// Reject requests that do not conform to the spec with a 400 response.
+    pub validate_requests: bool,
+    /// How responses that do not conform to their schema are reported,
+    /// or None to skip response validation.
+    pub validate_responses: Option<ResponseValidation>,
+    /// Add an `x-mock-reason` header explaining why the response was
+    /// selected.
+    pub explain: bool,
+    /// The maximum size of the request bodies, in bytes. Larger requests
+    /// are rejected with a 413 response.
+    pub max_body_size: usize,
+    /// The overrides registered through the admin API.
+    pub overrides: Mutex<Overrides>,
+    /// The journal of the requests dispatched to the spec.
+    pub journal: Mutex<Journal>,
+    /// The upstream the requests no named example matches are forwarded
+    /// to, or None to serve them from the spec.
+    pub proxy: Option<Proxy>,
+}
+
+impl AppState {
+    /// Create the application state for a spec, with the default options.
+    pub fn new(spec: Spec) -> Self {
+        Self::with_specs(vec![MountedSpec::new(spec)])
+    }
+
+    /// Create the application state for several specs, with the default
+    /// options.
+    pub fn with_specs(specs: Vec<MountedSpec>) -> Self {
+        Self {
+            specs,
+            validate_requests: false,
+            validate_responses: None,
+            explain: false,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            overrides: Mutex::default(),
+            journal: Mutex::default(),
+            proxy: None,
+        }
+    }
+
+    /// Enable the stateful mode, serving the collections of the specs
+    /// from in-memory stores seeded with their examples.
+    pub fn stateful(self) -> Self {
+        let specs = self
+            .specs
+            .into_iter()
+            .map(|mounted| MountedSpec {
+                store: Some(Mutex::new(Store::new(mounted.spec().collections()))),
+                ..mounted
+            })
+            .collect();
+        Self { specs, ..self }
+    }
+
+    /// Select the spec handling a request, among the specs served on the
+    /// port the request was received on: the first spec with a path
+    /// matching the request, or else the first spec whose prefix the
+    /// request is under.
+    /// # Returns
+    /// The mounted spec along with the spec being served, or None if no
+    /// spec is served on the port.
+    pub fn select(&self, req: &HttpRequest) -> Option<(&MountedSpec, Arc<Spec>)> {
+        let port = req.app_config().local_addr().port();
+        let claimed = self.specs.iter().any(|mounted| mounted.port == Some(port));
+        let served: Vec<&MountedSpec> = self
+            .specs
+            .iter()
+            .filter(|mounted| match mounted.port {
+                Some(p) => p == port,
+                None => !claimed,
+            })
+            .collect();
+
+        let order = dispatch_order(&served);
+        let under_prefix = |spec: &Spec| {
+            let rest = req.path().strip_prefix(spec.prefix());
+            rest.is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        };
+        let (position, spec) = order
+            .iter()
+            .find(|(_, spec)| spec.match_path(req.path()).is_some())
+            .or_else(|| order.iter().find(|(_, spec)| under_prefix(spec)))
+            .or(order.first())?;
+        Some((served[*position], spec.clone()))
+    }
+
+    /// Load the specs again from their source, and swap them in. The
+    /// stores of the stateful mode are seeded again from the new specs.
+    /// # Errors
+    /// Returns the errors, keeping the current spec, of the specs with no
+    /// source or failing to load. The other specs are reloaded.
+    pub fn reload(&self) -> Result<(), String> {
+        let errors: Vec<String> = (0..self.specs.len())
+            .filter_map(|index| self.reload_spec(index).err())
+            .collect();
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors.join("; ")),
+        }
+    }
+
+    /// Load a spec again from its source, see `reload`. The document
+    /// recorded in proxy mode is copied again from the new spec.
+    /// # Errors
+    /// Returns the error, keeping the current spec, if the spec has no
+    /// source or fails to load.
+    pub fn reload_spec(&self, index: usize) -> Result<(), String> {
+        let mounted = &self.specs[index];
+        mounted.reload()?;
+        if let Some(proxy) = &self.proxy {
+            proxy.refresh(&mounted.spec());
+        }
+        Ok(())
+    }
+}
+
+/// How a response that does not conform to its schema is reported.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ResponseValidation {
+    /// Log a warning and return the response as is.
+    Warn,
+    /// Return the response with an `x-mock-validation` header describing
+    /// the result of the validation.
+    Header,
+    /// Fail with a 500 response listing the violations.
+    Fail,
+}
+
+/// The header describing the result of the response validation.
+const VALIDATION_HEADER: &str = "x-mock-validation";
+
+/// The default maximum size of the request bodies: 64 MiB.
+pub const DEFAULT_MAX_BODY_SIZE: usize = 64 * 1024 * 1024;
+
+/// The header explaining why the response was selected.
+const REASON_HEADER: &str = "x-mock-reason";
+
+/// Returns a new Actix Web scope with all the routes for the server.
+/// Requests of any HTTP method are dispatched to the spec.
+pub fn get_scope() -> Scope {
+    web::scope("").default_service(web::to(handle_all))
+}
+
+async fn handle_all(
+    req: HttpRequest,
+    payload: web::Payload,
+    data: web::Data<AppState>,
+) -> HttpResponse {
+    // The body is read up to the configured size, rather than the 256 KB
+    // limit of the `Bytes` extractor
+    let body = match payload.to_bytes_limited(data.max_body_size).await {
+        Ok(Ok(body)) => body,
+        Ok(Err(error)) => return error.error_response(),
+        Err(_) => return HttpResponse::PayloadTooLarge().finish(),
+    };
+    let mut entry = JournalEntry::from_request(&req, &body);
+    let response = respond(&req, &body, &data, &mut entry).await;
+    entry.status = response.status().as_u16();
+    data.journal
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .record(entry);
+    response
+}
+
+/// Respond to a request, filling the journal entry with the operation and
+/// the example the request matched.
+async fn respond(
+    req: &HttpRequest,
+    body: &[u8],
+    data: &AppState,
+    entry: &mut JournalEntry,
+) -> HttpResponse {
+    let Some((mounted, spec)) = data.select(req) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let route = spec.match_path(req.path());
+    if let Some(route) = &route {
+        entry.route = Some(route.key.to_string());
+        entry.operation_id = spec
+            .operation_id(route.key, req.method())
+            .map(str::to_string);
+    }
+
+    if data.validate_requests {
+        let violations = spec.validate_request(req, body);
+        if !violations.is_empty() {
+            return HttpResponse::BadRequest().json(violations);
+        }
+    }
+
+    let overridden = data
+        .overrides
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take(req, route.as_ref(), body);
+    if let Some(overridden) = overridden {
+        return overridden.respond();
+    }
+
+    if let (Some(store), Some(route)) = (&mounted.store, &route) {
+        let mut store = store.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(response) = store.handle(route, req.method(), body) {
+            return match response.value {
+                Some(value) => HttpResponse::build(response.status).json(value),
+                None => HttpResponse::build(response.status).finish(),
+            };
+        }
+    }
+
+    let example = match data.explain {
+        true => spec.explain_example(req, body),
+        false => spec.get_example_with_body(req, body),
+    };
+    if let Some(proxy) = &data.proxy {
+        // The `default` example is a fallback, not a match of the request
+        let matched = example.as_ref().ok().and_then(|e| e.example.as_deref());
+        let directed = Directive::from_request(req).is_some();
+        if !directed && matched.is_none_or(|name| name == "default") {
+            return proxy.forward(req, body, route.as_ref()).await;
+        }
+    }
+
+    match example {
+        Ok(mut example) => {
+            entry.example = example.example.clone();
+            if let Some(reason) = example.reason.take() {
+                let reason = reason.escape_default().to_string();
+                example.headers.push((REASON_HEADER.to_string(), reason));
+            }
+            if let Some(mode) = data.validate_responses {
+                let violations = spec.validate_response(req, &example);
+                match mode {
+                    ResponseValidation::Warn if !violations.is_empty() => println!(
+                        "Warning: the response to {} {} does not match its schema: {}",
+                        req.method(),
+                        req.path(),
+                        describe(&violations)
+                    ),
+                    ResponseValidation::Header => {
+                        let validation = match violations.is_empty() {
+                            true => "valid".to_string(),
+                            false => format!("invalid; {}", describe(&violations)),
+                        };
+                        example
+                            .headers
+                            .push((VALIDATION_HEADER.to_string(), validation));
+                    }
+                    ResponseValidation::Fail if !violations.is_empty() => {
+                        return HttpResponse::InternalServerError().json(violations);
+                    }
+                    _ => {}
+                }
+            }
+
+            let mut response = HttpResponse::build(example.status);
+            for header in example.headers {
+                response.append_header(header);
+            }
+            match (example.value, example.content_type) {
+                (Some(value), Some(content_type)) => response
+                    .content_type(content_type.as_str())
+                    .body(serialize(&value, &content_type)),
+                (Some(value), None) => response.json(value),
+                (None, _) => response.finish(),
+            }
+        }
+        Err(MockError::MethodNotAllowed(methods)) => method_not_allowed(&methods),
+        Err(MockError::NotAcceptable(media_types)) => {
+            HttpResponse::NotAcceptable().json(media_types)
+        }
+        Err(MockError::ExampleNotFound(declared)) => {
+            let declared: Vec<serde_json::Value> = declared
+                .into_iter()
+                .map(|(code, example)| serde_json::json!({"code": code, "example": example}))
+                .collect();
+            HttpResponse::NotFound().json(declared)
+        }
+        Err(MockError::NotFound) => HttpResponse::NotFound().finish(),
+    }
+}
+
+/// Describe violations on a single line, escaping non ASCII characters
+/// so the description can be used as a header value.
+fn describe(violations: &[Violation]) -> String {
+    violations
+        .iter()
+        .map(|violation| format!("{} at {}", violation.keyword, violation.pointer))
+        .collect::<Vec<String>>()
+        .join(", ")
+        .escape_default()
+        .to_string()
+}
+
+fn method_not_allowed(methods: &[Method]) -> HttpResponse {
+    let allow = methods
+        .iter()
+        .map(|method| method.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ");
+    HttpResponse::MethodNotAllowed()
+        .insert_header((header::ALLOW, allow))
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+
+    #[actix_rt::test]
+    async fn test_request_default() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get().uri("/pets").to_request();
+        let resp = test::call_service(&app, req).await;
+        println!("{:?}", resp);
+        assert!(resp.status().is_success());
+
+        let expected_res = r#"[]"#;
+        let body = test::read_body(resp).await;
+        assert_eq!(body, expected_res);
+    }
+
+    #[actix_rt::test]
+    async fn test_request_query() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get().uri("/pets?page=1").to_request();
+        let resp = test::call_service(&app, req).await;
+        println!("{:?}", resp);
+        assert!(resp.status().is_success());
+
+        let expected_res =
+            r#"[{"id":1,"name":"doggie","tag":"dog"},{"id":2,"name":"kitty","tag":"cat"}]"#;
+        let body = test::read_body(resp).await;
+        assert_eq!(body, expected_res);
+    }
+
+    #[actix_rt::test]
+    async fn test_request_put() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::put().uri("/pets/1").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let expected_res = r#"{"id":1,"name":"updated doggie","tag":"dog"}"#;
+        let body = test::read_body(resp).await;
+        assert_eq!(body, expected_res);
+    }
+
+    #[actix_rt::test]
+    async fn test_request_method_not_allowed() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::patch().uri("/pets").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 405);
+        assert_eq!(resp.headers().get(header::ALLOW).unwrap(), "GET, POST");
+    }
+
+    #[actix_rt::test]
+    async fn test_request_status_code() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get().uri("/pets/5").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        let expected_res = r#"{"code":401,"message":"error"}"#;
+        let body = test::read_body(resp).await;
+        assert_eq!(body, expected_res);
+    }
+
+    #[actix_rt::test]
+    async fn test_request_no_content() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::delete().uri("/pets/1").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 204);
+
+        let body = test::read_body(resp).await;
+        assert!(body.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_request_generated() {
+        let spec = Spec::from_path("tests/testdata/generated.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get().uri("/users").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["role"], "admin");
+    }
+
+    #[actix_rt::test]
+    async fn test_request_headers() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get().uri("/pets?page=1").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("x-next").unwrap(), "/pets?page=2");
+        assert_eq!(resp.headers().get("x-rate-limit").unwrap(), "100");
+    }
+
+    #[actix_rt::test]
+    async fn test_request_accept() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get()
+            .uri("/pets")
+            .insert_header(("accept", "text/plain"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert_eq!(resp.headers().get("content-type").unwrap(), "text/plain");
+
+        let body = test::read_body(resp).await;
+        assert_eq!(body, "string");
+    }
+
+    #[actix_rt::test]
+    async fn test_request_base_path() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec.with_base_path("/api")));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get().uri("/api/pets").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let req = test::TestRequest::get().uri("/v1/pets").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[actix_rt::test]
+    async fn test_request_multiple_specs() {
+        let petstore =
+            Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let users = Spec::from_path("tests/testdata/generated.yaml").expect("failed to load spec");
+        let routes = Spec::from_path("tests/testdata/routes.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::with_specs(vec![
+            MountedSpec::new(petstore),
+            MountedSpec::new(users.with_prefix("/accounts")),
+            MountedSpec {
+                port: Some(8081),
+                ..MountedSpec::new(routes)
+            },
+        ]));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get().uri("/accounts/users").to_request();
+        let resp = test::call_service(&app, req).await;
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body[0]["role"], "admin");
+
+        let req = test::TestRequest::get().uri("/v1/pets/2").to_request();
+        let resp = test::call_service(&app, req).await;
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["name"], "kitty");
+
+        // The spec served on another port is not dispatched to
+        let req = test::TestRequest::get().uri("/files/1").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[actix_rt::test]
+    async fn test_request_stateful() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec).stateful());
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::post()
+            .uri("/pets")
+            .set_json(serde_json::json!({"name": "rex"}))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+
+        let req = test::TestRequest::get().uri("/v1/pets/5").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["name"], "rex");
+
+        let req = test::TestRequest::delete().uri("/pets/5").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 204);
+
+        let req = test::TestRequest::get().uri("/pets").to_request();
+        let resp = test::call_service(&app, req).await;
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body.as_array().unwrap().len(), 3);
+    }
+
+    #[actix_rt::test]
+    async fn test_request_not_acceptable() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get()
+            .uri("/pets/1")
+            .insert_header(("accept", "application/xml"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 406);
+
+        let body = test::read_body(resp).await;
+        assert_eq!(body, r#"["application/json"]"#);
+    }
+
+    #[actix_rt::test]
+    async fn test_request_validation() {
+        let spec = Spec::from_path("tests/testdata/validation.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState {
+            validate_requests: true,
+            ..AppState::new(spec)
+        });
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::post()
+            .uri("/pets")
+            .insert_header(("content-type", "application/json"))
+            .set_payload(r#"{"name": 1}"#)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body,
+            serde_json::json!([{
+                "pointer": "/body/name",
+                "keyword": "type",
+                "message": "expected string, found integer"
+            }])
+        );
+
+        let req = test::TestRequest::post()
+            .uri("/pets")
+            .insert_header(("content-type", "application/json"))
+            .set_payload(r#"{"name": "doggie"}"#)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 201);
+    }
+
+    #[actix_rt::test]
+    async fn test_request_validation_disabled() {
+        let spec = Spec::from_path("tests/testdata/validation.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get().uri("/pets?limit=abc").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+    }
+
+    #[actix_rt::test]
+    async fn test_response_validation_header() {
+        let spec = Spec::from_path("tests/testdata/validation.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState {
+            validate_responses: Some(ResponseValidation::Header),
+            ..AppState::new(spec)
+        });
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get()
+            .uri("/pets?status=sold")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert_eq!(
+            resp.headers().get(VALIDATION_HEADER).unwrap(),
+            "invalid; required at /body/0/name, enum at /body/0/tag"
+        );
+
+        let req = test::TestRequest::get().uri("/pets").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(VALIDATION_HEADER).unwrap(), "valid");
+    }
+
+    #[actix_rt::test]
+    async fn test_response_validation_fail() {
+        let spec = Spec::from_path("tests/testdata/validation.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState {
+            validate_responses: Some(ResponseValidation::Fail),
+            ..AppState::new(spec)
+        });
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get()
+            .uri("/pets?status=sold")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 500);
+
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body[0]["pointer"], "/body/0/name");
+        assert_eq!(body[1]["keyword"], "enum");
+    }
+
+    #[actix_rt::test]
+    async fn test_request_explain() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState {
+            explain: true,
+            ..AppState::new(spec)
+        });
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get().uri("/pets/2").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get(REASON_HEADER).unwrap(),
+            "example /pets/2 of response 200 satisfied the most conditions (1), matched: /pets/2 (1)"
+        );
+
+        let data = web::Data::new(AppState::new(
+            Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec"),
+        ));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get().uri("/pets/3").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.headers().get(REASON_HEADER).is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_request_directive() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get()
+            .uri("/pets/1")
+            .insert_header(("prefer", "code=401"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+
+        let req = test::TestRequest::get()
+            .uri("/pets/1")
+            .insert_header(("x-mock-status", "404"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body[0],
+            serde_json::json!({"code": "200", "example": "default"})
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_request_body_size() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState {
+            max_body_size: 2 * 1024 * 1024,
+            ..AppState::new(spec)
+        });
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        // Larger than the 256 KB limit of the `Bytes` extractor
+        let req = test::TestRequest::post()
+            .uri("/pets")
+            .set_payload(vec![b'a'; 1024 * 1024])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let req = test::TestRequest::post()
+            .uri("/pets")
+            .set_payload(vec![b'a'; 3 * 1024 * 1024])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 413);
+    }
+
+    #[actix_rt::test]
+    async fn test_request_not_found() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState::new(spec));
+        let app = App::new().app_data(data.clone()).service(get_scope());
+
+        let app = test::init_service(app).await;
+        let req = test::TestRequest::get().uri("/notfound").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_client_error());
+    }
+}
@@ -0,0 +1,273 @@
+//! Serving several specs from one server.
+//!
+//! Each spec is mounted under its own prefix, on its own port, or both. A
+//! request is dispatched to the specs served on the port it was received
+//! on, longest prefix first and then in the order the specs were given:
+//! the first spec with a path matching the request handles it. Paths no
+//! request can reach because another spec handles them first are reported
+//! as conflicts when the server starts.
+use std::cmp::Reverse;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
+
+use super::{reload::SpecSource, store::Store};
+use crate::openapi::spec::Spec;
+
+/// A spec file given on the command line, with where it is mounted:
+/// `<spec>`, `<spec>@/prefix`, `<spec>@:port` or `<spec>@:port/prefix`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecMount {
+    pub path: PathBuf,
+    /// The prefix the spec is mounted under, e.g. `/users`.
+    pub prefix: Option<String>,
+    /// The port the spec is served on, or None for the port of the server.
+    pub port: Option<u16>,
+}
+
+impl FromStr for SpecMount {
+    type Err = String;
+
+    /// Parse a spec mount.
+    /// # Example
+    /// ```rust
+    /// use openapi_mocker::server::mount::SpecMount;
+    /// let mount: SpecMount = "specs/users.yaml@:8081/users".parse().unwrap();
+    /// assert_eq!(mount.path.to_str(), Some("specs/users.yaml"));
+    /// assert_eq!(mount.prefix.as_deref(), Some("/users"));
+    /// assert_eq!(mount.port, Some(8081));
+    /// ```
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let Some((path, mount)) = value
+            .rsplit_once('@')
+            .filter(|(_, mount)| mount.starts_with(['/', ':']))
+        else {
+            return Ok(Self {
+                path: PathBuf::from(value),
+                prefix: None,
+                port: None,
+            });
+        };
+
+        let (port, prefix) = match mount.strip_prefix(':') {
+            Some(rest) => {
+                let (port, prefix) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+                let port = port
+                    .parse::<u16>()
+                    .map_err(|_| format!("Invalid port in {}", value))?;
+                (Some(port), prefix)
+            }
+            None => (None, mount),
+        };
+        Ok(Self {
+            path: PathBuf::from(path),
+            prefix: (!prefix.is_empty()).then(|| prefix.to_string()),
+            port,
+        })
+    }
+}
+
+/// A spec served by the server.
+pub struct MountedSpec {
+    /// The spec being served, swapped when it is reloaded.
+    pub spec: RwLock<Arc<Spec>>,
+    /// Where the spec is loaded from, or None if it cannot be reloaded.
+    pub source: Option<SpecSource>,
+    /// The port the spec is served on, or None for the port of the server.
+    pub port: Option<u16>,
+    /// The in-memory store of the stateful mode, or None to serve the
+    /// examples of the spec.
+    pub store: Option<Mutex<Store>>,
+}
+
+impl MountedSpec {
+    /// Mount a spec on the port of the server.
+    pub fn new(spec: Spec) -> Self {
+        Self {
+            spec: RwLock::new(Arc::new(spec)),
+            source: None,
+            port: None,
+            store: None,
+        }
+    }
+
+    /// Get the spec being served. The spec is kept for as long as it is
+    /// held, even if it is reloaded meanwhile.
+    pub fn spec(&self) -> Arc<Spec> {
+        self.spec.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Name the spec in messages: its file, or the title of the spec.
+    pub fn name(&self) -> String {
+        match &self.source {
+            Some(source) => source.path.display().to_string(),
+            None => self
+                .spec()
+                .document()
+                .pointer("/info/title")
+                .and_then(|title| title.as_str())
+                .unwrap_or("spec")
+                .to_string(),
+        }
+    }
+
+    /// Load the spec again from its source, and swap it in. The store of
+    /// the stateful mode is seeded again from the new spec.
+    /// # Errors
+    /// Returns an error, keeping the current spec, if the spec has no
+    /// source or fails to load.
+    pub fn reload(&self) -> Result<(), String> {
+        let source = self
+            .source
+            .as_ref()
+            .ok_or("The spec has no source to reload")?;
+        let spec = match source.load() {
+            Ok(spec) => spec,
+            Err(error) => {
+                let error = format!("{}, keeping the current spec", error);
+                println!("Failed to reload {}: {}", source.path.display(), error);
+                return Err(error);
+            }
+        };
+
+        if let Some(store) = &self.store {
+            *store.lock().unwrap_or_else(|e| e.into_inner()) = Store::new(spec.collections());
+        }
+        *self.spec.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(spec);
+        println!("Reloaded {}", source.path.display());
+        Ok(())
+    }
+}
+
+/// A path of a spec that no request can reach, because another spec
+/// handles its requests first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    /// The path as mounted, e.g. `/users/{userId}`.
+    pub path: String,
+    /// The spec declaring the path.
+    pub spec: String,
+    /// The spec handling the requests to the path.
+    pub shadowed_by: String,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} is shadowed by {}",
+            self.path, self.spec, self.shadowed_by
+        )
+    }
+}
+
+/// Order the specs served on a port the way requests are dispatched to
+/// them: longest prefix first, then in the order they were given.
+/// # Returns
+/// The position of each spec along with the spec being served
+pub fn dispatch_order(specs: &[&MountedSpec]) -> Vec<(usize, Arc<Spec>)> {
+    let mut order: Vec<(usize, Arc<Spec>)> = specs
+        .iter()
+        .map(|mounted| mounted.spec())
+        .enumerate()
+        .collect();
+    order.sort_by_key(|(_, spec)| Reverse(spec.prefix().len()));
+    order
+}
+
+/// Find the paths of the specs shadowed by another spec served on the
+/// same port.
+pub fn find_conflicts(specs: &[MountedSpec]) -> Vec<Conflict> {
+    let mut ports: Vec<Option<u16>> = specs.iter().map(|mounted| mounted.port).collect();
+    ports.sort();
+    ports.dedup();
+
+    let mut conflicts = Vec::new();
+    for port in ports {
+        let served: Vec<&MountedSpec> = specs.iter().filter(|m| m.port == port).collect();
+        let order = dispatch_order(&served);
+        for (position, spec) in &order {
+            for (key, _) in spec.paths() {
+                let path = format!("{}{}", spec.prefix(), key);
+                let handler = order
+                    .iter()
+                    .find(|(_, other)| other.match_path(&path).is_some())
+                    .map(|(handler, _)| *handler);
+                match handler {
+                    Some(handler) if handler != *position => conflicts.push(Conflict {
+                        path,
+                        spec: served[*position].name(),
+                        shadowed_by: served[handler].name(),
+                    }),
+                    _ => {}
+                }
+            }
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mount(path: &str, prefix: Option<&str>, port: Option<u16>) -> MountedSpec {
+        let source = SpecSource {
+            prefix: prefix.map(str::to_string),
+            ..SpecSource::new(path)
+        };
+        MountedSpec {
+            spec: RwLock::new(Arc::new(source.load().unwrap())),
+            source: Some(source),
+            port,
+            store: None,
+        }
+    }
+
+    #[test]
+    fn test_spec_mount_parse() {
+        let parse = |value: &str| value.parse::<SpecMount>();
+        assert_eq!(
+            parse("specs/users.yaml").unwrap(),
+            SpecMount {
+                path: PathBuf::from("specs/users.yaml"),
+                prefix: None,
+                port: None,
+            }
+        );
+        assert_eq!(
+            parse("users.yaml@/users").unwrap().prefix.as_deref(),
+            Some("/users")
+        );
+        let mount = parse("users.yaml@:8081").unwrap();
+        assert_eq!((mount.prefix, mount.port), (None, Some(8081)));
+        // An `@` not followed by a mount is part of the path
+        assert_eq!(
+            parse("specs@2/users.yaml").unwrap().path.to_str(),
+            Some("specs@2/users.yaml")
+        );
+        assert!(parse("users.yaml@:http").is_err());
+    }
+
+    #[test]
+    fn test_find_conflicts() {
+        let specs = vec![
+            mount("tests/testdata/petstore.yaml", None, None),
+            mount("tests/testdata/routes.yaml", None, None),
+            mount("tests/testdata/generated.yaml", Some("/pets"), None),
+            mount("tests/testdata/validation.yaml", None, Some(8081)),
+        ];
+        let conflicts: Vec<String> = find_conflicts(&specs)
+            .iter()
+            .map(|conflict| conflict.to_string())
+            .collect();
+        assert_eq!(
+            conflicts,
+            vec![
+                "/pets/mine of tests/testdata/routes.yaml is shadowed by tests/testdata/petstore.yaml",
+                "/pets/{petId} of tests/testdata/routes.yaml is shadowed by tests/testdata/petstore.yaml",
+            ]
+        );
+    }
+}
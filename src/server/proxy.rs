@@ -0,0 +1,255 @@
+//! The proxy mode of the mock server.
+//!
+//! Requests that no named example matches are forwarded to an upstream
+//! server, and its responses are returned as they are. When recording,
+//! the responses are also written back as named examples in a copy of the
+//! OpenAPI document, see `openapi::recorder`.
+use std::sync::Mutex;
+
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse};
+use serde_json::json;
+
+use crate::openapi::{
+    recorder::{Recorder, Recording},
+    router::RouteMatch,
+    spec::Spec,
+};
+
+/// Headers describing a single connection, which are not forwarded.
+const HOP_HEADERS: [&str; 7] = [
+    "connection",
+    "host",
+    "keep-alive",
+    "transfer-encoding",
+    "upgrade",
+    "content-length",
+    // The body is recorded as received, so it is requested uncompressed
+    "accept-encoding",
+];
+
+/// Forwards requests to an upstream server.
+#[derive(Debug)]
+pub struct Proxy {
+    upstream: String,
+    client: reqwest::Client,
+    recorder: Option<Mutex<Recorder>>,
+}
+
+impl Proxy {
+    /// Create a proxy to an upstream server.
+    /// # Arguments
+    /// * `upstream` - The base URL of the upstream, e.g. `http://localhost:3000`.
+    ///   The request path is appended to it.
+    pub fn new(upstream: &str) -> Self {
+        Self {
+            upstream: upstream.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            recorder: None,
+        }
+    }
+
+    /// Record the responses of the upstream.
+    pub fn recording(self, recorder: Recorder) -> Self {
+        Self {
+            recorder: Some(Mutex::new(recorder)),
+            ..self
+        }
+    }
+
+    /// Copy a reloaded spec again in the recorded document, if recording.
+    pub fn refresh(&self, spec: &Spec) {
+        if let Some(recorder) = &self.recorder {
+            recorder
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .refresh(spec);
+        }
+    }
+
+    /// Forward a request to the upstream, and record its response.
+    /// # Arguments
+    /// * `req` - The request
+    /// * `body` - The body of the request
+    /// * `route` - The path of the spec matching the request, if any. The
+    ///   responses of requests matching no path are recorded under a new
+    ///   path, named after the request path.
+    /// # Returns
+    /// The response of the upstream, or `502 Bad Gateway` if the upstream
+    /// cannot be reached.
+    pub async fn forward(
+        &self,
+        req: &HttpRequest,
+        body: &[u8],
+        route: Option<&RouteMatch<'_>>,
+    ) -> HttpResponse {
+        let url = match req.uri().path_and_query() {
+            Some(path) => format!("{}{}", self.upstream, path),
+            None => self.upstream.clone(),
+        };
+        let method = reqwest::Method::from_bytes(req.method().as_str().as_bytes())
+            .unwrap_or(reqwest::Method::GET);
+        let mut request = self.client.request(method, &url).body(body.to_vec());
+        for (name, value) in req.headers() {
+            if !is_hop_header(name.as_str()) {
+                request = request.header(name.as_str(), value.as_bytes());
+            }
+        }
+
+        let upstream = match request.send().await {
+            Ok(upstream) => upstream,
+            Err(error) => {
+                println!("Failed to forward {} {}: {}", req.method(), url, error);
+                return HttpResponse::BadGateway()
+                    .json(json!({ "message": format!("Failed to reach {}", self.upstream) }));
+            }
+        };
+
+        let status = upstream.status().as_u16();
+        let headers: Vec<(String, Vec<u8>)> = upstream
+            .headers()
+            .iter()
+            .filter(|(name, _)| !is_hop_header(name.as_str()))
+            .map(|(name, value)| (name.to_string(), value.as_bytes().to_vec()))
+            .collect();
+        let body = match upstream.bytes().await {
+            Ok(body) => body,
+            Err(error) => {
+                println!(
+                    "Failed to read the response to {} {}: {}",
+                    req.method(),
+                    url,
+                    error
+                );
+                return HttpResponse::BadGateway().finish();
+            }
+        };
+
+        if let Some(recorder) = &self.recorder {
+            let media_type = headers
+                .iter()
+                .find(|(name, _)| name == "content-type")
+                .and_then(|(_, value)| std::str::from_utf8(value).ok())
+                .and_then(|value| value.split(';').next())
+                .map(|value| value.trim().to_lowercase());
+            let (key, path) = match route {
+                Some(route) => (route.key, route.path.as_str()),
+                None => (req.path(), req.path()),
+            };
+
+            let (name, snapshot) = {
+                let mut recorder = recorder.lock().unwrap_or_else(|e| e.into_inner());
+                let name = recorder.example_name(req, path);
+                let method = req.method().as_str().to_lowercase();
+                let snapshot = recorder.record(&Recording {
+                    key,
+                    method: &method,
+                    name: &name,
+                    status,
+                    media_type: media_type.as_deref(),
+                    body: &body,
+                });
+                (name, snapshot)
+            };
+            // The document is written once the recorder is released, off
+            // the async runtime
+            let written = web::block(move || snapshot.write()).await;
+            if let Err(error) = written.unwrap_or_else(|e| Err(std::io::Error::other(e))) {
+                println!("Failed to record the response to {}: {}", name, error);
+            }
+        }
+
+        let mut response =
+            HttpResponse::build(StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY));
+        for (name, value) in headers {
+            response.append_header((name, value));
+        }
+        response.body(body)
+    }
+}
+
+fn is_hop_header(name: &str) -> bool {
+    HOP_HEADERS
+        .iter()
+        .any(|header| header.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::{document::load_document, spec::Spec};
+    use crate::server::{get_scope, AppState};
+    use actix_web::{
+        test::{call_service, init_service, read_body, TestRequest},
+        web, App, HttpServer,
+    };
+
+    /// Start an upstream stub answering every request with the request path.
+    fn start_upstream() -> String {
+        let server = HttpServer::new(|| {
+            App::new().default_service(web::to(|req: HttpRequest| async move {
+                match req.path() {
+                    "/pets/404" => HttpResponse::NotFound().json(json!({"code": 404})),
+                    path => HttpResponse::Ok().json(json!({ "path": path })),
+                }
+            }))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .expect("failed to bind the upstream");
+        let address = server.addrs()[0];
+        actix_rt::spawn(server.run());
+        format!("http://{}", address)
+    }
+
+    #[actix_rt::test]
+    async fn test_proxy_record() {
+        let upstream = start_upstream();
+        let path = std::env::temp_dir().join("openapi-mocker-proxy.yaml");
+        let document = load_document("tests/testdata/petstore.yaml").unwrap();
+        let recorder = Recorder::new(&path, document, Vec::new());
+
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState {
+            proxy: Some(Proxy::new(&upstream).recording(recorder)),
+            ..AppState::new(spec)
+        });
+        let app = init_service(App::new().app_data(data.clone()).service(get_scope())).await;
+
+        // Served from the named example
+        let req = TestRequest::get().uri("/pets/2").to_request();
+        let body = read_body(call_service(&app, req).await).await;
+        assert_eq!(body, r#"{"id":2,"name":"kitty","tag":"cat"}"#);
+
+        // Forwarded to the upstream, and recorded
+        let req = TestRequest::get().uri("/pets/7").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(read_body(resp).await, r#"{"path":"/pets/7"}"#);
+
+        let req = TestRequest::get().uri("/pets/404").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 404);
+
+        let recorded = Spec::from_path(path.to_str().unwrap()).unwrap();
+        let req = TestRequest::with_uri("/pets/7").to_http_request();
+        let example = recorded.get_example(&req).unwrap();
+        assert_eq!(example.value.unwrap()["path"], "/pets/7");
+
+        let req = TestRequest::with_uri("/pets/404").to_http_request();
+        assert_eq!(recorded.get_example(&req).unwrap().status, 404);
+    }
+
+    #[actix_rt::test]
+    async fn test_proxy_unreachable() {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").expect("failed to load spec");
+        let data = web::Data::new(AppState {
+            proxy: Some(Proxy::new("http://127.0.0.1:1")),
+            ..AppState::new(spec)
+        });
+        let app = init_service(App::new().app_data(data.clone()).service(get_scope())).await;
+
+        let req = TestRequest::get().uri("/pets/7").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), 502);
+    }
+}
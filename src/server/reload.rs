@@ -0,0 +1,185 @@
+//! Hot reload of the spec files.
+//!
+//! A spec is reloaded when its file changes, on `SIGHUP`, or through the
+//! admin API. The new spec is swapped in atomically: requests in flight
+//! keep the spec they started with, and the old spec keeps being served
+//! if the new one fails to load.
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use actix_web::web;
+
+use super::AppState;
+use crate::openapi::spec::{Spec, SpecResult};
+
+/// How often the spec files are checked for changes.
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where the spec is loaded from, to load it again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecSource {
+    pub path: PathBuf,
+    /// The base path overriding the paths of the spec servers.
+    pub base_path: Option<String>,
+    /// The prefix the spec is mounted under.
+    pub prefix: Option<String>,
+}
+
+impl SpecSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            base_path: None,
+            prefix: None,
+        }
+    }
+
+    /// Load the spec.
+    pub fn load(&self) -> SpecResult<Spec> {
+        let path = self.path.to_str().ok_or("Invalid spec path")?;
+        let spec = Spec::from_path(path)?;
+        let spec = match &self.base_path {
+            Some(base_path) => spec.with_base_path(base_path),
+            None => spec,
+        };
+        Ok(match &self.prefix {
+            Some(prefix) => spec.with_prefix(prefix),
+            None => spec,
+        })
+    }
+}
+
+/// Get the modification times of files, None for a missing file.
+fn modified(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    let modified = |file: &Path| std::fs::metadata(file).ok()?.modified().ok();
+    files.iter().map(|file| modified(file)).collect()
+}
+
+/// Watch the spec files, reloading a spec when its file, or a file it
+/// references, is modified. The files are polled, which also works for
+/// files mounted in containers.
+pub fn watch(data: web::Data<AppState>, interval: Duration) {
+    for index in 0..data.specs.len() {
+        let Some(source) = data.specs[index].source.clone() else {
+            continue;
+        };
+        let data = data.clone();
+        actix_rt::spawn(async move {
+            // The files of the spec being served, which a reload may change
+            let files = || data.specs[index].spec().files().to_vec();
+            let mut watched = files();
+            let mut last = modified(&watched);
+            let mut interval = actix_rt::time::interval(interval);
+            loop {
+                interval.tick().await;
+                let current = modified(&watched);
+                if current.iter().all(Option::is_some) && current != last {
+                    println!("Spec file changed, reloading {}", source.path.display());
+                    let _ = data.reload_spec(index);
+                    watched = files();
+                    last = modified(&watched);
+                }
+            }
+        });
+    }
+}
+
+/// Reload the spec when the process receives `SIGHUP`.
+#[cfg(unix)]
+pub fn reload_on_hangup(data: web::Data<AppState>) {
+    use actix_rt::signal::unix::{signal, SignalKind};
+
+    actix_rt::spawn(async move {
+        let Ok(mut hangup) = signal(SignalKind::hangup()) else {
+            println!("Failed to listen to SIGHUP, the spec will not reload on SIGHUP");
+            return;
+        };
+        while hangup.recv().await.is_some() {
+            println!("Received SIGHUP, reloading the spec");
+            let _ = data.reload();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::mount::MountedSpec;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_reload() {
+        let path = std::env::temp_dir().join("openapi-mocker-reload.yaml");
+        std::fs::copy("tests/testdata/petstore.yaml", &path).unwrap();
+        let source = SpecSource::new(&path);
+        let state = AppState::with_specs(vec![MountedSpec {
+            source: Some(source.clone()),
+            ..MountedSpec::new(source.load().unwrap())
+        }]);
+        let old = state.specs[0].spec();
+
+        std::fs::copy("tests/testdata/routes.yaml", &path).unwrap();
+        state.reload().unwrap();
+        assert!(state.specs[0].spec().match_path("/pets/mine").is_some());
+        // The spec held by a request in flight is left untouched
+        assert!(old.match_path("/pets").is_some());
+
+        std::fs::write(&path, "openapi: [").unwrap();
+        assert!(state.reload().is_err());
+        assert!(state.specs[0].spec().match_path("/pets/mine").is_some());
+    }
+
+    #[actix_rt::test]
+    async fn test_watch_referenced_file() {
+        let dir = std::env::temp_dir().join("openapi-mocker-watch");
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths = r#"{"$ref": "./paths.yaml#/pets"}"#;
+        let root = format!(
+            r#"{{"openapi": "3.0.0", "info": {{"title": "t", "version": "1"}}, "paths": {{"/pets": {}}}}}"#,
+            paths
+        );
+        std::fs::write(dir.join("openapi.yaml"), root).unwrap();
+        let pets = |description: &str| {
+            format!(
+                r#"{{"pets": {{"get": {{"responses": {{"200": {{"description": "{}"}}}}}}}}}}"#,
+                description
+            )
+        };
+        std::fs::write(dir.join("paths.yaml"), pets("old")).unwrap();
+
+        let source = SpecSource::new(dir.join("openapi.yaml"));
+        let data = web::Data::new(AppState::with_specs(vec![MountedSpec {
+            source: Some(source.clone()),
+            ..MountedSpec::new(source.load().unwrap())
+        }]));
+        assert_eq!(data.specs[0].spec().files().len(), 2);
+        watch(data.clone(), Duration::from_millis(10));
+        actix_rt::time::sleep(Duration::from_millis(50)).await;
+
+        let file = std::fs::File::options()
+            .write(true)
+            .truncate(true)
+            .open(dir.join("paths.yaml"))
+            .unwrap();
+        std::io::Write::write_all(&mut &file, pets("new").as_bytes()).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        actix_rt::time::sleep(Duration::from_millis(200)).await;
+
+        let spec = data.specs[0].spec();
+        let response = &spec.document()["paths"]["/pets"]["get"]["responses"]["200"];
+        assert_eq!(response["description"], "new");
+    }
+
+    #[test]
+    fn test_reload_base_path() {
+        let source = SpecSource {
+            path: PathBuf::from("tests/testdata/petstore.yaml"),
+            base_path: Some("/api".to_string()),
+            prefix: Some("/store".to_string()),
+        };
+        let spec = source.load().unwrap();
+        let req = TestRequest::with_uri("/store/api/pets").to_http_request();
+        assert!(spec.get_example(&req).is_ok());
+    }
+}
@@ -0,0 +1,365 @@
+//! The in-memory store of the stateful mode.
+//!
+//! Each collection of the spec keeps its items in memory, so that a pet
+//! created with `POST /pets` is listed by `GET /pets` and returned by
+//! `GET /pets/{petId}` until it is deleted. Nested collections, such as
+//! `/owners/{ownerId}/pets`, keep separate items for each parent path.
+use std::collections::HashMap;
+
+use actix_web::http::{Method, StatusCode};
+use serde_json::{json, Map, Value};
+
+use crate::openapi::{collection::Collection, router::RouteMatch};
+
+/// The in-memory store of the collections of a spec.
+#[derive(Debug, Default)]
+pub struct Store {
+    collections: Vec<CollectionState>,
+}
+
+#[derive(Debug)]
+struct CollectionState {
+    collection: Collection,
+    /// The items by collection path, e.g. `/owners/1/pets`, seeded on
+    /// first access.
+    items: HashMap<String, Vec<Value>>,
+}
+
+/// A response of the store.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoreResponse {
+    pub status: StatusCode,
+    pub value: Option<Value>,
+}
+
+impl StoreResponse {
+    fn new(status: StatusCode, value: Value) -> Self {
+        Self {
+            status,
+            value: Some(value),
+        }
+    }
+
+    fn error(status: StatusCode, message: &str) -> Self {
+        Self::new(status, json!({ "message": message }))
+    }
+}
+
+impl Store {
+    /// Create a store for collections, seeded with their examples.
+    pub fn new(collections: Vec<Collection>) -> Self {
+        let collections = collections
+            .into_iter()
+            .map(|collection| CollectionState {
+                collection,
+                items: HashMap::new(),
+            })
+            .collect();
+        Self { collections }
+    }
+
+    /// Reset the collections to the items they are seeded with.
+    pub fn reset(&mut self) {
+        for state in &mut self.collections {
+            state.items.clear();
+        }
+    }
+
+    /// Handle a request to a collection.
+    /// # Arguments
+    /// * `route` - The path matching the request
+    /// * `method` - The method of the request
+    /// * `body` - The body of the request
+    /// # Returns
+    /// The response of the store, or None if the request does not target
+    /// a collection, or uses a method the spec does not declare for it.
+    ///
+    /// - `GET /pets` lists the items.
+    /// - `POST /pets` creates an item, generating its identifier if missing.
+    /// - `GET /pets/{petId}` reads an item.
+    /// - `PUT /pets/{petId}` replaces an item.
+    /// - `PATCH /pets/{petId}` merges the fields of the body into an item.
+    /// - `DELETE /pets/{petId}` deletes an item.
+    /// # Example
+    /// ```rust
+    /// use actix_web::http::{Method, StatusCode};
+    /// use openapi_mocker::{openapi::spec::Spec, server::store::Store};
+    /// let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+    /// let mut store = Store::new(spec.collections());
+    ///
+    /// let route = spec.match_path("/pets").unwrap();
+    /// let created = store.handle(&route, &Method::POST, br#"{"name": "rex"}"#).unwrap();
+    /// assert_eq!(created.status, StatusCode::CREATED);
+    ///
+    /// let id = created.value.unwrap()["id"].to_string();
+    /// let route = spec.match_path(&format!("/pets/{}", id)).unwrap();
+    /// let found = store.handle(&route, &Method::GET, &[]).unwrap();
+    /// assert_eq!(found.value.unwrap()["name"], "rex");
+    /// ```
+    pub fn handle(
+        &mut self,
+        route: &RouteMatch,
+        method: &Method,
+        body: &[u8],
+    ) -> Option<StoreResponse> {
+        let state = self.collections.iter_mut().find(|state| {
+            state.collection.path == route.key || state.collection.item_path == route.key
+        })?;
+        let collection = &state.collection;
+
+        if collection.path == route.key {
+            let status = declared_status(&collection.methods, method)?;
+            let path = route.path.trim_end_matches('/').to_string();
+            let items = state
+                .items
+                .entry(path)
+                .or_insert_with(|| collection.seed.clone());
+            return Some(match *method {
+                Method::GET => StoreResponse::new(status, Value::Array(items.clone())),
+                Method::POST => create(collection, items, status, body),
+                _ => return None,
+            });
+        }
+
+        let status = declared_status(&collection.item_methods, method)?;
+        let id = route.param(&collection.param)?;
+        let path = route.path.trim_end_matches('/');
+        let path = path
+            .rsplit_once('/')
+            .map_or("", |(path, _)| path)
+            .to_string();
+        let items = state
+            .items
+            .entry(path)
+            .or_insert_with(|| collection.seed.clone());
+        let Some(position) = items
+            .iter()
+            .position(|item| item.get(&collection.id_field).is_some_and(|v| id_eq(v, id)))
+        else {
+            return Some(StoreResponse::error(
+                StatusCode::NOT_FOUND,
+                "Item not found",
+            ));
+        };
+
+        Some(match *method {
+            Method::GET => StoreResponse::new(status, items[position].clone()),
+            Method::PUT | Method::PATCH => {
+                let Some(Value::Object(fields)) = parse_body(body) else {
+                    return Some(invalid_body());
+                };
+                let item = &mut items[position];
+                let id = item[&collection.id_field].clone();
+                match *method {
+                    Method::PUT => *item = Value::Object(fields),
+                    _ => merge(item, fields),
+                }
+                item[&collection.id_field] = id;
+                StoreResponse::new(status, item.clone())
+            }
+            Method::DELETE => {
+                let item = items.remove(position);
+                StoreResponse {
+                    status,
+                    value: (status != StatusCode::NO_CONTENT).then_some(item),
+                }
+            }
+            _ => return None,
+        })
+    }
+}
+
+/// Get the success status declared for a method, or None if the method
+/// is not declared.
+fn declared_status(methods: &[(Method, StatusCode)], method: &Method) -> Option<StatusCode> {
+    methods
+        .iter()
+        .find(|(declared, _)| declared == method)
+        .map(|(_, status)| *status)
+}
+
+/// Create an item from a request body, generating its identifier if the
+/// body does not set it.
+fn create(
+    collection: &Collection,
+    items: &mut Vec<Value>,
+    status: StatusCode,
+    body: &[u8],
+) -> StoreResponse {
+    let Some(Value::Object(mut fields)) = parse_body(body) else {
+        return invalid_body();
+    };
+    let id_field = &collection.id_field;
+    match fields.get(id_field) {
+        Some(id) if items.iter().any(|item| item.get(id_field) == Some(id)) => {
+            return StoreResponse::error(StatusCode::CONFLICT, "Item already exists");
+        }
+        Some(_) => {}
+        None => {
+            fields.insert(id_field.clone(), next_id(items, id_field));
+        }
+    }
+
+    let item = Value::Object(fields);
+    items.push(item.clone());
+    StoreResponse::new(status, item)
+}
+
+/// Generate the next identifier of a collection: one more than the
+/// greatest numeric identifier, as a string if the items use string
+/// identifiers.
+fn next_id(items: &[Value], id_field: &str) -> Value {
+    let next = items
+        .iter()
+        .filter_map(|item| item.get(id_field))
+        .filter_map(|id| match id {
+            Value::String(id) => id.parse::<u64>().ok(),
+            id => id.as_u64(),
+        })
+        .max()
+        .map_or(1, |max| max + 1);
+
+    match items
+        .iter()
+        .any(|item| item.get(id_field).is_some_and(Value::is_string))
+    {
+        true => Value::String(next.to_string()),
+        false => Value::from(next),
+    }
+}
+
+/// Compare an identifier with the value of a path parameter.
+fn id_eq(id: &Value, value: &str) -> bool {
+    match id {
+        Value::String(id) => id == value,
+        id => serde_json::from_str::<Value>(value).is_ok_and(|value| value == *id),
+    }
+}
+
+/// Merge fields into an item, removing the fields set to null.
+fn merge(item: &mut Value, fields: Map<String, Value>) {
+    let Some(item) = item.as_object_mut() else {
+        return;
+    };
+    for (name, value) in fields {
+        match value {
+            Value::Null => item.remove(&name),
+            value => item.insert(name, value),
+        };
+    }
+}
+
+fn parse_body(body: &[u8]) -> Option<Value> {
+    serde_json::from_slice(body).ok()
+}
+
+fn invalid_body() -> StoreResponse {
+    StoreResponse::error(StatusCode::BAD_REQUEST, "Expected a JSON object")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::spec::Spec;
+
+    fn load_store() -> (Spec, Store) {
+        let spec = Spec::from_path("tests/testdata/petstore.yaml").unwrap();
+        let store = Store::new(spec.collections());
+        (spec, store)
+    }
+
+    fn handle(
+        spec: &Spec,
+        store: &mut Store,
+        method: Method,
+        path: &str,
+        body: &str,
+    ) -> StoreResponse {
+        let route = spec.match_path(path).unwrap();
+        store.handle(&route, &method, body.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_store_seed() {
+        let (spec, mut store) = load_store();
+        let list = handle(&spec, &mut store, Method::GET, "/pets", "");
+        assert_eq!(list.status, StatusCode::OK);
+        assert_eq!(list.value.unwrap().as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_store_create() {
+        let (spec, mut store) = load_store();
+        let created = handle(
+            &spec,
+            &mut store,
+            Method::POST,
+            "/pets",
+            r#"{"name": "rex"}"#,
+        );
+        assert_eq!(created.status, StatusCode::CREATED);
+        assert_eq!(created.value.unwrap()["id"], 5);
+
+        let conflict = handle(&spec, &mut store, Method::POST, "/pets", r#"{"id": 5}"#);
+        assert_eq!(conflict.status, StatusCode::CONFLICT);
+
+        let invalid = handle(&spec, &mut store, Method::POST, "/pets", "[]");
+        assert_eq!(invalid.status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_store_update() {
+        let (spec, mut store) = load_store();
+        let body = r#"{"id": 9, "name": "rex"}"#;
+        let updated = handle(&spec, &mut store, Method::PUT, "/pets/1", body);
+        assert_eq!(updated.value.unwrap(), json!({"id": 1, "name": "rex"}));
+
+        let found = handle(&spec, &mut store, Method::GET, "/pets/1", "");
+        assert_eq!(found.value.unwrap()["name"], "rex");
+
+        let missing = handle(&spec, &mut store, Method::PUT, "/pets/9", body);
+        assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_store_delete() {
+        let (spec, mut store) = load_store();
+        let deleted = handle(&spec, &mut store, Method::DELETE, "/pets/2", "");
+        assert_eq!(deleted.status, StatusCode::NO_CONTENT);
+        assert_eq!(deleted.value, None);
+
+        let missing = handle(&spec, &mut store, Method::GET, "/pets/2", "");
+        assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_store_reset() {
+        let (spec, mut store) = load_store();
+        handle(&spec, &mut store, Method::DELETE, "/pets/2", "");
+        store.reset();
+        let found = handle(&spec, &mut store, Method::GET, "/pets/2", "");
+        assert_eq!(found.status, StatusCode::OK);
+    }
+
+    #[test]
+    fn test_store_undeclared_method() {
+        let (spec, mut store) = load_store();
+        let route = spec.match_path("/pets/1").unwrap();
+        assert_eq!(store.handle(&route, &Method::PATCH, b"{}"), None);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut item = json!({"id": 1, "name": "rex", "tag": "dog"});
+        let fields = json!({"name": "max", "tag": null});
+        merge(&mut item, fields.as_object().unwrap().clone());
+        assert_eq!(item, json!({"id": 1, "name": "max"}));
+    }
+
+    #[test]
+    fn test_next_id() {
+        assert_eq!(next_id(&[], "id"), json!(1));
+        assert_eq!(next_id(&[json!({"id": "7"})], "id"), json!("8"));
+        assert_eq!(next_id(&[json!({"id": "abc"})], "id"), json!("1"));
+    }
+}